@@ -0,0 +1,20 @@
+//! Records the version of `concordium-rust-sdk` the tool is built against.
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let mut lines = lock.lines();
+    let mut version = "unknown".to_string();
+    while let Some(line) = lines.next() {
+        if line.trim() == "name = \"concordium-rust-sdk\"" {
+            if let Some(v) = lines
+                .next()
+                .and_then(|l| l.trim().strip_prefix("version = \""))
+                .and_then(|l| l.strip_suffix('"'))
+            {
+                version = v.to_string();
+            }
+            break;
+        }
+    }
+    println!("cargo:rustc-env=CONCORDIUM_SDK_VERSION={}", version);
+}
@@ -0,0 +1,531 @@
+//! Loading account keys and spreading batch transactions over accounts.
+use crate::{
+    chain::{self, ChainParams},
+    output::Printer,
+};
+use anyhow::Context;
+use base64::{engine::general_purpose, Engine as _};
+use concordium_rust_sdk::{
+    common::types::Amount,
+    common::types::{CredentialIndex, KeyIndex, KeyPair},
+    id::types::{AccountAddress, AccountKeys, CredentialData, SignatureThreshold},
+    types::{Energy, Nonce, WalletAccount},
+    v2::{self, BlockIdentifier},
+};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Load the keys from each path. A directory contributes every `.json` file
+/// directly inside it, in file name order.
+pub fn load_keys(paths: &[PathBuf]) -> anyhow::Result<Vec<WalletAccount>> {
+    let mut keys = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut files = Vec::new();
+            for entry in std::fs::read_dir(path)
+                .with_context(|| format!("Could not read key directory {}.", path.display()))?
+            {
+                let file = entry?.path();
+                if file.is_file() && file.extension().is_some_and(|e| e == "json") {
+                    files.push(file);
+                }
+            }
+            files.sort();
+            for file in files {
+                keys.extend(load_key_file(&file)?);
+            }
+        } else {
+            keys.extend(load_key_file(path)?);
+        }
+    }
+    Ok(keys)
+}
+
+/// The key file formats [`parse_key_file`] reads.
+const SUPPORTED_FORMATS: &str = "a browser wallet export (type \
+                                 concordium-browser-wallet-account), a decrypted mobile wallet \
+                                 export (type concordium-mobile-wallet-data) or a genesis \
+                                 account with accountKeys and address";
+
+fn load_key_file(path: &Path) -> anyhow::Result<Vec<WalletAccount>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read the keys file {}.", path.display()))?;
+    let value: Value = serde_json::from_str(&text)
+        .with_context(|| format!("The keys file {} is not valid JSON.", path.display()))?;
+    parse_key_file(value).with_context(|| {
+        format!(
+            "Could not read the keys file {}. Supported are {}.",
+            path.display(),
+            SUPPORTED_FORMATS
+        )
+    })
+}
+
+/// The accounts in a key file, detecting its format from its `type` field. A
+/// mobile wallet export contributes every account of every identity in it.
+/// Files of other types are left to the SDK's own key file parsing.
+fn parse_key_file(value: Value) -> anyhow::Result<Vec<WalletAccount>> {
+    anyhow::ensure!(
+        value.get("cipherText").is_none(),
+        "The wallet export is encrypted. Decrypt it with the export password first."
+    );
+    let format = value.get("type").and_then(Value::as_str).map(String::from);
+    match format.as_deref() {
+        None => Ok(vec![WalletAccount::from_genesis_account_value(value)
+            .context("The file has no type and is not a genesis account.")?]),
+        Some("concordium-browser-wallet-account") => {
+            Ok(vec![
+                WalletAccount::from_json_value(value).context("Invalid browser wallet export.")?
+            ])
+        }
+        Some("concordium-mobile-wallet-data") => {
+            let accounts: Vec<WalletAccount> = value["value"]["identities"]
+                .as_array()
+                .context("The mobile wallet export has no identities.")?
+                .iter()
+                .filter_map(|identity| identity["accounts"].as_array())
+                .flatten()
+                .map(|account| WalletAccount::from_genesis_account_value(account.clone()))
+                .collect::<Result<_, _>>()
+                .context("Invalid account in the mobile wallet export.")?;
+            anyhow::ensure!(
+                !accounts.is_empty(),
+                "The mobile wallet export has no accounts."
+            );
+            Ok(accounts)
+        }
+        Some(other) => Ok(vec![WalletAccount::from_json_value(value)
+            .with_context(|| format!("Unknown key file type {}.", other))?]),
+    }
+}
+
+/// The account `address` with the single Ed25519 signing key `key`, given in
+/// hex or base64, as key 0 of credential 0.
+pub fn signing_key_account(key: &str, address: AccountAddress) -> anyhow::Result<WalletAccount> {
+    let key = key.trim();
+    let bytes = hex::decode(key)
+        .or_else(|_| general_purpose::STANDARD.decode(key))
+        .context("The signing key is neither hex nor base64.")?;
+    anyhow::ensure!(
+        bytes.len() == ed25519_dalek::SECRET_KEY_LENGTH,
+        "The signing key has {} bytes, but an Ed25519 key has {}.",
+        bytes.len(),
+        ed25519_dalek::SECRET_KEY_LENGTH
+    );
+    let secret = ed25519_dalek::SecretKey::from_bytes(&bytes)?;
+    let public = ed25519_dalek::PublicKey::from(&secret);
+    let credential = CredentialData {
+        keys: BTreeMap::from([(KeyIndex(0), KeyPair { secret, public })]),
+        threshold: SignatureThreshold(1),
+    };
+    Ok(WalletAccount {
+        address,
+        keys: AccountKeys {
+            keys: BTreeMap::from([(CredentialIndex { index: 0 }, credential)]),
+            threshold: SignatureThreshold(1),
+        },
+    })
+}
+
+/// A signing key given as `<credential index>:<key index>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySelector {
+    pub credential: u8,
+    pub key: u8,
+}
+
+impl FromStr for KeySelector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (credential, key) = s
+            .split_once(':')
+            .context("A key index must have the form <credential>:<key>, e.g. 0:1.")?;
+        Ok(KeySelector {
+            credential: credential
+                .trim()
+                .parse()
+                .context("Invalid credential index.")?,
+            key: key.trim().parse().context("Invalid key index.")?,
+        })
+    }
+}
+
+/// Keep only the selected keys of the account, checking that they still meet
+/// the signature threshold of every credential used and the account threshold.
+/// Without a selection all keys are kept.
+pub fn select_keys(
+    account: WalletAccount,
+    selection: &[KeySelector],
+) -> anyhow::Result<WalletAccount> {
+    if selection.is_empty() {
+        return Ok(account);
+    }
+    let address = account.address;
+    let mut keys = account.keys;
+    for selector in selection {
+        let present = keys
+            .keys
+            .get(&CredentialIndex {
+                index: selector.credential,
+            })
+            .is_some_and(|c| c.keys.contains_key(&KeyIndex(selector.key)));
+        anyhow::ensure!(
+            present,
+            "The keys of account {} have no key {}:{}.",
+            address,
+            selector.credential,
+            selector.key
+        );
+    }
+    let mut credentials = BTreeMap::new();
+    for (index, data) in std::mem::take(&mut keys.keys) {
+        let selected: BTreeMap<_, _> = data
+            .keys
+            .into_iter()
+            .filter(|(key, _)| {
+                selection.contains(&KeySelector {
+                    credential: index.index,
+                    key: key.0,
+                })
+            })
+            .collect();
+        if selected.is_empty() {
+            continue;
+        }
+        anyhow::ensure!(
+            selected.len() >= usize::from(data.threshold.0),
+            "Credential {} of account {} needs {} signature(s), but only {} key(s) are selected.",
+            index.index,
+            address,
+            data.threshold.0,
+            selected.len()
+        );
+        credentials.insert(
+            index,
+            CredentialData {
+                keys: selected,
+                threshold: data.threshold,
+            },
+        );
+    }
+    anyhow::ensure!(
+        credentials.len() >= usize::from(keys.threshold.0),
+        "Account {} needs signatures from {} credential(s), but keys of only {} are selected.",
+        address,
+        keys.threshold.0,
+        credentials.len()
+    );
+    keys.keys = credentials;
+    Ok(WalletAccount { address, keys })
+}
+
+/// An account taking part in a batch, with its locally tracked nonce and
+/// balance.
+#[derive(Clone)]
+pub struct PoolAccount<'a> {
+    pub keys: &'a WalletAccount,
+    pub nonce: Nonce,
+    pub submitted: usize,
+    /// The available balance, less what the transactions submitted so far may
+    /// spend.
+    pub balance: Amount,
+}
+
+impl PoolAccount<'_> {
+    /// Fail if a transaction sending `amount` and needing `energy` in total
+    /// exceeds the block energy limit or what the account has left. Returns
+    /// the most the transaction may spend.
+    pub fn check_funds(
+        &self,
+        params: &ChainParams,
+        amount: Amount,
+        energy: Energy,
+    ) -> anyhow::Result<Amount> {
+        params.check_energy_limit(energy)?;
+        params
+            .check_balance(self.balance, amount, energy)
+            .with_context(|| {
+                format!(
+                    "Account {} cannot pay for the transaction.",
+                    self.keys.address
+                )
+            })?;
+        Ok(amount + params.energy_cost(energy))
+    }
+
+    /// Record that a transaction with the current nonce was accepted, which
+    /// may spend up to `cost`.
+    pub fn record_submission(&mut self, cost: Amount) {
+        self.nonce.next_mut();
+        self.submitted += 1;
+        self.balance = self.balance.checked_sub(cost).unwrap_or_else(Amount::zero);
+    }
+}
+
+/// Accounts that batch operations use in turn, so that no single account's
+/// nonce sequence limits the throughput.
+#[derive(Clone)]
+pub struct AccountPool<'a> {
+    accounts: Vec<PoolAccount<'a>>,
+    next: usize,
+}
+
+impl<'a> AccountPool<'a> {
+    /// Query the next nonce and the available balance of every account.
+    pub async fn new(client: &mut v2::Client, keys: &'a [WalletAccount]) -> anyhow::Result<Self> {
+        anyhow::ensure!(!keys.is_empty(), "At least one --account is required.");
+        let mut accounts = Vec::with_capacity(keys.len());
+        for keys in keys {
+            let nonce = client
+                .get_next_account_sequence_number(&keys.address)
+                .await?
+                .nonce;
+            let info = client
+                .get_account_info(&keys.address.into(), BlockIdentifier::LastFinal)
+                .await?
+                .response;
+            accounts.push(PoolAccount {
+                keys,
+                nonce,
+                submitted: 0,
+                balance: chain::available_balance(&info),
+            });
+        }
+        Ok(Self { accounts, next: 0 })
+    }
+
+    /// The account whose turn it is.
+    pub fn next_account(&mut self) -> &mut PoolAccount<'a> {
+        let index = self.next;
+        self.next = (self.next + 1) % self.accounts.len();
+        &mut self.accounts[index]
+    }
+
+    /// The account with the given address if it is in the pool, otherwise the
+    /// account whose turn it is.
+    pub fn account_for(&mut self, address: Option<&AccountAddress>) -> &mut PoolAccount<'a> {
+        match address.and_then(|a| self.accounts.iter().position(|acc| acc.keys.address == *a)) {
+            Some(index) => &mut self.accounts[index],
+            None => self.next_account(),
+        }
+    }
+
+    /// Print how many transactions each account submitted.
+    pub fn print_report(&self, printer: &Printer) {
+        if self.accounts.len() < 2 {
+            return;
+        }
+        for account in &self.accounts {
+            printer.progress(format_args!(
+                "Account {}: {} transaction(s) submitted.",
+                account.keys.address, account.submitted
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ADDRESS: &str = "3kBx2h5Y2veb4hZgAJWPrr8RyQESKm5TjzF3ti1QQ4VSYLwK1G";
+
+    /// An account with the given `(keys, threshold)` per credential, where
+    /// each key is derived from its byte.
+    fn account(credentials: &[(&[u8], u8)], threshold: u8) -> WalletAccount {
+        let keys = credentials
+            .iter()
+            .enumerate()
+            .map(|(index, (keys, threshold))| {
+                let keys = keys
+                    .iter()
+                    .enumerate()
+                    .map(|(key, byte)| {
+                        let secret = ed25519_dalek::SecretKey::from_bytes(&[*byte; 32]).unwrap();
+                        let public = ed25519_dalek::PublicKey::from(&secret);
+                        (KeyIndex(key as u8), KeyPair { secret, public })
+                    })
+                    .collect();
+                let credential = CredentialData {
+                    keys,
+                    threshold: SignatureThreshold(*threshold),
+                };
+                (CredentialIndex { index: index as u8 }, credential)
+            })
+            .collect();
+        WalletAccount {
+            address: ADDRESS.parse().unwrap(),
+            keys: AccountKeys {
+                keys,
+                threshold: SignatureThreshold(threshold),
+            },
+        }
+    }
+
+    fn selected(account: &WalletAccount) -> Vec<(u8, u8)> {
+        account
+            .keys
+            .keys
+            .iter()
+            .flat_map(|(credential, data)| data.keys.keys().map(|key| (credential.index, key.0)))
+            .collect()
+    }
+
+    fn selectors(s: &[&str]) -> Vec<KeySelector> {
+        s.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn key_selector_parsing() {
+        assert_eq!(
+            " 1 : 2".parse::<KeySelector>().unwrap(),
+            KeySelector {
+                credential: 1,
+                key: 2
+            }
+        );
+        assert!("1".parse::<KeySelector>().is_err());
+        assert!("a:1".parse::<KeySelector>().is_err());
+        assert!("0:256".parse::<KeySelector>().is_err());
+    }
+
+    #[test]
+    fn select_keys_without_selection_keeps_all() {
+        let keys = select_keys(account(&[(&[1, 2], 1), (&[3], 1)], 1), &[]).unwrap();
+        assert_eq!(selected(&keys), [(0, 0), (0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn select_keys_drops_unselected_keys_and_credentials() {
+        let keys = account(&[(&[1, 2], 1), (&[3], 1)], 1);
+        let keys = select_keys(keys, &selectors(&["0:1"])).unwrap();
+        assert_eq!(selected(&keys), [(0, 1)]);
+        assert_eq!(keys.keys.threshold, SignatureThreshold(1));
+    }
+
+    #[test]
+    fn select_keys_rejects_unknown_keys() {
+        let keys = account(&[(&[1], 1)], 1);
+        assert!(select_keys(keys, &selectors(&["0:1"])).is_err());
+        let keys = account(&[(&[1], 1)], 1);
+        assert!(select_keys(keys, &selectors(&["1:0"])).is_err());
+    }
+
+    #[test]
+    fn select_keys_checks_the_thresholds() {
+        let keys = account(&[(&[1, 2], 2)], 1);
+        assert!(select_keys(keys, &selectors(&["0:0"])).is_err());
+        let keys = account(&[(&[1], 1), (&[2], 1)], 2);
+        assert!(select_keys(keys, &selectors(&["0:0"])).is_err());
+        let keys = account(&[(&[1], 1), (&[2], 1)], 2);
+        assert!(select_keys(keys, &selectors(&["0:0", "1:0"])).is_ok());
+    }
+
+    fn genesis_account(byte: u8) -> Value {
+        let address: AccountAddress = ADDRESS.parse().unwrap();
+        let account = signing_key_account(&hex::encode([byte; 32]), address).unwrap();
+        json!({ "accountKeys": account.keys, "address": address })
+    }
+
+    fn signing_keys(accounts: &[WalletAccount]) -> Vec<String> {
+        accounts
+            .iter()
+            .flat_map(|account| account.keys.keys.values())
+            .flat_map(|credential| credential.keys.values())
+            .map(|pair| hex::encode(pair.secret.as_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_key_file_genesis_account() {
+        let accounts = parse_key_file(genesis_account(1)).unwrap();
+        assert_eq!(signing_keys(&accounts), [hex::encode([1; 32])]);
+    }
+
+    #[test]
+    fn parse_key_file_browser_wallet_export() {
+        let export = json!({
+            "type": "concordium-browser-wallet-account",
+            "v": 0,
+            "value": genesis_account(2),
+        });
+        let accounts = parse_key_file(export).unwrap();
+        assert_eq!(signing_keys(&accounts), [hex::encode([2; 32])]);
+    }
+
+    #[test]
+    fn parse_key_file_mobile_wallet_export() {
+        let export = json!({
+            "type": "concordium-mobile-wallet-data",
+            "v": 1,
+            "value": { "identities": [
+                { "accounts": [genesis_account(3), genesis_account(4)] },
+                { "accounts": [genesis_account(5)] },
+            ] },
+        });
+        let accounts = parse_key_file(export).unwrap();
+        assert_eq!(
+            signing_keys(&accounts),
+            [3, 4, 5].map(|byte| hex::encode([byte; 32]))
+        );
+        let empty = json!({
+            "type": "concordium-mobile-wallet-data",
+            "value": { "identities": [{ "accounts": [] }] },
+        });
+        assert!(parse_key_file(empty).is_err());
+    }
+
+    #[test]
+    fn parse_key_file_names_an_unknown_type() {
+        let export = json!({ "type": "hardware-wallet", "v": 0, "value": genesis_account(6) });
+        let error = format!("{:#}", parse_key_file(export).unwrap_err());
+        assert!(
+            error.contains("Unknown key file type hardware-wallet"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn parse_key_file_rejects_encrypted_exports() {
+        let export = json!({ "cipherText": "", "metadata": {} });
+        assert!(parse_key_file(export).is_err());
+    }
+
+    #[test]
+    fn signing_key_account_from_hex_and_base64() {
+        let address: AccountAddress = ADDRESS.parse().unwrap();
+        let hex_key = signing_key_account(&format!(" {} ", hex::encode([7; 32])), address).unwrap();
+        let base64_key =
+            signing_key_account(&general_purpose::STANDARD.encode([7; 32]), address).unwrap();
+        assert_eq!(hex_key.address, address);
+        assert_eq!(signing_keys(&[hex_key]), [hex::encode([7; 32])]);
+        assert_eq!(signing_keys(&[base64_key]), [hex::encode([7; 32])]);
+    }
+
+    #[test]
+    fn signing_key_account_has_one_key() {
+        let address: AccountAddress = ADDRESS.parse().unwrap();
+        let account = signing_key_account(&hex::encode([7; 32]), address).unwrap();
+        assert_eq!(selected(&account), [(0, 0)]);
+        assert_eq!(account.keys.threshold, SignatureThreshold(1));
+        let credential = &account.keys.keys[&CredentialIndex { index: 0 }];
+        assert_eq!(credential.threshold, SignatureThreshold(1));
+        let pair = &credential.keys[&KeyIndex(0)];
+        assert_eq!(pair.public, ed25519_dalek::PublicKey::from(&pair.secret));
+    }
+
+    #[test]
+    fn signing_key_account_rejects_invalid_keys() {
+        let address: AccountAddress = ADDRESS.parse().unwrap();
+        assert!(signing_key_account("not a key", address).is_err());
+        assert!(signing_key_account(&hex::encode([7; 31]), address).is_err());
+        assert!(signing_key_account(&hex::encode([7; 64]), address).is_err());
+    }
+}
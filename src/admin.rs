@@ -0,0 +1,294 @@
+//! Administrative operations on a deployed contract instance.
+use crate::{
+    cis2,
+    config::Config,
+    instance::{self, InvokeSettings},
+    schema,
+};
+use anyhow::Context;
+use concordium_rust_sdk::{
+    smart_contracts::common::schema::{Fields, Type, VersionedModuleSchema},
+    types::{
+        smart_contracts::{ModuleReference, OwnedParameter},
+        Address, ContractAddress,
+    },
+    v2,
+};
+use serde_json::{json, Value};
+use std::io::Write;
+
+/// Fail unless the module is deployed on chain.
+pub async fn ensure_deployed(
+    client: &mut v2::Client,
+    module_ref: ModuleReference,
+) -> anyhow::Result<()> {
+    match client
+        .get_module_source(&module_ref, v2::BlockIdentifier::LastFinal)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) if e.is_not_found() => anyhow::bail!(
+            "The module {} is not deployed. Deploy it before upgrading to it.",
+            module_ref
+        ),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The parameter of the `upgrade` entrypoint of the standard upgradeable
+/// contracts: the new module and optionally an entrypoint of the new module
+/// to call with the given parameter after the upgrade.
+pub fn upgrade_params_json(
+    module_ref: ModuleReference,
+    migrate: Option<(&str, &OwnedParameter)>,
+) -> Value {
+    let migrate = match migrate {
+        Some((entrypoint, parameter)) => {
+            json!({ "Some": [[entrypoint, hex::encode(parameter.as_ref())]] })
+        }
+        None => json!({ "None": [] }),
+    };
+    json!({ "module": module_ref.to_string(), "migrate": migrate })
+}
+
+/// The built-in method and parameter that pause or unpause the contract:
+/// either `setPaused` with a boolean, also as the `paused` field of a struct,
+/// or the separate `pause` and `unpause` methods. Methods are looked up under
+/// the entrypoint names the `config` maps them to.
+pub fn pause_call(
+    schema: &VersionedModuleSchema,
+    contract: &str,
+    paused: bool,
+    config: &Config,
+) -> anyhow::Result<(&'static str, Option<Value>)> {
+    let entrypoints = schema::entrypoints(schema, contract);
+    let set_paused = config.method("setPaused");
+    if entrypoints.iter().any(|e| e == set_paused) {
+        let parameter = match schema::receive_param_schema(schema, contract, set_paused)? {
+            Some(Type::Struct(Fields::Named(fields))) if fields.len() == 1 => {
+                json!({ fields[0].0.clone(): paused })
+            }
+            _ => json!(paused),
+        };
+        return Ok(("setPaused", Some(parameter)));
+    }
+    let method = if paused { "pause" } else { "unpause" };
+    anyhow::ensure!(
+        entrypoints.iter().any(|e| e == config.method(method)),
+        "Contract {} has neither a {} nor a {} entrypoint.",
+        contract,
+        set_paused,
+        config.method(method)
+    );
+    Ok((method, None))
+}
+
+/// Fail if the contract's read-only `method` reports that it is paused. The
+/// return value is a boolean, also as the `paused` field or only field of a
+/// struct.
+pub async fn ensure_not_paused(
+    client: &mut v2::Client,
+    settings: &InvokeSettings,
+    schema: &VersionedModuleSchema,
+    contract_name: &str,
+    contract: ContractAddress,
+    method: &str,
+) -> anyhow::Result<()> {
+    let response = instance::invoke_json(
+        client,
+        settings,
+        schema,
+        contract_name,
+        contract,
+        method,
+        None,
+    )
+    .await?;
+    let paused = match &response {
+        Value::Bool(paused) => Some(*paused),
+        Value::Object(fields) => match fields.get("paused") {
+            Some(paused) => paused.as_bool(),
+            None if fields.len() == 1 => fields.values().next().and_then(Value::as_bool),
+            None => None,
+        },
+        _ => None,
+    };
+    match paused {
+        Some(false) => Ok(()),
+        Some(true) => anyhow::bail!(
+            "Contract {} is paused, so the transaction would be rejected. Nothing was submitted.",
+            contract
+        ),
+        None => anyhow::bail!("Unexpected {} response: {}", method, response),
+    }
+}
+
+/// The parameter of `transferOwnership`: the new owner, either directly or as
+/// the only field of a struct.
+fn ownership_params_json(param_schema: Option<&Type>, new_owner: &Address) -> Value {
+    match param_schema {
+        Some(Type::Struct(Fields::Named(fields))) if fields.len() == 1 => {
+            json!({ fields[0].0.clone(): cis2::address_json(new_owner) })
+        }
+        _ => cis2::address_json(new_owner),
+    }
+}
+
+/// The parameter of `grantRole` and `revokeRole`: a struct with an address
+/// field and a role field, the role being an enum variant or a string. Fails
+/// on any other field, so that a parameter the contract does not expect is
+/// never sent.
+fn role_params_json(
+    method: &str,
+    param_schema: Option<&Type>,
+    address: &Address,
+    role: &str,
+) -> anyhow::Result<Value> {
+    let Some(Type::Struct(Fields::Named(fields))) = param_schema else {
+        anyhow::bail!(
+            "The parameter of {} is not a struct with an address and a role.",
+            method
+        );
+    };
+    let mut parameter = serde_json::Map::new();
+    let (mut has_address, mut has_role) = (false, false);
+    for (name, ty) in fields {
+        let value = match ty {
+            _ if is_address_type(ty) => {
+                has_address = true;
+                address_value(ty, address)
+                    .with_context(|| format!("Field {} of the {} parameter.", name, method))?
+            }
+            Type::Enum(variants) => {
+                anyhow::ensure!(
+                    variants.iter().any(|(variant, _)| variant == role),
+                    "Unknown role {}, the contract knows: {}.",
+                    role,
+                    variants
+                        .iter()
+                        .map(|(variant, _)| variant.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                has_role = true;
+                json!({ role: [] })
+            }
+            Type::String(_) => {
+                has_role = true;
+                json!(role)
+            }
+            _ => anyhow::bail!(
+                "Field {} of the {} parameter is neither an address nor a role.",
+                name,
+                method
+            ),
+        };
+        parameter.insert(name.clone(), value);
+    }
+    anyhow::ensure!(
+        has_address && has_role,
+        "The parameter of {} is not a struct with an address and a role.",
+        method
+    );
+    Ok(Value::Object(parameter))
+}
+
+/// Whether `ty` holds an account address, a contract address or either, as
+/// the `Address` enum.
+fn is_address_type(ty: &Type) -> bool {
+    match ty {
+        Type::AccountAddress | Type::ContractAddress => true,
+        Type::Enum(variants) => {
+            variants.len() == 2 && variants[0].0 == "Account" && variants[1].0 == "Contract"
+        }
+        _ => false,
+    }
+}
+
+/// `address` in the JSON of the address type `ty`.
+fn address_value(ty: &Type, address: &Address) -> anyhow::Result<Value> {
+    match (ty, address) {
+        (Type::AccountAddress, Address::Account(a)) => Ok(json!(a.to_string())),
+        (Type::ContractAddress, Address::Contract(c)) => {
+            Ok(json!({ "index": c.index, "subindex": c.subindex }))
+        }
+        (Type::Enum(_), _) => Ok(cis2::address_json(address)),
+        _ => anyhow::bail!(
+            "It takes a different kind of address than {}.",
+            cis2::describe_address(address)
+        ),
+    }
+}
+
+/// A change of the owner or of the roles of a contract.
+pub enum AdminChange {
+    TransferOwnership { new_owner: Address },
+    GrantRole { role: String, address: Address },
+    RevokeRole { role: String, address: Address },
+}
+
+impl AdminChange {
+    /// The built-in method making the change.
+    pub fn method(&self) -> &'static str {
+        match self {
+            AdminChange::TransferOwnership { .. } => "transferOwnership",
+            AdminChange::GrantRole { .. } => "grantRole",
+            AdminChange::RevokeRole { .. } => "revokeRole",
+        }
+    }
+
+    /// The JSON parameter of the contract's `entrypoint` for the change,
+    /// shaped after its schema.
+    pub fn parameter(
+        &self,
+        schema: &VersionedModuleSchema,
+        contract: &str,
+        entrypoint: &str,
+    ) -> anyhow::Result<Value> {
+        let param_schema = schema::receive_param_schema(schema, contract, entrypoint)?;
+        match self {
+            AdminChange::TransferOwnership { new_owner } => {
+                Ok(ownership_params_json(param_schema.as_ref(), new_owner))
+            }
+            AdminChange::GrantRole { role, address }
+            | AdminChange::RevokeRole { role, address } => {
+                role_params_json(self.method(), param_schema.as_ref(), address, role)
+            }
+        }
+    }
+
+    pub fn describe(&self, contract: ContractAddress) -> String {
+        match self {
+            AdminChange::TransferOwnership { new_owner } => format!(
+                "transfer the ownership of contract {} to {}",
+                contract,
+                cis2::describe_address(new_owner)
+            ),
+            AdminChange::GrantRole { role, address } => format!(
+                "grant the role {} to {} on contract {}",
+                role,
+                cis2::describe_address(address),
+                contract
+            ),
+            AdminChange::RevokeRole { role, address } => format!(
+                "revoke the role {} from {} on contract {}",
+                role,
+                cis2::describe_address(address),
+                contract
+            ),
+        }
+    }
+}
+
+/// Ask on the terminal whether to go ahead, failing unless the answer is yes.
+pub fn confirm(question: &str) -> anyhow::Result<()> {
+    eprint!("{} [y/N] ", question);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    anyhow::ensure!(
+        matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"),
+        "Aborted."
+    );
+    Ok(())
+}
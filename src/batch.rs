@@ -0,0 +1,730 @@
+//! Batch operations driven by CSV files.
+use crate::{
+    accounts::AccountPool,
+    chain::ChainParams,
+    cis2::{self, Transfer},
+    instance::{self, InvokeSettings},
+    output::{self, Printer, Style},
+    params,
+    submit::{self, Submitter, TransactionPlan},
+};
+use anyhow::Context;
+use concordium_rust_sdk::{
+    id::types::AccountAddress,
+    smart_contracts::common::{
+        constants::MAX_PARAMETER_LEN,
+        schema::{Fields, Type, VersionedModuleSchema},
+        Amount, OwnedReceiveName,
+    },
+    types::{
+        hashes::TransactionHash,
+        smart_contracts::{InvokeContractResult, OwnedParameter},
+        transactions::{ExactSizeTransactionSigner, Payload, UpdateContractPayload},
+        Address, ContractAddress, Energy,
+    },
+    v2,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// A row of a transfer CSV file.
+#[derive(Debug, Deserialize)]
+struct TransferRow {
+    from: String,
+    to: String,
+    token_id: String,
+    amount: String,
+    #[serde(default)]
+    data: Option<String>,
+}
+
+/// Read and validate all transfers of a CSV file with the columns `from`,
+/// `to`, `token_id`, `amount`, a positive integer, and optionally `data`. Rows
+/// without data get `default_data`. Every row is checked before any is
+/// returned, so nothing is submitted if a row is invalid.
+pub fn read_transfers(path: &Path, default_data: &str) -> anyhow::Result<Vec<Transfer>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(|| format!("Unable to read the CSV file {}.", path.display()))?;
+    let mut transfers = Vec::new();
+    let mut errors = Vec::new();
+    for (i, row) in reader.deserialize::<TransferRow>().enumerate() {
+        // Row 1 is the header.
+        let line = i + 2;
+        match row
+            .map_err(anyhow::Error::from)
+            .and_then(|row| parse_transfer(row, default_data))
+        {
+            Ok(transfer) => transfers.push(transfer),
+            Err(e) => errors.push(format!("row {}: {:#}", line, e)),
+        }
+    }
+    anyhow::ensure!(
+        errors.is_empty(),
+        "Invalid transfers in {}:\n  {}",
+        path.display(),
+        errors.join("\n  ")
+    );
+    anyhow::ensure!(!transfers.is_empty(), "No transfers in {}.", path.display());
+    Ok(transfers)
+}
+
+fn parse_transfer(row: TransferRow, default_data: &str) -> anyhow::Result<Transfer> {
+    let data = match row.data.filter(|data| !data.is_empty()) {
+        Some(data) => cis2::parse_transfer_data(&data)?,
+        None => default_data.to_string(),
+    };
+    Ok(Transfer {
+        token_id: cis2::parse_token_id(&row.token_id)?,
+        amount: cis2::parse_token_amount(&row.amount)?,
+        from: cis2::parse_address(&row.from)?,
+        to: cis2::parse_address(&row.to)?,
+        data,
+    })
+}
+
+/// Outcome of one item of a batch.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemOutcome {
+    /// Position of the item in the batch, starting from 1.
+    pub item: usize,
+    /// The token IDs of the item, comma separated.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tokens: String,
+    pub hash: Option<TransactionHash>,
+    /// The block the transaction was finalized in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<submit::BlockMetadata>,
+    pub outcome: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub energy_cost: Option<Energy>,
+    /// The CCD paid for the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<Amount>,
+}
+
+/// Outcome of simulating one item of a batch.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunOutcome {
+    /// Position of the item in the batch, starting from 1.
+    pub item: usize,
+    /// The token IDs of the item, comma separated.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tokens: String,
+    /// `success`, or the reason the update would be rejected.
+    pub outcome: String,
+    pub used_energy: Energy,
+}
+
+/// Totals of a batch run.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metrics {
+    pub submitted: usize,
+    /// Finalized transactions, rejected ones included.
+    pub finalized: usize,
+    pub rejected: usize,
+    /// Submitted transactions whose outcome is unknown because waiting for
+    /// them failed.
+    pub timed_out: usize,
+    pub not_submitted: usize,
+    pub energy_used: u64,
+    pub ccd_spent: Amount,
+    pub duration_secs: f64,
+    /// Finalized transactions per second.
+    pub throughput: f64,
+}
+
+impl Metrics {
+    pub fn new(outcomes: &[ItemOutcome], duration: std::time::Duration) -> Self {
+        let finalized: Vec<&ItemOutcome> = outcomes.iter().filter(|o| o.block.is_some()).collect();
+        let submitted = outcomes.iter().filter(|o| o.hash.is_some()).count();
+        let duration_secs = duration.as_secs_f64();
+        Metrics {
+            submitted,
+            finalized: finalized.len(),
+            rejected: finalized.iter().filter(|o| o.outcome != "success").count(),
+            timed_out: submitted - finalized.len(),
+            not_submitted: outcomes.len() - submitted,
+            energy_used: finalized
+                .iter()
+                .filter_map(|o| o.energy_cost)
+                .map(|e| e.energy)
+                .sum(),
+            ccd_spent: finalized
+                .iter()
+                .filter_map(|o| o.cost)
+                .fold(Amount::zero(), |total, cost| total + cost),
+            duration_secs,
+            throughput: if duration_secs > 0.0 {
+                finalized.len() as f64 / duration_secs
+            } else {
+                0.0
+            },
+        }
+    }
+
+    pub fn print(&self) {
+        println!(
+            "Submitted {}, finalized {} ({} rejected), timed out {}, not submitted {}.",
+            self.submitted, self.finalized, self.rejected, self.timed_out, self.not_submitted
+        );
+        println!(
+            "Used {} energy and {} CCD in {:.1} seconds ({:.2} tx/s).",
+            self.energy_used, self.ccd_spent, self.duration_secs, self.throughput
+        );
+    }
+
+    /// Write the metrics as JSON to `path`.
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Could not write the metrics file {}.", path.display()))
+    }
+}
+
+/// A single update of a batch.
+pub struct BatchItem {
+    pub parameter: OwnedParameter,
+    /// The account that should sign the update, if it is in the pool.
+    pub signer: Option<AccountAddress>,
+    /// The token IDs the update concerns, comma separated, for reports.
+    pub tokens: String,
+}
+
+/// Serialize one `transfer` parameter per transfer. A transfer is signed by
+/// the account of its `from` address if possible.
+pub fn transfer_items(
+    param_schema: &Type,
+    transfers: &[Transfer],
+) -> anyhow::Result<Vec<BatchItem>> {
+    transfers
+        .iter()
+        .enumerate()
+        .map(|(i, transfer)| {
+            cis2::check_token_id(param_schema, &transfer.token_id)
+                .with_context(|| format!("Transfer {} is invalid.", i + 1))?;
+            let parameter = cis2::transfer_params_json(std::slice::from_ref(transfer));
+            let parameter =
+                params::serialize_parameter(param_schema, &parameter, params::Checks::default())
+                    .with_context(|| format!("Transfer {} cannot be serialized.", i + 1))?;
+            let signer = match &transfer.from {
+                Address::Account(from) => Some(*from),
+                Address::Contract(_) => None,
+            };
+            Ok(BatchItem {
+                parameter,
+                signer,
+                tokens: transfer.token_id.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Serialize one `mint` parameter per token ID from the `template`
+/// parameter. In every string of the template `{id}` is replaced by the
+/// token ID in hex, `{n}` by its number and `{url}` by the `metadata_url`
+/// with the same substitutions.
+pub fn mint_items(
+    param_schema: &Type,
+    template: &Value,
+    token_ids: &[(u64, String)],
+    metadata_url: Option<&str>,
+    checks: params::Checks,
+) -> anyhow::Result<Vec<BatchItem>> {
+    token_ids
+        .iter()
+        .map(|(n, id)| {
+            let n = n.to_string();
+            let url = metadata_url
+                .map(|url| params::substitute_str(url, &[("{id}", id), ("{n}", &n)]))
+                .unwrap_or_default();
+            let parameter =
+                params::substitute(template, &[("{id}", id), ("{n}", &n), ("{url}", &url)]);
+            cis2::check_metadata_urls(param_schema, &parameter)
+                .with_context(|| format!("The mint parameter for token {} is invalid.", id))?;
+            let parameter = params::serialize_parameter(param_schema, &parameter, checks)
+                .with_context(|| format!("The mint parameter for token {} is invalid.", id))?;
+            Ok(BatchItem {
+                parameter,
+                signer: None,
+                tokens: id.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Split a `mint` parameter that is too large for one transaction, or has
+/// more than `max_items` tokens, into several. The list of tokens, the only
+/// field the schema declares as a list, set or map, is divided into chunks
+/// that each fit. Returns `None` if the parameter needs no splitting or has
+/// no such single field.
+pub fn split_mint(
+    param_schema: &Type,
+    parameter: &Value,
+    max_items: Option<usize>,
+    checks: params::Checks,
+) -> anyhow::Result<Option<Vec<BatchItem>>> {
+    let Some(field) = token_list_field(param_schema) else {
+        return Ok(None);
+    };
+    let Some(tokens) = parameter.get(field).and_then(Value::as_array) else {
+        return Ok(None);
+    };
+    let size = |chunk: &[Value]| -> anyhow::Result<usize> {
+        let mut parameter = parameter.clone();
+        parameter[field] = Value::Array(chunk.to_vec());
+        Ok(param_schema.serial_value(&parameter)?.len())
+    };
+    let mut chunk_size = max_items.unwrap_or(tokens.len()).max(1);
+    if chunk_size >= tokens.len() && size(tokens)? <= MAX_PARAMETER_LEN {
+        return Ok(None);
+    }
+    loop {
+        let mut largest = 0;
+        for chunk in tokens.chunks(chunk_size) {
+            largest = largest.max(size(chunk)?);
+        }
+        if largest <= MAX_PARAMETER_LEN {
+            break;
+        }
+        anyhow::ensure!(
+            chunk_size > 1,
+            "A single token of the mint parameter exceeds the parameter size limit of {} bytes.",
+            MAX_PARAMETER_LEN
+        );
+        chunk_size = chunk_size.div_ceil(2);
+    }
+    tokens
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let mut chunk_parameter = parameter.clone();
+            chunk_parameter[field] = Value::Array(chunk.to_vec());
+            let tokens: Vec<&str> = chunk
+                .iter()
+                .filter_map(|token| token.as_str().or_else(|| token.get(0)?.as_str()))
+                .collect();
+            Ok(BatchItem {
+                parameter: params::serialize_parameter(param_schema, &chunk_parameter, checks)?,
+                signer: None,
+                tokens: tokens.join(","),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map(Some)
+}
+
+/// The name of the only field of a struct parameter that is a list, set or
+/// map, if there is exactly one.
+fn token_list_field(param_schema: &Type) -> Option<&str> {
+    let Type::Struct(Fields::Named(fields)) = param_schema else {
+        return None;
+    };
+    let mut lists = fields
+        .iter()
+        .filter(|(_, ty)| matches!(ty, Type::List(..) | Type::Set(..) | Type::Map(..)));
+    match (lists.next(), lists.next()) {
+        (Some((name, _)), None) => Some(name),
+        _ => None,
+    }
+}
+
+/// Print how the batch is divided into transactions.
+pub fn print_plan(method: &str, items: &[BatchItem], printer: &Printer) {
+    printer.progress(format_args!(
+        "Submitting {} {} transaction(s):",
+        items.len(),
+        method
+    ));
+    for (i, item) in items.iter().enumerate() {
+        printer.progress(format_args!(
+            "{:>5}  {} bytes",
+            i + 1,
+            item.parameter.as_ref().len()
+        ));
+    }
+}
+
+/// Submit each item as its own update transaction, sending `amount` with
+/// each, and wait for all of them. The same client and serialized parameters
+/// are used for the whole batch.
+///
+/// An item is signed by its signer if that account is in the pool, and by
+/// the next account in turn otherwise. Each item expires relative to when it
+/// is signed, so a long batch does not run into the expiry of its first item.
+/// Before signing, the item is checked against the block energy limit and
+/// what the account has left after the earlier items.
+#[allow(clippy::too_many_arguments)]
+pub async fn submit_updates(
+    client: &mut v2::Client,
+    submitter: &Submitter,
+    pool: &mut AccountPool<'_>,
+    address: ContractAddress,
+    params: &ChainParams,
+    receive_name: &OwnedReceiveName,
+    items: Vec<BatchItem>,
+    amount: Amount,
+    energy: Energy,
+    continue_on_error: bool,
+) -> anyhow::Result<Vec<ItemOutcome>> {
+    let mut submitted = Vec::new();
+    let mut outcomes = Vec::new();
+    for (i, item) in items.into_iter().enumerate() {
+        let plan = TransactionPlan::new(
+            Payload::Update {
+                payload: UpdateContractPayload {
+                    amount,
+                    address,
+                    receive_name: receive_name.clone(),
+                    message: item.parameter,
+                },
+            },
+            energy,
+        );
+        let account = pool.account_for(item.signer.as_ref());
+        let expiry = submitter.expiry();
+        let total_energy = plan
+            .construct(
+                account.keys.address,
+                account.keys.num_keys(),
+                account.nonce,
+                expiry,
+            )
+            .header
+            .energy_amount;
+        let sent = match account.check_funds(params, amount, total_energy) {
+            Ok(cost) => submitter
+                .send(client, plan.sign(account.keys, account.nonce, expiry))
+                .await
+                .map(|hash| (hash, cost)),
+            Err(e) => Err(e),
+        };
+        match sent {
+            Ok((hash, cost)) => {
+                account.record_submission(cost);
+                submitted.push((i + 1, item.tokens, hash));
+            }
+            Err(e) if continue_on_error => outcomes.push(ItemOutcome {
+                item: i + 1,
+                tokens: item.tokens,
+                hash: None,
+                block: None,
+                outcome: format!("not submitted: {:#}", e),
+                energy_cost: None,
+                cost: None,
+            }),
+            Err(e) => return Err(e.context(format!("Item {} was not submitted.", i + 1))),
+        }
+    }
+    // Clones of the client share its connection, so the transactions are
+    // awaited together rather than one after the other.
+    let waits = submitted.into_iter().map(|(item, tokens, hash)| {
+        let mut client = client.clone();
+        async move {
+            match submitter.wait(&mut client, &hash).await {
+                Ok(finalized) => ItemOutcome {
+                    item,
+                    tokens,
+                    hash: Some(hash),
+                    block: Some(finalized.block),
+                    outcome: finalized.outcome,
+                    energy_cost: Some(finalized.energy_cost),
+                    cost: Some(finalized.cost),
+                },
+                Err(e) => ItemOutcome {
+                    item,
+                    tokens,
+                    hash: Some(hash),
+                    block: None,
+                    outcome: format!("unknown: {:#}", e),
+                    energy_cost: None,
+                    cost: None,
+                },
+            }
+        }
+    });
+    outcomes.extend(futures::future::join_all(waits).await);
+    outcomes.sort_by_key(|o| o.item);
+    Ok(outcomes)
+}
+
+/// Simulate each item with the account that would sign it, without
+/// submitting anything. Every item is simulated against the current state,
+/// so an item that depends on an earlier one, e.g. a transfer of a token the
+/// batch mints, is reported as rejected. Rejections are decoded with the
+/// error schema in `schema`, if given.
+#[allow(clippy::too_many_arguments)]
+pub async fn dry_run_items(
+    client: &mut v2::Client,
+    settings: &InvokeSettings,
+    pool: &AccountPool<'_>,
+    address: ContractAddress,
+    receive_name: &OwnedReceiveName,
+    items: &[BatchItem],
+    amount: Amount,
+    energy: Energy,
+    schema: Option<&VersionedModuleSchema>,
+) -> anyhow::Result<Vec<DryRunOutcome>> {
+    // A copy of the pool picks the same signers as the submission will.
+    let mut pool = pool.clone();
+    let mut outcomes = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        let sender = pool.account_for(item.signer.as_ref()).keys.address;
+        let payload = UpdateContractPayload {
+            amount,
+            address,
+            receive_name: receive_name.clone(),
+            message: item.parameter.clone(),
+        };
+        let (outcome, used_energy) =
+            match instance::dry_run(client, settings, sender, &payload, energy)
+                .await
+                .with_context(|| format!("Item {} could not be simulated.", i + 1))?
+            {
+                InvokeContractResult::Success { used_energy, .. } => {
+                    ("success".to_string(), used_energy)
+                }
+                InvokeContractResult::Failure {
+                    reason,
+                    return_value,
+                    used_energy,
+                } => {
+                    let return_value = return_value.as_ref().map(|rv| &rv.value[..]);
+                    let reason = crate::reject::describe(&reason, return_value, schema);
+                    (format!("rejected: {}", reason), used_energy)
+                }
+            };
+        outcomes.push(DryRunOutcome {
+            item: i + 1,
+            tokens: item.tokens.clone(),
+            outcome,
+            used_energy,
+        });
+    }
+    Ok(outcomes)
+}
+
+pub fn print_dry_run(outcomes: &[DryRunOutcome], printer: &Printer) {
+    println!(
+        "{}",
+        printer.paint(
+            &format!("{:>5}  {:>8}  outcome", "item", "energy"),
+            Style::Label
+        )
+    );
+    for o in outcomes {
+        println!(
+            "{:>5}  {:>8}  {}",
+            o.item,
+            o.used_energy.energy,
+            printer.paint_outcome(&o.outcome)
+        );
+    }
+}
+
+pub fn print_dry_run_csv(outcomes: &[DryRunOutcome]) -> anyhow::Result<()> {
+    output::print_csv(
+        &["item", "tokens", "outcome", "energy"],
+        outcomes.iter().map(|o| {
+            vec![
+                o.item.to_string(),
+                o.tokens.clone(),
+                o.outcome.clone(),
+                o.used_energy.energy.to_string(),
+            ]
+        }),
+    )
+}
+
+/// Print the outcomes as CSV, with the block, energy and cost of finalized
+/// items.
+pub fn print_outcomes_csv(outcomes: &[ItemOutcome]) -> anyhow::Result<()> {
+    let text = |value: Option<String>| value.unwrap_or_default();
+    output::print_csv(
+        &[
+            "item",
+            "tokens",
+            "transaction",
+            "block",
+            "outcome",
+            "energy",
+            "cost",
+        ],
+        outcomes.iter().map(|o| {
+            vec![
+                o.item.to_string(),
+                o.tokens.clone(),
+                text(o.hash.map(|h| h.to_string())),
+                text(o.block.as_ref().map(|b| b.block_hash.to_string())),
+                o.outcome.clone(),
+                text(o.energy_cost.map(|e| e.to_string())),
+                text(o.cost.map(|c| c.to_string())),
+            ]
+        }),
+    )
+}
+
+pub fn print_outcomes(outcomes: &[ItemOutcome], printer: &Printer) {
+    println!(
+        "{}",
+        printer.paint(
+            &format!("{:>5}  {:64}  outcome", "item", "transaction"),
+            Style::Label
+        )
+    );
+    for o in outcomes {
+        let hash = o
+            .hash
+            .map_or_else(|| "-".to_string(), |hash| hash.to_string());
+        println!(
+            "{:>5}  {:64}  {}",
+            o.item,
+            hash,
+            printer.paint_outcome(&o.outcome)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use concordium_rust_sdk::smart_contracts::common::schema::SizeLength;
+    use serde_json::json;
+
+    const ACCOUNT: &str = "3kBx2h5Y2veb4hZgAJWPrr8RyQESKm5TjzF3ti1QQ4VSYLwK1G";
+
+    /// Write `contents` to a CSV file of its own in the temporary directory.
+    fn csv_file(name: &str, contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "cis2-transfers-{}-{}.csv",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn read_transfers_rows() {
+        let path = csv_file(
+            "rows",
+            &format!(
+                "from,to,token_id,amount,data\n{a},\"<5,0>\",0A,2,\n{a}, {a} ,01,1,FF\n",
+                a = ACCOUNT
+            ),
+        );
+        let transfers = read_transfers(&path, "00").unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[0].token_id, "0a");
+        assert_eq!(transfers[0].amount, 2);
+        assert_eq!(
+            transfers[0].to,
+            Address::Contract(ContractAddress::new(5, 0))
+        );
+        assert_eq!(transfers[0].data, "00");
+        assert_eq!(transfers[1].data, "ff");
+    }
+
+    #[test]
+    fn read_transfers_reports_every_invalid_row() {
+        let path = csv_file(
+            "invalid",
+            &format!(
+                "from,to,token_id,amount\n{a},{a},zz,1\n{a},{a},01,1\nnobody,{a},01,0\n",
+                a = ACCOUNT
+            ),
+        );
+        let error = format!("{:#}", read_transfers(&path, "").unwrap_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.contains("row 2"), "{}", error);
+        assert!(!error.contains("row 3"), "{}", error);
+        assert!(error.contains("row 4"), "{}", error);
+    }
+
+    #[test]
+    fn read_transfers_needs_a_row() {
+        let path = csv_file("empty", "from,to,token_id,amount\n");
+        let result = read_transfers(&path, "");
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    fn mint_schema(token: Type) -> Type {
+        Type::Struct(Fields::Named(vec![
+            ("owner".into(), Type::String(SizeLength::U8)),
+            (
+                "tokens".into(),
+                Type::List(SizeLength::U32, Box::new(token)),
+            ),
+        ]))
+    }
+
+    #[test]
+    fn token_list_field_is_the_only_list() {
+        let schema = mint_schema(Type::String(SizeLength::U8));
+        assert_eq!(token_list_field(&schema), Some("tokens"));
+        let two_lists = Type::Struct(Fields::Named(vec![
+            ("a".into(), Type::List(SizeLength::U8, Box::new(Type::U8))),
+            ("b".into(), Type::Set(SizeLength::U8, Box::new(Type::U8))),
+        ]));
+        assert_eq!(token_list_field(&two_lists), None);
+        assert_eq!(
+            token_list_field(&Type::List(SizeLength::U8, Box::new(Type::U8))),
+            None
+        );
+    }
+
+    #[test]
+    fn split_mint_by_max_items() {
+        let schema = mint_schema(Type::String(SizeLength::U8));
+        let parameter = json!({ "owner": "me", "tokens": ["01", "02", "03", "04", "05"] });
+        let items = split_mint(&schema, &parameter, Some(2), params::Checks::default())
+            .unwrap()
+            .unwrap();
+        let tokens: Vec<&str> = items.iter().map(|i| i.tokens.as_str()).collect();
+        assert_eq!(tokens, ["01,02", "03,04", "05"]);
+    }
+
+    #[test]
+    fn split_mint_keeps_a_parameter_that_fits() {
+        let schema = mint_schema(Type::String(SizeLength::U8));
+        let parameter = json!({ "owner": "me", "tokens": ["01", "02"] });
+        let items = split_mint(&schema, &parameter, None, params::Checks::default()).unwrap();
+        assert!(items.is_none());
+        let items = split_mint(&schema, &parameter, Some(2), params::Checks::default()).unwrap();
+        assert!(items.is_none());
+    }
+
+    #[test]
+    fn split_mint_by_size() {
+        let schema = mint_schema(Type::Pair(
+            Box::new(Type::String(SizeLength::U8)),
+            Box::new(Type::String(SizeLength::U8)),
+        ));
+        let tokens: Vec<Value> = (0..300)
+            .map(|n| json!([format!("{:02x}", n % 256), "u".repeat(250)]))
+            .collect();
+        let parameter = json!({ "owner": "me", "tokens": tokens });
+        let items = split_mint(&schema, &parameter, None, params::Checks::default())
+            .unwrap()
+            .unwrap();
+        assert!(items.len() > 1);
+        assert!(items
+            .iter()
+            .all(|i| i.parameter.as_ref().len() <= MAX_PARAMETER_LEN));
+        let count: usize = items.iter().map(|i| i.tokens.split(',').count()).sum();
+        assert_eq!(count, 300);
+    }
+
+    #[test]
+    fn split_mint_fails_on_a_token_over_the_limit() {
+        let schema = mint_schema(Type::String(SizeLength::U32));
+        let parameter = json!({ "owner": "me", "tokens": ["x".repeat(MAX_PARAMETER_LEN)] });
+        assert!(split_mint(&schema, &parameter, None, params::Checks::default()).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+//! Chain parameters and the cost computations derived from them.
+use anyhow::Context;
+use concordium_rust_sdk::{
+    common::types::Amount,
+    endpoints::BlocksAtHeightInput,
+    id::types::AccountAddress,
+    smart_contracts::common::ExchangeRate,
+    types::{hashes::BlockHash, AccountInfo, BlockItemSummary, CredentialsPerBlockLimit, Energy},
+    v2::{self, BlockIdentifier, ChainParameters},
+};
+use futures::TryStreamExt;
+use serde::Serialize;
+use std::str::FromStr;
+use strum_macros::{Display, EnumString};
+
+/// Below this much execution energy even a simple contract update fails, so a
+/// smaller `--max-fee` budget is a mistake.
+const MIN_EXECUTION_ENERGY: u64 = 500;
+
+/// The energy limit of a block, which no transaction can exceed. It is a
+/// genesis parameter that chain parameters of version 0 and 1 do not report,
+/// and is the same on mainnet and testnet.
+const MAX_BLOCK_ENERGY: u64 = 3000000;
+
+/// The subset of the chain parameters that matter for fees and limits.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainParams {
+    /// The block in which the parameters are in effect.
+    pub block_hash: BlockHash,
+    /// Version of the chain parameters, `0` for protocol 1-3, `1` from 4.
+    pub version: u8,
+    pub euro_per_energy: ExchangeRate,
+    pub micro_ccd_per_euro: ExchangeRate,
+    pub account_creation_limit: CredentialsPerBlockLimit,
+    pub foundation_account: AccountAddress,
+    pub max_block_energy: Energy,
+}
+
+impl ChainParams {
+    /// Fetch the chain parameters in effect in the last finalized block.
+    pub async fn fetch(client: &mut v2::Client) -> anyhow::Result<Self> {
+        let res = client
+            .get_block_chain_parameters(&BlockIdentifier::LastFinal)
+            .await?;
+        let params = match res.response {
+            ChainParameters::V0(v0) => ChainParams {
+                block_hash: res.block_hash,
+                version: 0,
+                euro_per_energy: v0.euro_per_energy,
+                micro_ccd_per_euro: v0.micro_ccd_per_euro,
+                account_creation_limit: v0.account_creation_limit,
+                foundation_account: v0.foundation_account,
+                max_block_energy: MAX_BLOCK_ENERGY.into(),
+            },
+            ChainParameters::V1(v1) => ChainParams {
+                block_hash: res.block_hash,
+                version: 1,
+                euro_per_energy: v1.euro_per_energy,
+                micro_ccd_per_euro: v1.micro_ccd_per_euro,
+                account_creation_limit: v1.account_creation_limit,
+                foundation_account: v1.foundation_account,
+                max_block_energy: MAX_BLOCK_ENERGY.into(),
+            },
+        };
+        Ok(params)
+    }
+
+    /// The cost of `energy` in CCD at the current exchange rate, rounded up to
+    /// the nearest micro CCD.
+    pub fn energy_cost(&self, energy: Energy) -> Amount {
+        let num = u128::from(energy.energy)
+            * u128::from(self.euro_per_energy.numerator())
+            * u128::from(self.micro_ccd_per_euro.numerator());
+        let denom = u128::from(self.euro_per_energy.denominator())
+            * u128::from(self.micro_ccd_per_euro.denominator());
+        Amount::from_micro_ccd(num.div_ceil(denom) as u64)
+    }
+
+    /// The most energy whose cost stays within `budget`.
+    pub fn energy_within(&self, budget: Amount) -> Energy {
+        let num = u128::from(budget.micro_ccd())
+            * u128::from(self.euro_per_energy.denominator())
+            * u128::from(self.micro_ccd_per_euro.denominator());
+        let denom = u128::from(self.euro_per_energy.numerator())
+            * u128::from(self.micro_ccd_per_euro.numerator());
+        Energy::from(u64::try_from(num / denom).unwrap_or(u64::MAX))
+    }
+
+    /// The energy for executing a transaction whose base cost is `base`, so
+    /// that its whole fee stays within `max_fee`.
+    pub fn execution_energy(&self, max_fee: Amount, base: Energy) -> anyhow::Result<Energy> {
+        let total = self.energy_within(max_fee);
+        let execution = total.energy.saturating_sub(base.energy);
+        anyhow::ensure!(
+            execution >= MIN_EXECUTION_ENERGY,
+            "A fee of at most {} CCD buys {} energy, which leaves only {} energy for execution \
+             after the base cost of {}. Raise --max-fee.",
+            max_fee,
+            total,
+            execution,
+            base
+        );
+        Ok(Energy::from(execution))
+    }
+
+    /// Check that `balance` covers sending `amount` to a contract plus the fee
+    /// for spending `energy` on the transaction.
+    pub fn check_balance(
+        &self,
+        balance: Amount,
+        amount: Amount,
+        energy: Energy,
+    ) -> anyhow::Result<()> {
+        let fee = self.energy_cost(energy);
+        let needed = amount
+            .checked_add(fee)
+            .context("The amount plus the fee overflows.")?;
+        anyhow::ensure!(
+            balance >= needed,
+            "Insufficient balance: have {} CCD, need {} CCD ({} CCD amount + {} CCD fee for {} \
+             energy).",
+            balance,
+            needed,
+            amount,
+            fee,
+            energy
+        );
+        Ok(())
+    }
+
+    /// Fail if a transaction needing `energy` in total cannot fit in a block,
+    /// so that it would be rejected however it is sent.
+    pub fn check_energy_limit(&self, energy: Energy) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            energy <= self.max_block_energy,
+            "The transaction needs {} energy, more than the block energy limit of {}. Lower \
+             --energy.",
+            energy,
+            self.max_block_energy
+        );
+        Ok(())
+    }
+
+    /// Print the parameters in a human readable form.
+    pub fn print(&self) {
+        println!("Chain parameters (block {}):", self.block_hash);
+        println!("  version:                {}", self.version);
+        println!(
+            "  euro per energy:        {}/{}",
+            self.euro_per_energy.numerator(),
+            self.euro_per_energy.denominator()
+        );
+        println!(
+            "  micro CCD per euro:     {}/{}",
+            self.micro_ccd_per_euro.numerator(),
+            self.micro_ccd_per_euro.denominator()
+        );
+        println!(
+            "  CCD per 1000 energy:    {}",
+            self.energy_cost(Energy::from(1000))
+        );
+        println!("  account creation limit: {}", self.account_creation_limit);
+        println!("  foundation account:     {}", self.foundation_account);
+        println!("  block energy limit:     {}", self.max_block_energy);
+    }
+}
+
+/// The part of the account's balance that can be spent, i.e. what is neither
+/// locked in a release schedule nor staked.
+pub fn available_balance(info: &AccountInfo) -> Amount {
+    let staked = info
+        .account_stake
+        .as_ref()
+        .map_or(Amount::zero(), |stake| stake.staked_amount());
+    let locked = std::cmp::max(info.account_release_schedule.total, staked);
+    info.account_amount
+        .checked_sub(locked)
+        .unwrap_or_else(Amount::zero)
+}
+
+/// A block given on the command line: `best`, `last-final`, a block hash or
+/// an absolute block height.
+#[derive(Debug, Clone, Copy)]
+pub enum BlockSelector {
+    Best,
+    LastFinal,
+    Hash(BlockHash),
+    Height(u64),
+}
+
+impl FromStr for BlockSelector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s {
+            "best" => return Ok(BlockSelector::Best),
+            "last-final" => return Ok(BlockSelector::LastFinal),
+            _ => (),
+        }
+        if let Ok(height) = s.parse() {
+            return Ok(BlockSelector::Height(height));
+        }
+        s.parse().map(BlockSelector::Hash).map_err(|_| {
+            anyhow::anyhow!(
+                "Invalid block {}: expected best, last-final, a block hash or a height.",
+                s
+            )
+        })
+    }
+}
+
+impl std::fmt::Display for BlockSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockSelector::Best => write!(f, "the best block"),
+            BlockSelector::LastFinal => write!(f, "the last finalized block"),
+            BlockSelector::Hash(hash) => write!(f, "block {}", hash),
+            BlockSelector::Height(height) => write!(f, "the block at height {}", height),
+        }
+    }
+}
+
+impl BlockSelector {
+    /// The block to query, looking up the block at a height.
+    pub async fn resolve(&self, client: &mut v2::Client) -> anyhow::Result<BlockIdentifier> {
+        match self {
+            BlockSelector::Best => Ok(BlockIdentifier::Best),
+            BlockSelector::LastFinal => Ok(BlockIdentifier::LastFinal),
+            BlockSelector::Hash(hash) => Ok(BlockIdentifier::Given(*hash)),
+            BlockSelector::Height(height) => {
+                let blocks = client
+                    .get_blocks_at_height(&BlocksAtHeightInput::Absolute {
+                        height: (*height).into(),
+                    })
+                    .await?;
+                let hash = blocks
+                    .first()
+                    .with_context(|| format!("The node knows no block at height {}.", height))?;
+                Ok(BlockIdentifier::Given(*hash))
+            }
+        }
+    }
+
+    /// The height of the selected block.
+    pub async fn height(&self, client: &mut v2::Client) -> anyhow::Result<u64> {
+        let block_id = self.resolve(client).await?;
+        Ok(client
+            .get_block_info(&block_id)
+            .await?
+            .response
+            .block_height
+            .height)
+    }
+}
+
+/// The heights from `from` to `to`, both included, for scanning a range of
+/// blocks.
+pub async fn height_range(
+    client: &mut v2::Client,
+    from: &BlockSelector,
+    to: &BlockSelector,
+) -> anyhow::Result<std::ops::RangeInclusive<u64>> {
+    let from_height = from.height(client).await?;
+    let to_height = to.height(client).await?;
+    anyhow::ensure!(
+        from_height <= to_height,
+        "The range ends at height {} before it starts at height {}.",
+        to_height,
+        from_height
+    );
+    Ok(from_height..=to_height)
+}
+
+/// The block at `height` and the outcomes of all transactions in it.
+pub async fn block_items(
+    client: &mut v2::Client,
+    height: u64,
+) -> anyhow::Result<(BlockHash, Vec<BlockItemSummary>)> {
+    let block_id = BlockSelector::Height(height).resolve(client).await?;
+    let response = client.get_block_transaction_events(&block_id).await?;
+    let items = response.response.try_collect().await?;
+    Ok((response.block_hash, items))
+}
+
+/// Abort unless the chain runs at least protocol version `required`.
+pub async fn require_protocol_version(
+    client: &mut v2::Client,
+    required: u64,
+) -> anyhow::Result<()> {
+    let info = client.get_consensus_info().await?;
+    let detected = u64::from(info.protocol_version);
+    anyhow::ensure!(
+        detected >= required,
+        "The node runs protocol version {}, but at least version {} is required.",
+        detected,
+        required
+    );
+    Ok(())
+}
+
+/// A public network, recognized by its genesis block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Network {
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    /// The hash of the network's genesis block.
+    pub fn genesis_block(self) -> BlockHash {
+        let hash = match self {
+            Network::Mainnet => "9dd9ca4d19e9393877d2c44b70f89acbfc0883c2243e5eeaecc0d1cd0503f478",
+            Network::Testnet => "4221332d34e1694168c2a0c0b3fd0f273809612cb13d000d5c2e00e85f50f796",
+        };
+        hash.parse().expect("Genesis block hashes are valid.")
+    }
+}
+
+/// Abort unless the node's chain starts with the genesis block of `network`.
+/// With `force` a mismatch is only reported.
+pub async fn require_network(
+    client: &mut v2::Client,
+    network: Network,
+    force: bool,
+) -> anyhow::Result<()> {
+    let genesis = client.get_consensus_info().await?.genesis_block;
+    let expected = network.genesis_block();
+    if genesis == expected {
+        return Ok(());
+    }
+    let message = format!(
+        "The node is not on {}: its genesis block is {}, but {} starts with {}.",
+        network, genesis, network, expected
+    );
+    anyhow::ensure!(force, "{} Pass --force-network to proceed anyway.", message);
+    eprintln!("Warning: {}", message);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_selector_parsing() {
+        assert!(matches!("best".parse(), Ok(BlockSelector::Best)));
+        assert!(matches!(
+            " last-final ".parse(),
+            Ok(BlockSelector::LastFinal)
+        ));
+        assert!(matches!("1234".parse(), Ok(BlockSelector::Height(1234))));
+        let hash = "b3e8b7c1f7a5f7d4c4a6c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f80910";
+        match hash.parse() {
+            Ok(BlockSelector::Hash(parsed)) => assert_eq!(parsed.to_string(), hash),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn block_selector_rejects_other_input() {
+        assert!("latest".parse::<BlockSelector>().is_err());
+        assert!("-1".parse::<BlockSelector>().is_err());
+        assert!("b3e8".parse::<BlockSelector>().is_err());
+    }
+}
@@ -0,0 +1,629 @@
+//! JSON parameters of the standard CIS-2 entrypoints, in the shape the schema
+//! serializer expects.
+use crate::{
+    instance::{self, InvokeSettings},
+    intent,
+    params::{self, Checks},
+    schema,
+};
+use anyhow::Context;
+use concordium_rust_sdk::{
+    id::types::AccountAddress,
+    smart_contracts::common::{
+        schema::{Fields, SizeLength, Type, VersionedModuleSchema},
+        Cursor,
+    },
+    types::{Address, ContractAddress},
+    v2,
+};
+use serde_json::{json, Value};
+
+/// Entrypoint invoked on a contract receiving tokens in a transfer.
+pub const DEFAULT_RECEIVE_HOOK: &str = "onReceivingCIS2";
+
+/// Parse an account address, or a contract address written `<index,subindex>`.
+pub fn parse_address(s: &str) -> anyhow::Result<Address> {
+    let s = s.trim();
+    if s.starts_with('<') {
+        let contract: ContractAddress = s
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid contract address {}.", s))?;
+        Ok(Address::Contract(contract))
+    } else {
+        let account: AccountAddress = s
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid account address {}.", s))?;
+        Ok(Address::Account(account))
+    }
+}
+
+/// Check that a token ID is given as hex and return it lower-cased.
+pub fn parse_token_id(s: &str) -> anyhow::Result<String> {
+    let s = s.trim();
+    hex::decode(s).with_context(|| format!("Token ID {} is not valid hex.", s))?;
+    Ok(s.to_lowercase())
+}
+
+/// The most bytes of additional data a CIS-2 transfer can carry, as the data
+/// is prefixed with its length in two bytes.
+pub const MAX_TRANSFER_DATA_LEN: usize = u16::MAX as usize;
+
+/// Check that the additional data of a transfer is hex within the size limit
+/// and return it lower-cased.
+pub fn parse_transfer_data(s: &str) -> anyhow::Result<String> {
+    let s = s.trim();
+    let bytes = hex::decode(s).with_context(|| format!("The data {} is not valid hex.", s))?;
+    anyhow::ensure!(
+        bytes.len() <= MAX_TRANSFER_DATA_LEN,
+        "The data has {} bytes, but a transfer carries at most {}.",
+        bytes.len(),
+        MAX_TRANSFER_DATA_LEN
+    );
+    Ok(s.to_lowercase())
+}
+
+/// Parse a token amount, which must be a positive integer.
+pub fn parse_token_amount(s: &str) -> anyhow::Result<u64> {
+    s.trim()
+        .parse()
+        .ok()
+        .filter(|amount| *amount > 0)
+        .with_context(|| format!("Token amount {} is not a positive integer.", s))
+}
+
+/// An inclusive range of sequential token numbers, written `1..100`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenIdRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl TokenIdRange {
+    pub fn count(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+impl std::str::FromStr for TokenIdRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (start, end) = s
+            .trim()
+            .split_once("..")
+            .with_context(|| format!("Token ID range {} is not of the form 1..100.", s))?;
+        let number = |n: &str| -> anyhow::Result<u64> {
+            n.trim()
+                .parse()
+                .with_context(|| format!("Invalid token number {} in the range {}.", n, s))
+        };
+        let (start, end) = (number(start)?, number(end.trim_start_matches('='))?);
+        anyhow::ensure!(start <= end, "The token ID range {} is empty.", s);
+        anyhow::ensure!(
+            end - start < u64::MAX,
+            "The token ID range {} is too large.",
+            s
+        );
+        Ok(Self { start, end })
+    }
+}
+
+/// The token IDs `start..start + count`, each with its number. IDs are
+/// encoded like the fixed-size CIS-2 token IDs (`TokenIdU8` to `TokenIdU64`),
+/// i.e. as `width` bytes in little-endian order, padded with zeros beyond
+/// eight bytes.
+pub fn sequential_token_ids(
+    start: u64,
+    count: u64,
+    width: usize,
+) -> anyhow::Result<Vec<(u64, String)>> {
+    anyhow::ensure!(width > 0, "The token ID width must be positive.");
+    anyhow::ensure!(count > 0, "The token count must be positive.");
+    let last = start
+        .checked_add(count - 1)
+        .context("The token IDs overflow.")?;
+    anyhow::ensure!(
+        width >= 8 || last >> (8 * width) == 0,
+        "Token ID {} does not fit in {} byte(s).",
+        last,
+        width
+    );
+    Ok((start..=last)
+        .map(|n| {
+            let mut bytes = n.to_le_bytes().to_vec();
+            bytes.resize(width, 0);
+            (n, hex::encode(bytes))
+        })
+        .collect())
+}
+
+/// The type of the token IDs in a parameter: the type of a `token_id` field,
+/// or the element or key type of a `tokens` field.
+pub fn token_id_type(param_schema: &Type) -> Option<&Type> {
+    match param_schema {
+        Type::Struct(Fields::Named(fields)) => {
+            fields.iter().find_map(|(name, ty)| match name.as_str() {
+                "token_id" => Some(ty),
+                "tokens" => match ty {
+                    Type::List(_, element) | Type::Set(_, element) => match element.as_ref() {
+                        Type::Pair(key, _) => Some(key.as_ref()),
+                        element => Some(element),
+                    },
+                    Type::Map(_, key, _) => Some(key.as_ref()),
+                    _ => None,
+                },
+                _ => token_id_type(ty),
+            })
+        }
+        Type::Struct(Fields::Unnamed(types)) => types.iter().find_map(token_id_type),
+        Type::List(_, element) | Type::Set(_, element) => token_id_type(element),
+        _ => None,
+    }
+}
+
+/// The byte width of generated token IDs. A fixed-size token ID type in the
+/// schema determines it, otherwise `width` or one byte is used.
+pub fn token_id_width(param_schema: &Type, width: Option<usize>) -> anyhow::Result<usize> {
+    match token_id_type(param_schema) {
+        Some(Type::ByteArray(size)) => {
+            let size = *size as usize;
+            anyhow::ensure!(
+                width.is_none_or(|w| w == size),
+                "The contract's token IDs have {} bytes, not {}.",
+                size,
+                width.unwrap_or_default()
+            );
+            Ok(size)
+        }
+        Some(Type::ByteList(size_len)) => {
+            let width = width.unwrap_or(1);
+            anyhow::ensure!(
+                width <= max_len(size_len),
+                "The contract's token IDs have at most {} bytes, not {}.",
+                max_len(size_len),
+                width
+            );
+            Ok(width)
+        }
+        Some(ty) => anyhow::bail!(
+            "The contract's token IDs are of type {:?}, not bytes, so they cannot be generated.",
+            ty
+        ),
+        None => Ok(width.unwrap_or(1)),
+    }
+}
+
+/// Fail unless the hex token ID has the size of the schema's token ID type.
+pub fn check_token_id(param_schema: &Type, token_id: &str) -> anyhow::Result<()> {
+    check_token_id_size(token_id_type(param_schema), token_id)
+}
+
+/// Fail unless the hex token ID has the size of the token ID type `id_type`.
+fn check_token_id_size(id_type: Option<&Type>, token_id: &str) -> anyhow::Result<()> {
+    let len = token_id.len() / 2;
+    match id_type {
+        Some(Type::ByteArray(size)) => anyhow::ensure!(
+            len == *size as usize,
+            "Token ID {} has {} bytes, but the contract's token IDs have {}.",
+            token_id,
+            len,
+            size
+        ),
+        Some(Type::ByteList(size_len)) => anyhow::ensure!(
+            len <= max_len(size_len),
+            "Token ID {} has {} bytes, but the contract's token IDs have at most {}.",
+            token_id,
+            len,
+            max_len(size_len)
+        ),
+        _ => (),
+    }
+    Ok(())
+}
+
+/// The parameter of `tokenMetadata` querying the hex token IDs: a list of
+/// IDs, also as the only field of a struct as in the CIS-2 library. Each ID
+/// must fit the list's element type.
+pub fn token_metadata_params_json(
+    param_schema: &Type,
+    token_ids: &[String],
+) -> anyhow::Result<Value> {
+    let (field, list) = match param_schema {
+        Type::Struct(Fields::Named(fields)) if fields.len() == 1 => {
+            (Some(fields[0].0.clone()), &fields[0].1)
+        }
+        ty => (None, ty),
+    };
+    let (Type::List(_, id_type) | Type::Set(_, id_type)) = list else {
+        anyhow::bail!("The tokenMetadata parameter is not a list of token IDs.");
+    };
+    for token_id in token_ids {
+        check_token_id_size(Some(id_type), token_id)?;
+    }
+    let ids = json!(token_ids);
+    Ok(match field {
+        Some(field) => json!({ field: ids }),
+        None => ids,
+    })
+}
+
+/// The URLs of a `tokenMetadata` response, a list of `MetadataUrl`s, also as
+/// the only field of a struct, in the order of the queried IDs.
+pub fn metadata_urls_of(response: &Value) -> Option<Vec<&str>> {
+    let list = match response {
+        Value::Object(obj) if obj.len() == 1 => obj.values().next()?,
+        list => list,
+    };
+    list.as_array()?
+        .iter()
+        .map(|item| item.get("url")?.as_str())
+        .collect()
+}
+
+/// The largest length a schema size prefix allows.
+fn max_len(size_len: &SizeLength) -> usize {
+    match size_len {
+        SizeLength::U8 => u8::MAX.into(),
+        SizeLength::U16 => u16::MAX.into(),
+        SizeLength::U32 | SizeLength::U64 => u32::MAX as usize,
+    }
+}
+
+pub fn address_json(address: &Address) -> Value {
+    match address {
+        Address::Account(a) => json!({ "Account": [a.to_string()] }),
+        Address::Contract(c) => json!({ "Contract": [contract_json(c)] }),
+    }
+}
+
+fn contract_json(contract: &ContractAddress) -> Value {
+    json!({ "index": contract.index, "subindex": contract.subindex })
+}
+
+/// An address for messages, e.g. `account 3abc...` or `contract <1,0>`.
+pub fn describe_address(address: &Address) -> String {
+    match address {
+        Address::Account(a) => format!("account {}", a),
+        Address::Contract(c) => format!("contract {}", c),
+    }
+}
+
+/// A receiver of a transfer. Contracts are notified via the default hook.
+pub fn receiver_json(address: &Address) -> Value {
+    match address {
+        Address::Account(a) => json!({ "Account": [a.to_string()] }),
+        Address::Contract(c) => json!({ "Contract": [contract_json(c), DEFAULT_RECEIVE_HOOK] }),
+    }
+}
+
+/// A single transfer in the parameter of `transfer`.
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub token_id: String,
+    pub amount: u64,
+    pub from: Address,
+    pub to: Address,
+    /// Additional data passed to a receiving contract, in hex.
+    pub data: String,
+}
+
+impl Transfer {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "token_id": self.token_id,
+            "amount": self.amount.to_string(),
+            "from": address_json(&self.from),
+            "to": receiver_json(&self.to),
+            "data": self.data,
+        })
+    }
+}
+
+/// Give every transfer of the `transfer` parameter without a `data` field the
+/// `data`, and check the data of the others.
+pub fn fill_transfer_data(parameter: &mut Value, data: &str) -> anyhow::Result<()> {
+    for (i, transfer) in parameter.as_array_mut().into_iter().flatten().enumerate() {
+        let Some(transfer) = transfer.as_object_mut() else {
+            continue;
+        };
+        match transfer.get("data") {
+            Some(Value::String(existing)) => {
+                parse_transfer_data(existing)
+                    .with_context(|| format!("Transfer {} is invalid.", i + 1))?;
+            }
+            Some(_) => (),
+            None => {
+                transfer.insert("data".into(), json!(data));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The parameter of `transfer` for a list of transfers.
+pub fn transfer_params_json(transfers: &[Transfer]) -> Value {
+    Value::Array(transfers.iter().map(Transfer::to_json).collect())
+}
+
+/// Warn about every transfer in the `transfer` parameter whose `from` address
+/// is neither the `sender` nor has the sender as an operator, which the
+/// contract would reject, or fail if `abort` is set. Operators are queried
+/// with the contract's `operator_of` entrypoint.
+#[allow(clippy::too_many_arguments)]
+pub async fn check_transfer_authorized(
+    client: &mut v2::Client,
+    settings: &InvokeSettings,
+    schema: &VersionedModuleSchema,
+    contract: &str,
+    address: ContractAddress,
+    sender: AccountAddress,
+    parameter: &Value,
+    operator_of: &str,
+    abort: bool,
+) -> anyhow::Result<()> {
+    let sender_json = address_json(&Address::Account(sender));
+    let mut owners: Vec<&Value> = Vec::new();
+    for transfer in parameter.as_array().into_iter().flatten() {
+        if let Some(from) = transfer.get("from") {
+            if *from != sender_json && !owners.contains(&from) {
+                owners.push(from);
+            }
+        }
+    }
+    if owners.is_empty() {
+        return Ok(());
+    }
+    let queries: Vec<Value> = owners
+        .iter()
+        .map(|owner| json!({ "owner": owner, "address": sender_json }))
+        .collect();
+    let param_schema = schema.get_receive_param_schema(contract, operator_of)?;
+    let rv_schema = schema.get_receive_return_value_schema(contract, operator_of)?;
+    let parameter =
+        params::serialize_parameter(&param_schema, &Value::Array(queries), Checks::default())?;
+    let method = schema::receive_name(contract, operator_of, Some(schema))?;
+    let bytes =
+        instance::invoke(client, settings, address, method, parameter, Some(schema)).await?;
+    let response = rv_schema.to_json(&mut Cursor::new(&bytes[..]))?;
+    let is_operator = response
+        .as_array()
+        .context("Unexpected operatorOf response.")?;
+    let unauthorized: Vec<String> = owners
+        .iter()
+        .zip(is_operator)
+        .filter(|(_, is_operator)| **is_operator != Value::Bool(true))
+        .map(|(owner, _)| match intent::address_from_json(owner) {
+            Some(owner) => describe_address(&owner),
+            None => owner.to_string(),
+        })
+        .collect();
+    if unauthorized.is_empty() {
+        return Ok(());
+    }
+    anyhow::ensure!(
+        !abort,
+        "{} is not an operator of {}, so the contract would reject the transfer. The owner must \
+         first add it with updateOperator.",
+        sender,
+        unauthorized.join(", ")
+    );
+    for owner in unauthorized {
+        eprintln!(
+            "Warning: {} is neither the owner {} nor one of its operators, the transfer will be \
+             rejected.",
+            sender, owner
+        );
+    }
+    Ok(())
+}
+
+/// URL schemes token metadata may be fetched from.
+const METADATA_URL_SCHEMES: [&str; 3] = ["http", "https", "ipfs"];
+
+/// Fail if a string field of the parameter whose name contains `url` is not
+/// a well-formed http, https or ipfs URL, or is longer than the schema allows.
+/// Non-ASCII characters are allowed, the length is counted in bytes.
+pub fn check_metadata_urls(param_schema: &Type, parameter: &Value) -> anyhow::Result<()> {
+    let mut problems = Vec::new();
+    metadata_urls("$", false, param_schema, parameter, &mut problems);
+    anyhow::ensure!(
+        problems.is_empty(),
+        "Invalid metadata URL(s):\n  {}",
+        problems.join("\n  ")
+    );
+    Ok(())
+}
+
+fn metadata_urls(path: &str, is_url: bool, ty: &Type, value: &Value, out: &mut Vec<String>) {
+    match (ty, value) {
+        (Type::String(size_len), Value::String(url)) if is_url => {
+            let max = max_len(size_len);
+            if url.len() > max {
+                out.push(format!(
+                    "{}: {} bytes, but the contract takes at most {}",
+                    path,
+                    url.len(),
+                    max
+                ));
+            } else if let Err(e) = check_url(url) {
+                out.push(format!("{}: {}: {}", path, url, e));
+            }
+        }
+        (Type::Struct(fields), value) => field_metadata_urls(path, fields, value, out),
+        (Type::Enum(variants), Value::Object(obj)) => {
+            for (key, v) in obj {
+                if let Some((_, fields)) = variants.iter().find(|(name, _)| name == key) {
+                    field_metadata_urls(&format!("{}.{}", path, key), fields, v, out);
+                }
+            }
+        }
+        (Type::TaggedEnum(variants), Value::Object(obj)) => {
+            for (key, v) in obj {
+                if let Some((_, fields)) = variants.values().find(|(name, _)| name == key) {
+                    field_metadata_urls(&format!("{}.{}", path, key), fields, v, out);
+                }
+            }
+        }
+        (Type::List(_, item) | Type::Set(_, item) | Type::Array(_, item), Value::Array(items)) => {
+            for (i, v) in items.iter().enumerate() {
+                metadata_urls(&format!("{}[{}]", path, i), is_url, item, v, out);
+            }
+        }
+        (Type::Map(_, key, val), Value::Array(entries)) => {
+            for (i, entry) in entries.iter().enumerate() {
+                if let Some([k, v]) = entry.as_array().map(Vec::as_slice) {
+                    metadata_urls(&format!("{}[{}][0]", path, i), false, key, k, out);
+                    metadata_urls(&format!("{}[{}][1]", path, i), is_url, val, v, out);
+                }
+            }
+        }
+        (Type::Pair(a, b), Value::Array(items)) if items.len() == 2 => {
+            metadata_urls(&format!("{}[0]", path), is_url, a, &items[0], out);
+            metadata_urls(&format!("{}[1]", path), is_url, b, &items[1], out);
+        }
+        _ => (),
+    }
+}
+
+fn field_metadata_urls(path: &str, fields: &Fields, value: &Value, out: &mut Vec<String>) {
+    match (fields, value) {
+        (Fields::Named(named), Value::Object(obj)) => {
+            for (name, ty) in named {
+                if let Some(v) = obj.get(name) {
+                    let is_url = name.to_lowercase().contains("url");
+                    metadata_urls(&format!("{}.{}", path, name), is_url, ty, v, out);
+                }
+            }
+        }
+        (Fields::Unnamed(types), Value::Array(items)) => {
+            for (i, (ty, v)) in types.iter().zip(items).enumerate() {
+                metadata_urls(&format!("{}[{}]", path, i), false, ty, v, out);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Check the shape `<scheme>://<host or CID>[/path][?query][#fragment]`.
+fn check_url(url: &str) -> anyhow::Result<()> {
+    let (scheme, rest) = url
+        .split_once("://")
+        .context("expected <scheme>://..., e.g. https://example.com/1.json")?;
+    anyhow::ensure!(
+        METADATA_URL_SCHEMES.contains(&scheme),
+        "the scheme {} is not one of {}",
+        scheme,
+        METADATA_URL_SCHEMES.join(", ")
+    );
+    anyhow::ensure!(
+        !url.chars().any(|c| c.is_whitespace() || c.is_control()),
+        "it contains whitespace or control characters"
+    );
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    anyhow::ensure!(!host.is_empty(), "the host is missing");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_amounts_are_positive() {
+        assert_eq!(parse_token_amount(" 7 ").unwrap(), 7);
+        assert!(parse_token_amount("0").is_err());
+        assert!(parse_token_amount("-1").is_err());
+        assert!(parse_token_amount("1.5").is_err());
+    }
+
+    #[test]
+    fn sequential_token_ids_little_endian() {
+        assert_eq!(
+            sequential_token_ids(255, 2, 2).unwrap(),
+            [(255, "ff00".to_string()), (256, "0001".to_string())]
+        );
+        assert_eq!(
+            sequential_token_ids(1, 1, 4).unwrap(),
+            [(1, "01000000".to_string())]
+        );
+        assert_eq!(
+            sequential_token_ids(u64::MAX, 1, 10).unwrap(),
+            [(u64::MAX, "ffffffffffffffff0000".to_string())]
+        );
+    }
+
+    #[test]
+    fn sequential_token_ids_must_fit() {
+        assert!(sequential_token_ids(255, 1, 1).is_ok());
+        assert!(sequential_token_ids(255, 2, 1).is_err());
+        assert!(sequential_token_ids(1, 0, 1).is_err());
+        assert!(sequential_token_ids(u64::MAX, 2, 8).is_err());
+        assert!(sequential_token_ids(1, 1, 0).is_err());
+    }
+
+    #[test]
+    fn check_url_accepts_metadata_urls() {
+        assert!(check_url("https://example.com/1.json").is_ok());
+        assert!(check_url("http://localhost:8080?id=1").is_ok());
+        assert!(
+            check_url("ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi").is_ok()
+        );
+    }
+
+    #[test]
+    fn check_url_rejects_malformed_urls() {
+        assert!(check_url("example.com/1.json").is_err());
+        assert!(check_url("ftp://example.com/1.json").is_err());
+        assert!(check_url("https://example.com/a b.json").is_err());
+        assert!(check_url("https://example.com/1.json\n").is_err());
+        assert!(check_url("https:///1.json").is_err());
+    }
+
+    fn mint_schema(token_id: Type) -> Type {
+        Type::Struct(Fields::Named(vec![(
+            "tokens".into(),
+            Type::Map(SizeLength::U8, Box::new(token_id), Box::new(Type::U8)),
+        )]))
+    }
+
+    #[test]
+    fn token_id_width_of_a_fixed_size_type() {
+        let schema = mint_schema(Type::ByteArray(4));
+        assert_eq!(token_id_width(&schema, None).unwrap(), 4);
+        assert_eq!(token_id_width(&schema, Some(4)).unwrap(), 4);
+        assert!(token_id_width(&schema, Some(2)).is_err());
+    }
+
+    #[test]
+    fn token_id_width_of_a_variable_size_type() {
+        let schema = mint_schema(Type::ByteList(SizeLength::U8));
+        assert_eq!(token_id_width(&schema, None).unwrap(), 1);
+        assert_eq!(token_id_width(&schema, Some(255)).unwrap(), 255);
+        assert!(token_id_width(&schema, Some(256)).is_err());
+    }
+
+    #[test]
+    fn token_id_width_without_bytes() {
+        assert_eq!(token_id_width(&Type::U8, Some(3)).unwrap(), 3);
+        assert!(token_id_width(&mint_schema(Type::U32), None).is_err());
+    }
+
+    #[test]
+    fn token_id_range_is_inclusive() {
+        let range: TokenIdRange = "1..100".parse().unwrap();
+        assert_eq!(range, TokenIdRange { start: 1, end: 100 });
+        assert_eq!(range.count(), 100);
+        let range: TokenIdRange = " 3 ..= 3 ".parse().unwrap();
+        assert_eq!(range, TokenIdRange { start: 3, end: 3 });
+        assert_eq!(range.count(), 1);
+    }
+
+    #[test]
+    fn token_id_range_rejects_invalid_ranges() {
+        assert!("5..1".parse::<TokenIdRange>().is_err());
+        assert!("1-5".parse::<TokenIdRange>().is_err());
+        assert!("a..5".parse::<TokenIdRange>().is_err());
+        assert!("1..".parse::<TokenIdRange>().is_err());
+        assert!(format!("0..{}", u64::MAX).parse::<TokenIdRange>().is_err());
+    }
+}
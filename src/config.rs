@@ -0,0 +1,127 @@
+//! Defaults loaded from a TOML configuration file.
+use anyhow::Context;
+use concordium_rust_sdk::{
+    types::{ContractAddress, Energy},
+    v2,
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// Energy for executing contract transactions if neither `--energy` nor the
+/// configuration gives one.
+pub const DEFAULT_ENERGY: u64 = 10000;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Default energy per method, e.g. `mint = 50000`. The `default` entry
+    /// applies to methods without their own entry.
+    #[serde(default)]
+    pub energy: BTreeMap<String, u64>,
+    /// Entrypoint names of the contract for the built-in methods, e.g.
+    /// `mint = "mintNft"`. They apply to every entrypoint the tool calls,
+    /// including `operatorOf`, `paused` and the permit queries, except
+    /// `balanceOf`, which the SDK's CIS-2 client calls by its standard name.
+    #[serde(default)]
+    pub methods: BTreeMap<String, String>,
+    /// Named environments, e.g. `[profiles.testnet]`, selected with
+    /// `--profile`.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// The profile selected with `--profile`.
+    #[serde(skip)]
+    pub profile: Option<Profile>,
+}
+
+/// Settings of one environment. Command line flags take precedence.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// GRPC interface of the node.
+    pub node: Option<String>,
+    /// Account key files or directories.
+    #[serde(default)]
+    pub accounts: Vec<PathBuf>,
+    /// The contract to update, e.g. `"<1234,0>"`.
+    pub contract: Option<String>,
+    /// Energy per method, taking precedence over the top-level table.
+    #[serde(default)]
+    pub energy: BTreeMap<String, u64>,
+}
+
+impl Config {
+    /// Read the configuration file, or use the built-in defaults if no file is
+    /// given, and select the named profile.
+    pub fn load(path: Option<&Path>, profile: Option<&str>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            anyhow::ensure!(profile.is_none(), "--profile requires a --config file.");
+            return Ok(Self::default());
+        };
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read the config file {}.", path.display()))?;
+        let mut config: Self = toml::from_str(&text)
+            .with_context(|| format!("Could not parse the config file {}.", path.display()))?;
+        if let Some(name) = profile {
+            let selected = config.profiles.get(name).with_context(|| {
+                format!(
+                    "The config file has no profile {}. Its profiles are: {}.",
+                    name,
+                    config
+                        .profiles
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?;
+            config.profile = Some(selected.clone());
+        }
+        Ok(config)
+    }
+
+    /// The energy for calling `method`, unless given explicitly.
+    pub fn energy_for(&self, explicit: Option<Energy>, method: &str) -> Energy {
+        let profile_energy = self.profile.as_ref().map(|p| &p.energy);
+        explicit.unwrap_or_else(|| {
+            [profile_energy, Some(&self.energy)]
+                .into_iter()
+                .flatten()
+                .find_map(|energy| energy.get(method).or_else(|| energy.get("default")))
+                .copied()
+                .unwrap_or(DEFAULT_ENERGY)
+                .into()
+        })
+    }
+
+    /// The contract's entrypoint for the built-in method `name`.
+    pub fn method<'a>(&'a self, name: &'a str) -> &'a str {
+        self.methods.get(name).map_or(name, String::as_str)
+    }
+
+    /// The node of the selected profile.
+    pub fn node(&self) -> anyhow::Result<Option<v2::Endpoint>> {
+        let Some(node) = self.profile.as_ref().and_then(|p| p.node.as_deref()) else {
+            return Ok(None);
+        };
+        let endpoint = node
+            .parse()
+            .with_context(|| format!("Invalid node {} in the profile.", node))?;
+        Ok(Some(endpoint))
+    }
+
+    /// The contract of the selected profile.
+    pub fn contract(&self) -> anyhow::Result<Option<ContractAddress>> {
+        let Some(contract) = self.profile.as_ref().and_then(|p| p.contract.as_deref()) else {
+            return Ok(None);
+        };
+        let contracts = crate::portfolio::parse_contracts(contract)?;
+        anyhow::ensure!(
+            contracts.len() == 1,
+            "The profile must name a single contract."
+        );
+        Ok(contracts.first().copied())
+    }
+}
@@ -0,0 +1,136 @@
+//! Differences between two decoded contract states.
+use serde::Serialize;
+use serde_json::Value;
+
+/// A value that was added, removed or changed at a path of the state.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    /// Where in the state, e.g. `$.tokens[2].owner`.
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<Value>,
+}
+
+/// The changes from `before` to `after`. Objects are compared field by field
+/// and arrays element by element, anything else as a whole.
+pub fn diff(before: &Value, after: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_at("$", before, after, &mut changes);
+    changes
+}
+
+fn diff_at(path: &str, before: &Value, after: &Value, out: &mut Vec<Change>) {
+    match (before, after) {
+        (Value::Object(b), Value::Object(a)) => {
+            for (key, value) in b {
+                let path = format!("{}.{}", path, key);
+                match a.get(key) {
+                    Some(other) => diff_at(&path, value, other, out),
+                    None => out.push(change(path, Some(value), None)),
+                }
+            }
+            for (key, value) in a.iter().filter(|(key, _)| !b.contains_key(*key)) {
+                out.push(change(format!("{}.{}", path, key), None, Some(value)));
+            }
+        }
+        (Value::Array(b), Value::Array(a)) => {
+            for i in 0..b.len().max(a.len()) {
+                let path = format!("{}[{}]", path, i);
+                match (b.get(i), a.get(i)) {
+                    (Some(b), Some(a)) => diff_at(&path, b, a, out),
+                    (b, a) => out.push(change(path, b, a)),
+                }
+            }
+        }
+        _ if before != after => out.push(change(path.to_string(), Some(before), Some(after))),
+        _ => (),
+    }
+}
+
+fn change(path: String, before: Option<&Value>, after: Option<&Value>) -> Change {
+    Change {
+        path,
+        before: before.cloned(),
+        after: after.cloned(),
+    }
+}
+
+/// Print the changes as CSV with the values as JSON, empty where a value was
+/// added or removed.
+pub fn print_csv(changes: &[Change]) -> anyhow::Result<()> {
+    let json = |v: &Option<Value>| v.as_ref().map(Value::to_string).unwrap_or_default();
+    crate::output::print_csv(
+        &["path", "before", "after"],
+        changes
+            .iter()
+            .map(|c| vec![c.path.clone(), json(&c.before), json(&c.after)]),
+    )
+}
+
+/// Print one line per change: `+` for added, `-` for removed and `~` for
+/// changed values.
+pub fn print(changes: &[Change]) {
+    if changes.is_empty() {
+        println!("The state is unchanged.");
+    }
+    for c in changes {
+        match (&c.before, &c.after) {
+            (Some(before), Some(after)) => println!("~ {}: {} -> {}", c.path, before, after),
+            (None, Some(after)) => println!("+ {}: {}", c.path, after),
+            (Some(before), None) => println!("- {}: {}", c.path, before),
+            (None, None) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(changes: &[Change]) -> Vec<&str> {
+        changes.iter().map(|c| c.path.as_str()).collect()
+    }
+
+    #[test]
+    fn no_changes() {
+        let state = json!({ "tokens": [{ "owner": "a" }] });
+        assert!(diff(&state, &state).is_empty());
+    }
+
+    #[test]
+    fn changed_added_and_removed_fields() {
+        let before = json!({ "paused": false, "owner": "a", "nested": { "x": 1 } });
+        let after = json!({ "paused": true, "nested": { "x": 2 }, "admin": "b" });
+        let changes = diff(&before, &after);
+        assert_eq!(
+            paths(&changes),
+            ["$.nested.x", "$.owner", "$.paused", "$.admin"]
+        );
+        let owner = &changes[1];
+        assert_eq!(owner.before, Some(json!("a")));
+        assert_eq!(owner.after, None);
+        let admin = &changes[3];
+        assert_eq!(admin.before, None);
+        assert_eq!(admin.after, Some(json!("b")));
+    }
+
+    #[test]
+    fn arrays_element_by_element() {
+        let changes = diff(&json!([1, 2]), &json!([1, 3, 4]));
+        assert_eq!(paths(&changes), ["$[1]", "$[2]"]);
+        assert_eq!(changes[1].before, None);
+        let changes = diff(&json!([1, 2]), &json!([1]));
+        assert_eq!(paths(&changes), ["$[1]"]);
+        assert_eq!(changes[0].after, None);
+    }
+
+    #[test]
+    fn different_kinds_as_a_whole() {
+        let changes = diff(&json!({ "a": [1] }), &json!({ "a": "x" }));
+        assert_eq!(paths(&changes), ["$.a"]);
+    }
+}
@@ -0,0 +1,229 @@
+//! The complete effects of finalized account transactions as JSON, for tools
+//! that need more than the outcome summary.
+use anyhow::Context;
+use concordium_rust_sdk::{
+    smart_contracts::common::{schema::Type, Cursor},
+    types::{
+        hashes::TransactionHash,
+        smart_contracts::{ContractEvent, ContractTraceElement},
+        AccountTransactionEffects, BlockItemSummaryDetails, ContractAddress,
+    },
+    v2,
+};
+use serde_json::{json, Value};
+
+/// Query the finalized transaction and convert its effects to JSON.
+pub async fn fetch(
+    client: &mut v2::Client,
+    hash: &TransactionHash,
+    event_schema: Option<&Type>,
+) -> anyhow::Result<Value> {
+    let status = client.get_block_item_status(hash).await?;
+    let (_, summary) = status
+        .is_finalized()
+        .context("The transaction is not finalized.")?;
+    let BlockItemSummaryDetails::AccountTransaction(details) = &summary.details else {
+        anyhow::bail!("Transaction {} is not an account transaction.", hash);
+    };
+    to_json(&details.effects, event_schema)
+}
+
+/// The effects with a `type` tag per variant. Events of the contract the
+/// transaction created or called are decoded with the `event_schema`, if it
+/// decodes them, next to their hex bytes.
+pub fn to_json(
+    effects: &AccountTransactionEffects,
+    event_schema: Option<&Type>,
+) -> anyhow::Result<Value> {
+    let value = match effects {
+        AccountTransactionEffects::None {
+            transaction_type,
+            reject_reason,
+        } => json!({
+            "type": "none",
+            "transactionType": transaction_type,
+            "rejectReason": reject_reason,
+        }),
+        AccountTransactionEffects::ModuleDeployed { module_ref } => json!({
+            "type": "moduleDeployed",
+            "moduleRef": module_ref,
+        }),
+        AccountTransactionEffects::ContractInitialized { data } => {
+            let mut value = serde_json::to_value(data)?;
+            value["events"] = events_json(event_schema, &data.events);
+            json!({ "type": "contractInitialized", "data": value })
+        }
+        AccountTransactionEffects::ContractUpdateIssued { effects } => {
+            let called = effects.iter().find_map(|element| match element {
+                ContractTraceElement::Updated { data } => Some(data.address),
+                ContractTraceElement::Interrupted { address, .. } => Some(*address),
+                _ => None,
+            });
+            let trace = effects
+                .iter()
+                .map(|element| trace_element_json(element, called, event_schema))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            json!({ "type": "contractUpdateIssued", "effects": trace })
+        }
+        AccountTransactionEffects::AccountTransfer { amount, to } => json!({
+            "type": "accountTransfer",
+            "amount": amount,
+            "to": to,
+        }),
+        AccountTransactionEffects::AccountTransferWithMemo { amount, to, memo } => json!({
+            "type": "accountTransferWithMemo",
+            "amount": amount,
+            "to": to,
+            "memo": memo,
+        }),
+        AccountTransactionEffects::BakerAdded { data } => json!({
+            "type": "bakerAdded",
+            "data": data,
+        }),
+        AccountTransactionEffects::BakerRemoved { baker_id } => json!({
+            "type": "bakerRemoved",
+            "bakerId": baker_id,
+        }),
+        AccountTransactionEffects::BakerStakeUpdated { data } => json!({
+            "type": "bakerStakeUpdated",
+            "data": data.map(|d| json!({
+                "bakerId": d.baker_id,
+                "newStake": d.new_stake,
+                "increased": d.increased,
+            })),
+        }),
+        AccountTransactionEffects::BakerRestakeEarningsUpdated {
+            baker_id,
+            restake_earnings,
+        } => json!({
+            "type": "bakerRestakeEarningsUpdated",
+            "bakerId": baker_id,
+            "restakeEarnings": restake_earnings,
+        }),
+        AccountTransactionEffects::BakerKeysUpdated { data } => json!({
+            "type": "bakerKeysUpdated",
+            "data": data,
+        }),
+        AccountTransactionEffects::EncryptedAmountTransferred { removed, added } => json!({
+            "type": "encryptedAmountTransferred",
+            "removed": removed,
+            "added": added,
+        }),
+        AccountTransactionEffects::EncryptedAmountTransferredWithMemo {
+            removed,
+            added,
+            memo,
+        } => json!({
+            "type": "encryptedAmountTransferredWithMemo",
+            "removed": removed,
+            "added": added,
+            "memo": memo,
+        }),
+        AccountTransactionEffects::TransferredToEncrypted { data } => json!({
+            "type": "transferredToEncrypted",
+            "data": data,
+        }),
+        AccountTransactionEffects::TransferredToPublic { removed, amount } => json!({
+            "type": "transferredToPublic",
+            "removed": removed,
+            "amount": amount,
+        }),
+        AccountTransactionEffects::TransferredWithSchedule { to, amount } => json!({
+            "type": "transferredWithSchedule",
+            "to": to,
+            "amount": amount,
+        }),
+        AccountTransactionEffects::TransferredWithScheduleAndMemo { to, amount, memo } => json!({
+            "type": "transferredWithScheduleAndMemo",
+            "to": to,
+            "amount": amount,
+            "memo": memo,
+        }),
+        AccountTransactionEffects::CredentialKeysUpdated { cred_id } => json!({
+            "type": "credentialKeysUpdated",
+            "credId": cred_id,
+        }),
+        AccountTransactionEffects::CredentialsUpdated {
+            new_cred_ids,
+            removed_cred_ids,
+            new_threshold,
+        } => json!({
+            "type": "credentialsUpdated",
+            "newCredIds": new_cred_ids,
+            "removedCredIds": removed_cred_ids,
+            "newThreshold": new_threshold,
+        }),
+        AccountTransactionEffects::DataRegistered { data } => json!({
+            "type": "dataRegistered",
+            "data": data,
+        }),
+        // The SDK offers no JSON form of baker and delegation events.
+        AccountTransactionEffects::BakerConfigured { data } => json!({
+            "type": "bakerConfigured",
+            "data": data.iter().map(|e| format!("{:?}", e)).collect::<Vec<_>>(),
+        }),
+        AccountTransactionEffects::DelegationConfigured { data } => json!({
+            "type": "delegationConfigured",
+            "data": data.iter().map(|e| format!("{:?}", e)).collect::<Vec<_>>(),
+        }),
+    };
+    Ok(value)
+}
+
+/// One element of an update's trace. Only events of the `called` contract
+/// are decoded, since other contracts have their own schemas.
+fn trace_element_json(
+    element: &ContractTraceElement,
+    called: Option<ContractAddress>,
+    event_schema: Option<&Type>,
+) -> anyhow::Result<Value> {
+    let schema_for = |address: ContractAddress| {
+        if Some(address) == called {
+            event_schema
+        } else {
+            None
+        }
+    };
+    let value = match element {
+        ContractTraceElement::Updated { data } => {
+            let mut value = serde_json::to_value(data)?;
+            value["events"] = events_json(schema_for(data.address), &data.events);
+            json!({ "type": "updated", "data": value })
+        }
+        ContractTraceElement::Transferred { from, amount, to } => json!({
+            "type": "transferred",
+            "from": from,
+            "amount": amount,
+            "to": to,
+        }),
+        ContractTraceElement::Interrupted { address, events } => json!({
+            "type": "interrupted",
+            "address": address,
+            "events": events_json(schema_for(*address), events),
+        }),
+        ContractTraceElement::Resumed { address, success } => json!({
+            "type": "resumed",
+            "address": address,
+            "success": success,
+        }),
+        ContractTraceElement::Upgraded { address, from, to } => json!({
+            "type": "upgraded",
+            "address": address,
+            "from": from,
+            "to": to,
+        }),
+    };
+    Ok(value)
+}
+
+/// Events with their hex bytes and, if the schema decodes them, their JSON.
+fn events_json(event_schema: Option<&Type>, events: &[ContractEvent]) -> Value {
+    events
+        .iter()
+        .map(|event| {
+            let bytes: &[u8] = event.as_ref();
+            let decoded = event_schema.and_then(|ty| ty.to_json(&mut Cursor::new(bytes)).ok());
+            json!({ "hex": hex::encode(bytes), "decoded": decoded })
+        })
+        .collect()
+}
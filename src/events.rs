@@ -0,0 +1,113 @@
+//! Decoding the events contracts log.
+use crate::output::{self, Printer, Style};
+use concordium_rust_sdk::{
+    smart_contracts::common::{schema::Type, Cursor},
+    types::{
+        smart_contracts::{ContractEvent, ContractTraceElement},
+        AccountTransactionEffects, BlockItemSummary, BlockItemSummaryDetails, ContractAddress,
+    },
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// An event logged by a contract, decoded if its schema is known.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedEvent {
+    pub contract: ContractAddress,
+    /// The event as JSON, or `{"raw": "<hex>"}` if it cannot be decoded.
+    pub event: Value,
+}
+
+/// Decode one event with the event schema, falling back to its hex bytes.
+pub fn decode_event(event_schema: Option<&Type>, event: &ContractEvent) -> Value {
+    let bytes: &[u8] = event.as_ref();
+    event_schema
+        .and_then(|ty| ty.to_json(&mut Cursor::new(bytes)).ok())
+        .unwrap_or_else(|| serde_json::json!({ "raw": hex::encode(bytes) }))
+}
+
+/// All events in the trace of an update. Only events of `contract` are decoded
+/// with the `event_schema`, since other contracts have their own schemas.
+pub fn decode_trace(
+    contract: ContractAddress,
+    event_schema: Option<&Type>,
+    trace: &[ContractTraceElement],
+) -> Vec<DecodedEvent> {
+    let mut decoded = Vec::new();
+    for element in trace {
+        let (address, events) = match element {
+            ContractTraceElement::Updated { data } => (data.address, &data.events),
+            ContractTraceElement::Interrupted { address, events } => (*address, events),
+            _ => continue,
+        };
+        let schema = if address == contract {
+            event_schema
+        } else {
+            None
+        };
+        decoded.extend(events.iter().map(|event| DecodedEvent {
+            contract: address,
+            event: decode_event(schema, event),
+        }));
+    }
+    decoded
+}
+
+/// All events logged by a finalized transaction. Only events of the contract
+/// the transaction created or called are decoded with the `event_schema`.
+pub fn decode_summary(
+    event_schema: Option<&Type>,
+    summary: &BlockItemSummary,
+) -> Vec<DecodedEvent> {
+    let BlockItemSummaryDetails::AccountTransaction(details) = &summary.details else {
+        return Vec::new();
+    };
+    match &details.effects {
+        AccountTransactionEffects::ContractInitialized { data } => data
+            .events
+            .iter()
+            .map(|event| DecodedEvent {
+                contract: data.address,
+                event: decode_event(event_schema, event),
+            })
+            .collect(),
+        AccountTransactionEffects::ContractUpdateIssued { effects } => {
+            let called = effects.iter().find_map(|element| match element {
+                ContractTraceElement::Updated { data } => Some(data.address),
+                ContractTraceElement::Interrupted { address, .. } => Some(*address),
+                _ => None,
+            });
+            called
+                .map(|contract| decode_trace(contract, event_schema, effects))
+                .unwrap_or_default()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Print the events as CSV with the events as JSON.
+pub fn print_events_csv(events: &[DecodedEvent]) -> anyhow::Result<()> {
+    output::print_csv(
+        &["contract", "event"],
+        events
+            .iter()
+            .map(|e| vec![e.contract.to_string(), e.event.to_string()]),
+    )
+}
+
+pub fn print_events(events: &[DecodedEvent], printer: &Printer) {
+    let width = events
+        .iter()
+        .map(|e| e.contract.to_string().len())
+        .max()
+        .unwrap_or_default();
+    for event in events {
+        let contract = format!("{:<width$}", event.contract.to_string(), width = width);
+        println!(
+            "  {}  {}",
+            printer.paint(&contract, Style::Dim),
+            event.event
+        );
+    }
+}
@@ -0,0 +1,753 @@
+//! Plain-English descriptions of what a command would do, built from local
+//! inputs only.
+use crate::{accounts, admin, batch, cis2, config, module, params, schema, submit, Action, App};
+use crate::{TransactionType, CONTRACT_NAME};
+use anyhow::Context;
+use concordium_rust_sdk::{smart_contracts::common::Amount, types::ContractAddress};
+use serde_json::Value;
+
+/// Describe `action` without connecting to a node. The inputs are read and
+/// validated as the command itself would, so errors surface here as well.
+pub async fn describe(app: &App, action: &Action) -> anyhow::Result<String> {
+    let config = config::Config::load(app.config.as_deref(), app.profile.as_deref())?;
+    let energy_for = |method: &str| config.energy_for(app.energy, method);
+    let entrypoint = |method: &'static str| config.method(method);
+    let mut lines = Vec::new();
+    match action {
+        Action::Deploy {
+            module_path,
+            estimate,
+            ..
+        } => {
+            let paths = if module_path.is_dir() {
+                module::modules_in_dir(module_path)?
+            } else {
+                vec![module_path.clone()]
+            };
+            anyhow::ensure!(
+                !paths.is_empty(),
+                "No .wasm.v1 modules found in {}.",
+                module_path.display()
+            );
+            let verb = if *estimate {
+                "Will estimate the cost of deploying"
+            } else {
+                "Will deploy"
+            };
+            for path in paths {
+                let module = module::read_module(&path)?;
+                lines.push(format!(
+                    "{} the module {} ({} bytes) with reference {}.",
+                    verb,
+                    path.display(),
+                    module.source.size(),
+                    module.get_module_ref()
+                ));
+            }
+        }
+        Action::Init { module_ref, amount } => {
+            lines.push(format!(
+                "Will create an instance of contract {} from module {}, paying up to {} energy.",
+                CONTRACT_NAME,
+                module_ref,
+                energy_for("init")
+            ));
+            if *amount != Amount::zero() {
+                lines.push(format!("Will fund the instance with {} CCD.", amount));
+            }
+        }
+        Action::WithSchema {
+            parameter,
+            schema,
+            address,
+            transaction_type_,
+            verify_roundtrip,
+            follow_operator_chain,
+            check_paused,
+            amount,
+            from_file,
+            token_id_start,
+            parameter_from_return,
+            from_field,
+            token_ids,
+            fetch_metadata,
+            ipfs_gateways,
+            token_metadata_hash_check,
+            parameter_items,
+            parameter_base64,
+            data,
+            count,
+            token_id_width,
+            metadata_url,
+            max_items_per_tx,
+            dry_run_all,
+            continue_on_dry_run_failure,
+            new_module_ref,
+            migrate,
+            new_owner,
+            role,
+            role_address,
+            permit_signer,
+            permit_entrypoint,
+            ..
+        } => {
+            let address = &app.contract(*address)?;
+            let schema = app.schemas.load(schema).await?;
+            if let Some(source) = parameter_from_return {
+                let parameter = parameter
+                    .as_deref()
+                    .map(|p| params::read_json(p, app.parameter_format))
+                    .transpose()?;
+                let param_schema = schema::receive_param_schema(&schema, CONTRACT_NAME, source)?;
+                params::serialize_optional(
+                    source,
+                    param_schema.as_ref(),
+                    parameter.as_ref(),
+                    app.checks(false),
+                )?;
+                schema::receive_name(CONTRACT_NAME, source, Some(&schema))?;
+                let field = from_field.as_deref().unwrap_or("$");
+                lines.push(format!(
+                    "Will query {} of contract {} and use {} of its return value as the parameter \
+                     of the update. The parameter is only known once the query has run.",
+                    source, address, field
+                ));
+            } else if let Some(raw) = parameter_base64 {
+                let method = match transaction_type_ {
+                    TransactionType::Mint => entrypoint("mint"),
+                    TransactionType::Transfer => entrypoint("transfer"),
+                    _ => anyhow::bail!(
+                        "--parameter-base64 is only supported for the Mint and Transfer \
+                         transaction types."
+                    ),
+                };
+                let raw = params::read_base64(raw)?;
+                schema::receive_name(CONTRACT_NAME, method, Some(&schema))?;
+                lines.push(format!(
+                    "Will call {} of contract {} with the given {} byte parameter as it is, \
+                     paying up to {} energy.",
+                    method,
+                    address,
+                    raw.as_ref().len(),
+                    energy_for(method)
+                ));
+            } else if let Some(from_file) = from_file {
+                let transfers =
+                    batch::read_transfers(from_file, data.as_deref().unwrap_or_default())?;
+                let param_schema =
+                    schema.get_receive_param_schema(CONTRACT_NAME, entrypoint("transfer"))?;
+                batch::transfer_items(&param_schema, &transfers)?;
+                lines.push(format!(
+                    "Will submit {} transfer transaction(s) on contract {}, each paying up to {} \
+                     energy:",
+                    transfers.len(),
+                    address,
+                    energy_for("transfer")
+                ));
+                for t in &transfers {
+                    lines.push(format!(
+                        "  {} of token {} from {} to {}",
+                        t.amount,
+                        token(&t.token_id),
+                        cis2::describe_address(&t.from),
+                        cis2::describe_address(&t.to)
+                    ));
+                }
+            } else if let Some(start) = token_id_start {
+                let template = params::read_json(
+                    parameter
+                        .as_ref()
+                        .context("--parameter is required as the template of the mints.")?,
+                    app.parameter_format,
+                )?;
+                let param_schema =
+                    schema.get_receive_param_schema(CONTRACT_NAME, entrypoint("mint"))?;
+                let token_ids = cis2::sequential_token_ids(
+                    *start,
+                    count.context("--count is required.")?,
+                    cis2::token_id_width(&param_schema, *token_id_width)?,
+                )?;
+                batch::mint_items(
+                    &param_schema,
+                    &template,
+                    &token_ids,
+                    metadata_url.as_deref(),
+                    app.checks(false),
+                )?;
+                let (first, last) = (&token_ids[0].1, &token_ids[token_ids.len() - 1].1);
+                lines.push(format!(
+                    "Will mint {} token(s) from {} to {} on contract {}, one transaction each \
+                     paying up to {} energy.",
+                    token_ids.len(),
+                    token(first),
+                    token(last),
+                    address,
+                    energy_for("mint")
+                ));
+            } else if let TransactionType::Upgrade = transaction_type_ {
+                let module_ref =
+                    new_module_ref.context("--new-module-ref is required for an upgrade.")?;
+                let parameter = parameter
+                    .as_deref()
+                    .map(|p| params::read_json(p, app.parameter_format))
+                    .transpose()?;
+                let migration = match migrate {
+                    Some(entrypoint) => {
+                        let param_schema =
+                            schema::receive_param_schema(&schema, CONTRACT_NAME, entrypoint)?;
+                        Some(params::serialize_optional(
+                            entrypoint,
+                            param_schema.as_ref(),
+                            parameter.as_ref(),
+                            app.checks(*verify_roundtrip),
+                        )?)
+                    }
+                    None => None,
+                };
+                let upgrade = admin::upgrade_params_json(
+                    module_ref,
+                    migrate.as_deref().zip(migration.as_ref()),
+                );
+                schema::receive_name(CONTRACT_NAME, entrypoint("upgrade"), Some(&schema))?;
+                let param_schema =
+                    schema::receive_param_schema(&schema, CONTRACT_NAME, entrypoint("upgrade"))?;
+                params::serialize_optional(
+                    entrypoint("upgrade"),
+                    param_schema.as_ref(),
+                    Some(&upgrade),
+                    app.checks(*verify_roundtrip),
+                )?;
+                lines.push(format!(
+                    "Will upgrade contract {} to module {}, paying up to {} energy. The module \
+                     must already be deployed.",
+                    address,
+                    module_ref,
+                    energy_for("upgrade")
+                ));
+                if let Some(entrypoint) = migrate {
+                    lines.push(format!(
+                        "After the upgrade the contract calls {} of the new module.",
+                        entrypoint
+                    ));
+                }
+            } else if let TransactionType::Pause | TransactionType::Unpause = transaction_type_ {
+                let paused = matches!(transaction_type_, TransactionType::Pause);
+                let (method, parameter) =
+                    admin::pause_call(&schema, CONTRACT_NAME, paused, &config)?;
+                let param_schema =
+                    schema::receive_param_schema(&schema, CONTRACT_NAME, entrypoint(method))?;
+                params::serialize_optional(
+                    entrypoint(method),
+                    param_schema.as_ref(),
+                    parameter.as_ref(),
+                    app.checks(*verify_roundtrip),
+                )?;
+                lines.push(format!(
+                    "Will {} contract {} by calling {}, paying up to {} energy.",
+                    if paused { "pause" } else { "unpause" },
+                    address,
+                    entrypoint(method),
+                    energy_for(method)
+                ));
+            } else if let TransactionType::TransferOwnership
+            | TransactionType::GrantRole
+            | TransactionType::RevokeRole = transaction_type_
+            {
+                let change = crate::admin_change(
+                    transaction_type_,
+                    *new_owner,
+                    role.clone(),
+                    *role_address,
+                )?;
+                let method = entrypoint(change.method());
+                let parameter = change.parameter(&schema, CONTRACT_NAME, method)?;
+                schema::receive_name(CONTRACT_NAME, method, Some(&schema))?;
+                let param_schema = schema::receive_param_schema(&schema, CONTRACT_NAME, method)?;
+                params::serialize_optional(
+                    method,
+                    param_schema.as_ref(),
+                    Some(&parameter),
+                    app.checks(*verify_roundtrip),
+                )?;
+                lines.push(format!(
+                    "Will {} by calling {}, paying up to {} energy, after asking for confirmation.",
+                    change.describe(*address),
+                    method,
+                    energy_for(change.method())
+                ));
+            } else if let TransactionType::Permit = transaction_type_ {
+                let signer = permit_signer
+                    .as_ref()
+                    .context("--permit-signer is required for a permit.")?;
+                let signer = accounts::load_keys(std::slice::from_ref(signer))?
+                    .pop()
+                    .context("--permit-signer must be a single key file.")?;
+                let entrypoint = permit_entrypoint
+                    .as_deref()
+                    .context("--permit-entrypoint is required for a permit.")?;
+                schema::receive_name(CONTRACT_NAME, config.method("permit"), Some(&schema))?;
+                schema::receive_name(CONTRACT_NAME, entrypoint, Some(&schema))?;
+                let parameter = parameter
+                    .as_deref()
+                    .map(|p| params::read_json(p, app.parameter_format))
+                    .transpose()?;
+                let param_schema =
+                    schema::receive_param_schema(&schema, CONTRACT_NAME, entrypoint)?;
+                params::serialize_optional(
+                    entrypoint,
+                    param_schema.as_ref(),
+                    parameter.as_ref(),
+                    app.checks(*verify_roundtrip),
+                )?;
+                lines.push(format!(
+                    "Will call {} of contract {} on behalf of account {} through permit, with a \
+                     message signed by its keys, paying up to {} energy.",
+                    entrypoint,
+                    address,
+                    signer.address,
+                    energy_for("permit")
+                ));
+            } else {
+                let method = match transaction_type_ {
+                    TransactionType::Mint => "mint",
+                    TransactionType::Transfer => "transfer",
+                    TransactionType::TokenMetadata => "tokenMetadata",
+                    TransactionType::View => "view",
+                    TransactionType::Upgrade
+                    | TransactionType::Pause
+                    | TransactionType::Unpause
+                    | TransactionType::TransferOwnership
+                    | TransactionType::GrantRole
+                    | TransactionType::RevokeRole
+                    | TransactionType::Permit => unreachable!("Described above."),
+                };
+                schema::receive_name(CONTRACT_NAME, entrypoint(method), Some(&schema))?;
+                let param_schema =
+                    schema::receive_param_schema(&schema, CONTRACT_NAME, entrypoint(method))?;
+                let mut parameter = match (&param_schema, token_ids.is_empty()) {
+                    (Some(param_schema), false) => {
+                        Some(cis2::token_metadata_params_json(param_schema, token_ids)?)
+                    }
+                    _ if !parameter_items.is_empty() => Some(params::wrap_items(
+                        entrypoint(method),
+                        param_schema.as_ref(),
+                        parameter_items.clone(),
+                    )?),
+                    _ => parameter
+                        .as_deref()
+                        .map(|p| params::read_json(p, app.parameter_format))
+                        .transpose()?,
+                };
+                if let (TransactionType::Transfer, Some(parameter)) =
+                    (transaction_type_, &mut parameter)
+                {
+                    cis2::fill_transfer_data(parameter, data.as_deref().unwrap_or_default())?;
+                }
+                let mut chunks = None;
+                if let (TransactionType::Mint, Some(param_schema), Some(parameter)) =
+                    (transaction_type_, &param_schema, &parameter)
+                {
+                    cis2::check_metadata_urls(param_schema, parameter)?;
+                    chunks = batch::split_mint(
+                        param_schema,
+                        parameter,
+                        *max_items_per_tx,
+                        app.checks(false),
+                    )?
+                    .map(|items| items.len());
+                }
+                params::serialize_optional(
+                    entrypoint(method),
+                    param_schema.as_ref(),
+                    parameter.as_ref(),
+                    app.checks(*verify_roundtrip),
+                )?;
+                let paying = format!(", paying up to {} energy", energy_for(method));
+                match transaction_type_ {
+                    TransactionType::Mint => lines.push(format!(
+                        "Will mint {} on contract {}{}.",
+                        describe_mint(parameter.as_ref()),
+                        address,
+                        paying
+                    )),
+                    TransactionType::Transfer => {
+                        lines.push(format!(
+                            "Will transfer tokens on contract {}{}:",
+                            address, paying
+                        ));
+                        for t in parameter.iter().flat_map(|p| p.as_array()).flatten() {
+                            lines.push(format!(
+                                "  {} of token {} from {} to {}",
+                                string_field(t, "amount"),
+                                token(&string_field(t, "token_id")),
+                                address_field(t, "from"),
+                                address_field(t, "to")
+                            ));
+                        }
+                    }
+                    TransactionType::TokenMetadata | TransactionType::View => lines.push(format!(
+                        "Will query {} of contract {} and print the result. No transaction is \
+                         sent.",
+                        entrypoint(method),
+                        address
+                    )),
+                    TransactionType::Upgrade
+                    | TransactionType::Pause
+                    | TransactionType::Unpause
+                    | TransactionType::TransferOwnership
+                    | TransactionType::GrantRole
+                    | TransactionType::RevokeRole
+                    | TransactionType::Permit => unreachable!("Described above."),
+                }
+                if let Some(chunks) = chunks {
+                    lines.push(format!(
+                        "The mint is split into {} transactions, each within the parameter size \
+                         limit.",
+                        chunks
+                    ));
+                }
+            }
+            if *amount != Amount::zero() {
+                lines.push(format!("Will send {} CCD with each update.", amount));
+            }
+            if *check_paused {
+                lines.push(format!(
+                    "Will first query {} and abort without submitting if the contract is paused.",
+                    entrypoint("paused")
+                ));
+            }
+            if *token_metadata_hash_check {
+                lines.push(
+                    "After the mint is finalized, will fetch the metadata of every token logged \
+                     with a hash and fail if its SHA-256 differs."
+                        .into(),
+                );
+            }
+            if *follow_operator_chain {
+                lines.push(
+                    "Will first query operatorOf and abort without submitting if the sender may \
+                     not transfer the tokens of an owner."
+                        .into(),
+                );
+            }
+            if *fetch_metadata {
+                lines.push(format!(
+                    "Will fetch the metadata JSON at each URL, trying the IPFS gateways {} in \
+                     turn.",
+                    ipfs_gateways.join(", ")
+                ));
+            }
+            if let Some(data) = data {
+                lines.push(format!(
+                    "Transfers that give no data pass the {} byte(s) {} to receiving contracts.",
+                    data.len() / 2,
+                    data
+                ));
+            }
+            if *dry_run_all {
+                lines.push(
+                    if *continue_on_dry_run_failure {
+                        "A batch is first simulated item by item, and submitted even if some \
+                         items would be rejected."
+                    } else {
+                        "A batch is first simulated item by item, and nothing is submitted \
+                         unless every item would succeed."
+                    }
+                    .into(),
+                );
+            }
+        }
+        Action::ChainParams => lines.push("Will print the chain parameters.".into()),
+        Action::Version => {
+            lines.push("Will print the versions of this tool, the SDK and the node.".into())
+        }
+        Action::StateSize { address } => lines.push(format!(
+            "Will report the state size of contract {}.",
+            address
+        )),
+        Action::VerifyModule {
+            address,
+            module_path,
+        } => {
+            let module = module::read_module(module_path)?;
+            lines.push(format!(
+                "Will fetch the module contract {} runs and compare its reference with {} of \
+                 {}.",
+                address,
+                module.get_module_ref(),
+                module_path.display()
+            ));
+        }
+        Action::UpgradeHistory {
+            address,
+            from_block,
+            to_block,
+        } => {
+            lines.push(format!(
+                "Will scan every block from {} to {} for upgrades of contract {} and list the \
+                 modules it ran.",
+                from_block,
+                to_block,
+                app.contract(*address)?
+            ));
+        }
+        Action::AccountHistory {
+            account,
+            from_block,
+            to_block,
+        } => {
+            lines.push(format!(
+                "Will scan every block from {} to {} for transactions affecting account {} and \
+                 export them.",
+                from_block, to_block, account
+            ));
+        }
+        Action::SubmitRaw { hex, file } => {
+            let bytes = match (hex, file) {
+                (Some(hex), _) => hex.as_bytes().to_vec(),
+                (None, Some(file)) => {
+                    std::fs::read(file).context("Unable to read the transaction file.")?
+                }
+                (None, None) => anyhow::bail!("Either --hex or --file is required."),
+            };
+            let item = submit::parse_block_item(&bytes)?;
+            lines.push(format!(
+                "Will submit the signed transaction {} and wait for it to finalize.",
+                item.hash()
+            ));
+        }
+        Action::DecodeReturnValue { method, .. } => {
+            lines.push(format!("Will decode a return value of {} locally.", method))
+        }
+        Action::EncodeParameter { method, .. } => {
+            lines.push(format!("Will serialize a parameter of {} locally.", method))
+        }
+        Action::ValidateParameters { method, .. } => {
+            lines.push(format!("Will check parameter files of {} locally.", method))
+        }
+        Action::ModuleRef { module_path } => lines.push(format!(
+            "Will print the reference of the module {}.",
+            module_path.display()
+        )),
+        Action::EmbedSchema {
+            module_path,
+            schema,
+            out,
+        } => lines.push(format!(
+            "Will embed the schema {} into the module {} and write the result to {}.",
+            schema.display(),
+            module_path.display(),
+            out.display()
+        )),
+        Action::ListMethods { contract, .. } => lines.push(format!(
+            "Will list the entrypoints of {} that the schema describes.",
+            contract
+        )),
+        Action::Balances {
+            address,
+            token_id,
+            owners,
+            owners_file,
+        } => {
+            let owners = crate::portfolio::parse_owners(owners.as_deref(), owners_file.as_deref())?;
+            lines.push(format!(
+                "Will query balanceOf of contract {} for token {} and {} owner(s).",
+                app.contract(*address)?,
+                token_id,
+                owners.len()
+            ));
+        }
+        Action::Holdings {
+            owner,
+            contracts,
+            token_ids,
+            token_id_range,
+            token_id_width,
+        } => {
+            let contracts = crate::portfolio::parse_contracts(contracts)?;
+            let count = match token_id_range {
+                Some(range) => crate::portfolio::range_token_ids(*range, *token_id_width)?.len(),
+                None => token_ids.len(),
+            };
+            lines.push(format!(
+                "Will query the balance of {} token(s) of {} in {} contract(s).",
+                count,
+                cis2::describe_address(owner),
+                contracts.len()
+            ));
+        }
+        Action::Supply {
+            schema,
+            address,
+            method,
+        } => {
+            let address = app.contract(*address)?;
+            let schema = app.schemas.load(schema).await?;
+            let method = crate::supply::method(
+                &schema,
+                CONTRACT_NAME,
+                method
+                    .as_deref()
+                    .or(config.methods.get("supply").map(String::as_str)),
+            )?;
+            lines.push(format!(
+                "Will query {} of contract {} and print the supply it reports. No transaction \
+                 is sent.",
+                method, address
+            ));
+        }
+        Action::StateDiff {
+            schema,
+            address,
+            block_before,
+            block_after,
+        } => {
+            let address = app.contract(*address)?;
+            let schema = app.schemas.load(schema).await?;
+            schema::receive_name(CONTRACT_NAME, entrypoint("view"), Some(&schema))?;
+            lines.push(format!(
+                "Will query {} of contract {} in {} and in {} and print what changed.",
+                entrypoint("view"),
+                address,
+                block_before,
+                block_after
+            ));
+        }
+        Action::Events { tx, schema } => {
+            app.schemas.load(schema).await?;
+            lines.push(format!(
+                "Will print the events the finalized transaction {} logged.",
+                tx
+            ));
+        }
+        Action::Repl => lines.push("Will read commands from stdin on one connection.".into()),
+    }
+    if sends_transaction(action) {
+        lines.push(sender_line(app)?);
+        if let Some(max_fee) = app.max_fee {
+            lines.push(format!(
+                "The execution energy is instead derived from --max-fee so that the fee is at \
+                 most {} CCD.",
+                max_fee
+            ));
+        }
+        if app.dry_run {
+            lines.push("With --dry-run the update is only simulated.".into());
+        } else if app.parameter_validate_only {
+            lines.push(
+                "With --parameter-validate-only the parameter is only serialized and printed \
+                 decoded back to JSON."
+                    .into(),
+            );
+        } else if app.build_unsigned {
+            lines.push("With --build-unsigned the transaction is only built, not sent.".into());
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+fn sends_transaction(action: &Action) -> bool {
+    match action {
+        Action::Deploy { estimate, .. } => !estimate,
+        Action::Init { .. } => true,
+        Action::WithSchema {
+            transaction_type_, ..
+        } => matches!(
+            transaction_type_,
+            TransactionType::Mint
+                | TransactionType::Transfer
+                | TransactionType::Upgrade
+                | TransactionType::Pause
+                | TransactionType::Unpause
+                | TransactionType::TransferOwnership
+                | TransactionType::GrantRole
+                | TransactionType::RevokeRole
+                | TransactionType::Permit
+        ),
+        _ => false,
+    }
+}
+
+/// Which account signs, from the key files or --sender.
+fn sender_line(app: &App) -> anyhow::Result<String> {
+    let keys = accounts::load_keys(&app.keys_path)?;
+    let sender = match (app.sender, keys.first()) {
+        (Some(sender), _) => sender,
+        (None, Some(keys)) => keys.address,
+        (None, None) => anyhow::bail!("Either --account or --sender is required."),
+    };
+    if let Some(url) = &app.signer_url {
+        return Ok(format!(
+            "Sent by account {}, signed by the external signer at {}.",
+            sender, url
+        ));
+    }
+    if let Some(key) = &app.signing_key {
+        accounts::signing_key_account(key, sender)?;
+        return Ok(format!(
+            "Sent by account {}, signed with the --signing-key.",
+            sender
+        ));
+    }
+    Ok(match keys.len() {
+        0 | 1 => format!("Sent by account {}.", sender),
+        n => format!(
+            "Sent by account {}, batches by the {} given accounts.",
+            sender, n
+        ),
+    })
+}
+
+/// The recipient and tokens of a `mint` parameter with `owner` and `tokens`
+/// fields, as in the CIS-2 minting tutorial.
+fn describe_mint(parameter: Option<&Value>) -> String {
+    let Some(parameter) = parameter else {
+        return "tokens".into();
+    };
+    let tokens: Vec<String> = match parameter.get("tokens") {
+        Some(Value::Array(tokens)) => tokens
+            .iter()
+            .map(|t| token(t.as_str().unwrap_or_default()))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let tokens = match tokens.len() {
+        0 => "tokens".into(),
+        1 => format!("token {}", tokens[0]),
+        _ => format!("tokens {}", tokens.join(", ")),
+    };
+    match parameter.get("owner") {
+        Some(_) => format!("{} to {}", tokens, address_field(parameter, "owner")),
+        None => tokens,
+    }
+}
+
+fn token(id: &str) -> String {
+    format!("0x{}", id)
+}
+
+fn string_field(value: &Value, key: &str) -> String {
+    match value.get(key) {
+        Some(Value::String(s)) => s.clone(),
+        Some(v) => v.to_string(),
+        None => "?".into(),
+    }
+}
+
+/// An address in the schema JSON of `Address` or `Receiver`, e.g.
+/// `{"Account": ["3abc..."]}`.
+fn address_field(value: &Value, key: &str) -> String {
+    let Some(address) = value.get(key) else {
+        return "?".into();
+    };
+    if let Some(account) = address["Account"][0].as_str() {
+        return format!("account {}", account);
+    }
+    let contract = &address["Contract"][0];
+    match (contract["index"].as_u64(), contract["subindex"].as_u64()) {
+        (Some(index), Some(subindex)) => {
+            format!("contract {}", ContractAddress::new(index, subindex))
+        }
+        _ => address.to_string(),
+    }
+}
@@ -0,0 +1,304 @@
+//! Inspecting deployed contract instances.
+use crate::{params, schema};
+use anyhow::Context;
+use concordium_rust_sdk::{
+    id::types::AccountAddress,
+    smart_contracts::common::{schema::VersionedModuleSchema, Amount, Cursor, OwnedReceiveName},
+    types::{
+        hashes::BlockHash,
+        smart_contracts::{ContractContext, InstanceInfo, InvokeContractResult, OwnedParameter},
+        transactions::UpdateContractPayload,
+        Address, ContractAddress, Energy, RejectReason,
+    },
+    v2::{self, BlockIdentifier},
+};
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Energy for read-only invocations without `--invoke-energy`.
+pub const DEFAULT_INVOKE_ENERGY: u64 = 1000000;
+
+/// How read-only invocations are made and reported, from the flags.
+#[derive(Debug, Clone, Copy)]
+pub struct InvokeSettings {
+    /// Energy available to read-only invocations.
+    pub energy: Energy,
+    /// Whether the raw result of every invocation is printed to stderr.
+    pub dump_raw_response: bool,
+}
+
+impl Default for InvokeSettings {
+    fn default() -> Self {
+        Self {
+            energy: DEFAULT_INVOKE_ENERGY.into(),
+            dump_raw_response: false,
+        }
+    }
+}
+
+impl InvokeSettings {
+    /// Warn if a read-only invocation of `method` failed for lack of energy.
+    pub fn warn_if_out_of_energy(&self, method: &OwnedReceiveName, result: &InvokeContractResult) {
+        if let InvokeContractResult::Failure {
+            reason: RejectReason::OutOfEnergy,
+            ..
+        } = result
+        {
+            eprintln!(
+                "Warning: invoking {} ran out of its {} energy, raise --invoke-energy.",
+                method, self.energy
+            );
+        }
+    }
+
+    /// Print the energy used, the return value in hex and the whole result of
+    /// invoking `method` to stderr, if enabled.
+    pub fn dump_raw_response(&self, method: &OwnedReceiveName, result: &InvokeContractResult) {
+        if !self.dump_raw_response {
+            return;
+        }
+        let (used_energy, return_value) = match result {
+            InvokeContractResult::Success {
+                used_energy,
+                return_value,
+                ..
+            }
+            | InvokeContractResult::Failure {
+                used_energy,
+                return_value,
+                ..
+            } => (used_energy, return_value),
+        };
+        eprintln!("Raw response of {}:", method);
+        eprintln!("  used energy:  {}", used_energy);
+        match return_value {
+            Some(rv) => eprintln!("  return value: {}", hex::encode(&rv.value)),
+            None => eprintln!("  return value: none"),
+        }
+        eprintln!("{:#?}", result);
+    }
+}
+
+/// Size of a contract instance's state.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSize {
+    pub address: ContractAddress,
+    pub block_hash: BlockHash,
+    /// Total size of the state in bytes, keys included for V1 contracts.
+    pub bytes: u64,
+    /// Number of key-value entries, only available for V1 contracts.
+    pub entries: Option<u64>,
+}
+
+impl StateSize {
+    /// Measure the state of `address` in the last finalized block.
+    pub async fn fetch(client: &mut v2::Client, address: ContractAddress) -> anyhow::Result<Self> {
+        let info = client
+            .get_instance_info(address, &BlockIdentifier::LastFinal)
+            .await?;
+        if let InstanceInfo::V0 { model, .. } = &info.response {
+            return Ok(StateSize {
+                address,
+                block_hash: info.block_hash,
+                bytes: model.len() as u64,
+                entries: None,
+            });
+        }
+        let state = client.get_instance_state(address, &info.block_hash).await?;
+        let mut stream = Box::pin(state.response);
+        let mut bytes = 0u64;
+        let mut entries = 0u64;
+        while let Some(entry) = stream.next().await {
+            let (key, value) = entry?;
+            bytes += (key.len() + value.len()) as u64;
+            entries += 1;
+        }
+        Ok(StateSize {
+            address,
+            block_hash: state.block_hash,
+            bytes,
+            entries: Some(entries),
+        })
+    }
+
+    pub fn print(&self) {
+        println!("State of {} (block {}):", self.address, self.block_hash);
+        println!("  size:    {} bytes", self.bytes);
+        if let Some(entries) = self.entries {
+            println!("  entries: {}", entries);
+        }
+    }
+}
+
+/// Ways in which the schema does not describe the contract of the instance
+/// at `address`, e.g. because it belongs to an earlier version of the module.
+pub async fn schema_mismatches(
+    client: &mut v2::Client,
+    address: ContractAddress,
+    schema: &VersionedModuleSchema,
+    contract_name: &str,
+) -> anyhow::Result<Vec<String>> {
+    let info = client
+        .get_instance_info(address, &BlockIdentifier::LastFinal)
+        .await?
+        .response;
+    let mut mismatches = Vec::new();
+    let instance_name = info.name().as_contract_name().contract_name();
+    if instance_name != contract_name {
+        mismatches.push(format!(
+            "Contract {} is an instance of {}, not {}.",
+            address, instance_name, contract_name
+        ));
+    }
+    let described = schema::contracts(schema);
+    if !described.iter().any(|c| c == contract_name) {
+        mismatches.push(format!(
+            "The schema does not describe a contract {}. It describes: {}.",
+            contract_name,
+            described.join(", ")
+        ));
+        return Ok(mismatches);
+    }
+    let missing: Vec<String> = schema::entrypoints(schema, contract_name)
+        .into_iter()
+        .filter(|e| {
+            !info
+                .entrypoints()
+                .iter()
+                .any(|m| m.as_receive_name().entrypoint_name().to_string() == *e)
+        })
+        .collect();
+    if !missing.is_empty() {
+        mismatches.push(format!(
+            "Contract {} has no entrypoints {}, which the schema describes.",
+            address,
+            missing.join(", ")
+        ));
+    }
+    Ok(mismatches)
+}
+
+/// Simulate an update by `sender` in the best block, returning the result
+/// with the events it would log.
+pub async fn dry_run(
+    client: &mut v2::Client,
+    settings: &InvokeSettings,
+    sender: AccountAddress,
+    payload: &UpdateContractPayload,
+    energy: Energy,
+) -> anyhow::Result<InvokeContractResult> {
+    let context = ContractContext {
+        invoker: Some(Address::Account(sender)),
+        contract: payload.address,
+        amount: payload.amount,
+        method: payload.receive_name.clone(),
+        parameter: payload.message.clone(),
+        energy,
+    };
+    let info = client
+        .invoke_instance(&BlockIdentifier::Best, &context)
+        .await?;
+    settings.dump_raw_response(&context.method, &info.response);
+    Ok(info.response)
+}
+
+/// Invoke an entrypoint without a transaction in the best block and return
+/// its return value. A rejection is decoded with the error schema in
+/// `schema`, if given.
+pub async fn invoke(
+    client: &mut v2::Client,
+    settings: &InvokeSettings,
+    contract: ContractAddress,
+    method: OwnedReceiveName,
+    parameter: OwnedParameter,
+    schema: Option<&VersionedModuleSchema>,
+) -> anyhow::Result<Vec<u8>> {
+    invoke_at(
+        client,
+        settings,
+        &BlockIdentifier::Best,
+        contract,
+        method,
+        parameter,
+        schema,
+    )
+    .await
+}
+
+/// Invoke an entrypoint without a transaction in the best block with a JSON
+/// parameter and decode its return value with the schema.
+pub async fn invoke_json(
+    client: &mut v2::Client,
+    settings: &InvokeSettings,
+    schema: &VersionedModuleSchema,
+    contract_name: &str,
+    contract: ContractAddress,
+    method: &str,
+    parameter: Option<&Value>,
+) -> anyhow::Result<Value> {
+    let param_schema = schema::receive_param_schema(schema, contract_name, method)?;
+    let parameter = params::serialize_optional(
+        method,
+        param_schema.as_ref(),
+        parameter,
+        params::Checks::default(),
+    )?;
+    let rv_schema = schema
+        .get_receive_return_value_schema(contract_name, method)
+        .with_context(|| format!("The schema has no return value schema for {}.", method))?;
+    let receive_name = schema::receive_name(contract_name, method, Some(schema))?;
+    let bytes = invoke(
+        client,
+        settings,
+        contract,
+        receive_name,
+        parameter,
+        Some(schema),
+    )
+    .await?;
+    Ok(rv_schema.to_json(&mut Cursor::new(&bytes[..]))?)
+}
+
+/// Invoke an entrypoint without a transaction in the given block and return
+/// its return value. A rejection is decoded with the error schema in
+/// `schema`, if given.
+pub async fn invoke_at(
+    client: &mut v2::Client,
+    settings: &InvokeSettings,
+    block: &BlockIdentifier,
+    contract: ContractAddress,
+    method: OwnedReceiveName,
+    parameter: OwnedParameter,
+    schema: Option<&VersionedModuleSchema>,
+) -> anyhow::Result<Vec<u8>> {
+    let context = ContractContext {
+        invoker: None,
+        contract,
+        amount: Amount::zero(),
+        method,
+        parameter,
+        energy: settings.energy,
+    };
+    let info = client.invoke_instance(block, &context).await?;
+    settings.dump_raw_response(&context.method, &info.response);
+    settings.warn_if_out_of_energy(&context.method, &info.response);
+    match info.response {
+        InvokeContractResult::Success { return_value, .. } => {
+            Ok(return_value.map(|rv| rv.value).unwrap_or_default())
+        }
+        InvokeContractResult::Failure {
+            reason,
+            return_value,
+            ..
+        } => {
+            let return_value = return_value.as_ref().map(|rv| &rv.value[..]);
+            anyhow::bail!(
+                "Invoking {} failed: {}",
+                context.method,
+                crate::reject::describe(&reason, return_value, schema)
+            )
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Checking that a finalized update logged the CIS-2 events its parameter
+//! asked for.
+use crate::output::Printer;
+use anyhow::Context;
+use concordium_rust_sdk::{
+    cis2::{TokenAmount, TokenId},
+    id::types::AccountAddress,
+    smart_contracts::common::{Cursor, Deserial},
+    types::{
+        hashes::TransactionHash, smart_contracts::ContractEvent, Address, BlockItemSummary,
+        ContractAddress,
+    },
+    v2,
+};
+use serde_json::Value;
+
+/// Tags of the standard CIS-2 events.
+const TRANSFER_EVENT: u8 = 255;
+const MINT_EVENT: u8 = 254;
+
+/// An event an update is expected to log.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expected {
+    Mint {
+        token_id: String,
+        owner: Option<Address>,
+    },
+    Transfer {
+        token_id: String,
+        amount: TokenAmount,
+        from: Address,
+        to: Address,
+    },
+}
+
+impl std::fmt::Display for Expected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expected::Mint { token_id, owner } => {
+                write!(f, "Mint of token {}", token_id)?;
+                if let Some(owner) = owner {
+                    write!(f, " to {}", crate::cis2::describe_address(owner))?;
+                }
+                Ok(())
+            }
+            Expected::Transfer {
+                token_id,
+                amount,
+                from,
+                to,
+            } => write!(
+                f,
+                "Transfer of {} of token {} from {} to {}",
+                amount,
+                token_id,
+                crate::cis2::describe_address(from),
+                crate::cis2::describe_address(to)
+            ),
+        }
+    }
+}
+
+/// The `Mint` events expected from a `mint` parameter with an `owner` and a
+/// list of `tokens`, given as token IDs or as pairs starting with one.
+pub fn expected_mints(parameter: &Value) -> anyhow::Result<Vec<Expected>> {
+    let owner = parameter.get("owner").and_then(address_from_json);
+    let tokens = parameter
+        .get("tokens")
+        .and_then(Value::as_array)
+        .context("The mint parameter has no list of tokens to check the events against.")?;
+    tokens
+        .iter()
+        .map(|token| {
+            let token_id = token
+                .as_str()
+                .or_else(|| token.get(0).and_then(Value::as_str))
+                .context("Unexpected token in the mint parameter.")?;
+            Ok(Expected::Mint {
+                token_id: token_id.to_lowercase(),
+                owner,
+            })
+        })
+        .collect()
+}
+
+/// The `Transfer` events expected from a `transfer` parameter.
+pub fn expected_transfers(parameter: &Value) -> anyhow::Result<Vec<Expected>> {
+    let transfers = parameter
+        .as_array()
+        .context("The transfer parameter is not a list of transfers.")?;
+    transfers
+        .iter()
+        .map(|transfer| {
+            let field = |name: &str| {
+                transfer
+                    .get(name)
+                    .with_context(|| format!("A transfer has no {}.", name))
+            };
+            let amount = match field("amount")? {
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            };
+            Ok(Expected::Transfer {
+                token_id: field("token_id")?
+                    .as_str()
+                    .context("Invalid token_id.")?
+                    .to_lowercase(),
+                amount: amount
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid amount {}.", amount))?,
+                from: address_from_json(field("from")?).context("Invalid from address.")?,
+                to: address_from_json(field("to")?).context("Invalid to address.")?,
+            })
+        })
+        .collect()
+}
+
+/// An address or receiver in schema JSON, e.g. `{"Account": ["3abc..."]}`.
+pub fn address_from_json(value: &Value) -> Option<Address> {
+    if let Some(account) = value["Account"][0].as_str() {
+        return account.parse::<AccountAddress>().ok().map(Address::Account);
+    }
+    let contract = &value["Contract"][0];
+    Some(Address::Contract(ContractAddress::new(
+        contract["index"].as_u64()?,
+        contract["subindex"].as_u64()?,
+    )))
+}
+
+/// Decode a standard CIS-2 `Transfer` or `Mint` event.
+fn decode(event: &ContractEvent) -> Option<Expected> {
+    let mut cursor = Cursor::new(event.as_ref());
+    let tag = u8::deserial(&mut cursor).ok()?;
+    let token_id = TokenId::deserial(&mut cursor).ok()?.to_string();
+    let amount = TokenAmount::deserial(&mut cursor).ok()?;
+    match tag {
+        TRANSFER_EVENT => Some(Expected::Transfer {
+            token_id,
+            amount,
+            from: Address::deserial(&mut cursor).ok()?,
+            to: Address::deserial(&mut cursor).ok()?,
+        }),
+        MINT_EVENT => Some(Expected::Mint {
+            token_id,
+            owner: Some(Address::deserial(&mut cursor).ok()?),
+        }),
+        _ => None,
+    }
+}
+
+/// Query the outcome of the finalized transaction and verify its events.
+pub async fn verify_finalized(
+    client: &mut v2::Client,
+    hash: &TransactionHash,
+    contract: ContractAddress,
+    expected: &[Expected],
+    printer: &Printer,
+) -> anyhow::Result<()> {
+    let status = client.get_block_item_status(hash).await?;
+    let (_, summary) = status
+        .is_finalized()
+        .context("The transaction is not finalized.")?;
+    verify(summary, contract, expected)?;
+    printer.progress(format_args!(
+        "All {} expected event(s) were logged by contract {}.",
+        expected.len(),
+        contract
+    ));
+    Ok(())
+}
+
+/// Fail unless `contract` logged every expected event in the finalized
+/// transaction. A mint without an owner in the parameter matches any owner.
+fn verify(
+    summary: &BlockItemSummary,
+    contract: ContractAddress,
+    expected: &[Expected],
+) -> anyhow::Result<()> {
+    let logged: Vec<Expected> = summary
+        .contract_update_logs()
+        .into_iter()
+        .flatten()
+        .filter(|(address, _)| *address == contract)
+        .flat_map(|(_, events)| events.iter().filter_map(decode))
+        .collect();
+    let mut unmatched = logged.iter().collect::<Vec<_>>();
+    let mut missing = Vec::new();
+    for e in expected {
+        let found = unmatched.iter().position(|l| matches(e, l));
+        match found {
+            Some(i) => {
+                unmatched.remove(i);
+            }
+            None => missing.push(e.to_string()),
+        }
+    }
+    anyhow::ensure!(
+        missing.is_empty(),
+        "The transaction was finalized, but contract {} did not log the expected events:\n  {}",
+        contract,
+        missing.join("\n  ")
+    );
+    Ok(())
+}
+
+fn matches(expected: &Expected, logged: &Expected) -> bool {
+    match (expected, logged) {
+        (
+            Expected::Mint { token_id, owner },
+            Expected::Mint {
+                token_id: logged_id,
+                owner: logged_owner,
+            },
+        ) => token_id == logged_id && (owner.is_none() || owner == logged_owner),
+        _ => expected == logged,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use concordium_rust_sdk::smart_contracts::common::Serial;
+
+    fn event(tag: u8, token: u8, amount: u64, addresses: &[Address]) -> ContractEvent {
+        let mut bytes = vec![tag];
+        TokenId::new_unchecked(vec![token])
+            .serial(&mut bytes)
+            .unwrap();
+        TokenAmount::from(amount).serial(&mut bytes).unwrap();
+        for address in addresses {
+            address.serial(&mut bytes).unwrap();
+        }
+        bytes.into()
+    }
+
+    fn account(byte: u8) -> Address {
+        Address::Account(AccountAddress([byte; 32]))
+    }
+
+    #[test]
+    fn decode_transfer() {
+        let from = account(1);
+        let to = Address::Contract(ContractAddress::new(4, 0));
+        assert_eq!(
+            decode(&event(TRANSFER_EVENT, 0x0a, 5, &[from, to])),
+            Some(Expected::Transfer {
+                token_id: "0a".into(),
+                amount: TokenAmount::from(5u64),
+                from,
+                to,
+            })
+        );
+    }
+
+    #[test]
+    fn decode_mint() {
+        let owner = account(2);
+        assert_eq!(
+            decode(&event(MINT_EVENT, 0xff, 1, &[owner])),
+            Some(Expected::Mint {
+                token_id: "ff".into(),
+                owner: Some(owner),
+            })
+        );
+    }
+
+    #[test]
+    fn decode_ignores_other_and_truncated_events() {
+        assert_eq!(decode(&event(253, 1, 1, &[account(3)])), None);
+        assert_eq!(decode(&event(TRANSFER_EVENT, 1, 1, &[account(3)])), None);
+        assert_eq!(decode(&ContractEvent::from(Vec::new())), None);
+    }
+}
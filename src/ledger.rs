@@ -0,0 +1,195 @@
+//! The transactions affecting an account within a range of blocks, found by
+//! scanning the blocks, as the node offers no index by account.
+use crate::chain::{self, BlockSelector};
+use chrono::{DateTime, Utc};
+use concordium_rust_sdk::{
+    common::types::Amount,
+    id::types::AccountAddress,
+    types::{
+        hashes::{BlockHash, TransactionHash},
+        smart_contracts::ContractTraceElement,
+        AccountTransactionEffects, BlockItemSummary, BlockItemSummaryDetails,
+    },
+    v2,
+};
+use serde::Serialize;
+
+/// One transaction affecting the account.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub height: u64,
+    pub block: BlockHash,
+    pub slot_time: DateTime<Utc>,
+    pub hash: TransactionHash,
+    /// The transaction type, e.g. `transfer` or `update`, or `accountCreation`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub sender: Option<AccountAddress>,
+    /// The CCD the transaction moved, for transfers and contract calls.
+    pub amount: Option<Amount>,
+    /// The fee the sender paid.
+    pub cost: Option<Amount>,
+    pub outcome: &'static str,
+}
+
+/// The transactions affecting an account.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct History {
+    pub account: AccountAddress,
+    pub from_height: u64,
+    pub to_height: u64,
+    pub transactions: Vec<Entry>,
+}
+
+/// The type, sender, amount and cost of a transaction.
+fn describe(
+    summary: &BlockItemSummary,
+) -> (
+    String,
+    Option<AccountAddress>,
+    Option<Amount>,
+    Option<Amount>,
+) {
+    let details = match &summary.details {
+        BlockItemSummaryDetails::AccountTransaction(details) => details,
+        BlockItemSummaryDetails::AccountCreation(_) => {
+            return ("accountCreation".into(), None, None, None)
+        }
+        BlockItemSummaryDetails::Update(_) => return ("update".into(), None, None, None),
+    };
+    let kind = details
+        .transaction_type()
+        .and_then(|t| serde_json::to_value(t).ok())
+        .and_then(|t| t.as_str().map(String::from))
+        .unwrap_or_else(|| "unknown".into());
+    let amount = match &details.effects {
+        AccountTransactionEffects::AccountTransfer { amount, .. }
+        | AccountTransactionEffects::AccountTransferWithMemo { amount, .. } => Some(*amount),
+        AccountTransactionEffects::TransferredWithSchedule { amount, .. }
+        | AccountTransactionEffects::TransferredWithScheduleAndMemo { amount, .. } => amount
+            .iter()
+            .try_fold(Amount::zero(), |total, (_, a)| total.checked_add(*a)),
+        AccountTransactionEffects::ContractInitialized { data } => Some(data.amount),
+        AccountTransactionEffects::ContractUpdateIssued { effects } => {
+            effects.iter().find_map(|element| match element {
+                ContractTraceElement::Updated { data } => Some(data.amount),
+                _ => None,
+            })
+        }
+        _ => None,
+    };
+    (kind, Some(details.sender), amount, Some(details.cost))
+}
+
+impl History {
+    /// Scan every block from `from` to `to` for transactions affecting
+    /// `account`. This queries each block, so narrow the range on long chains.
+    pub async fn scan(
+        client: &mut v2::Client,
+        account: AccountAddress,
+        from: &BlockSelector,
+        to: &BlockSelector,
+        verbose: bool,
+    ) -> anyhow::Result<Self> {
+        let heights = chain::height_range(client, from, to).await?;
+        let (from_height, to_height) = (*heights.start(), *heights.end());
+        let mut transactions = Vec::new();
+        for height in heights {
+            if verbose && (height - from_height) % 1000 == 0 {
+                eprintln!("Scanning block {} of {}.", height, to_height);
+            }
+            let (block, summaries) = chain::block_items(client, height).await?;
+            let affecting: Vec<_> = summaries
+                .iter()
+                .filter(|summary| {
+                    summary
+                        .affected_addresses()
+                        .iter()
+                        .any(|a| a.is_alias(&account))
+                })
+                .collect();
+            if affecting.is_empty() {
+                continue;
+            }
+            let slot_time = client
+                .get_block_info(&block)
+                .await?
+                .response
+                .block_slot_time;
+            transactions.extend(affecting.into_iter().map(|summary| {
+                let (kind, sender, amount, cost) = describe(summary);
+                Entry {
+                    height,
+                    block,
+                    slot_time,
+                    hash: summary.hash,
+                    kind,
+                    sender,
+                    amount,
+                    cost,
+                    outcome: if summary.is_success() {
+                        "success"
+                    } else {
+                        "rejected"
+                    },
+                }
+            }));
+        }
+        Ok(Self {
+            account,
+            from_height,
+            to_height,
+            transactions,
+        })
+    }
+
+    pub fn print(&self) {
+        println!(
+            "Transactions of {} from height {} to {}:",
+            self.account, self.from_height, self.to_height
+        );
+        for entry in &self.transactions {
+            let amount = entry
+                .amount
+                .map(|a| format!(" {} CCD", a))
+                .unwrap_or_default();
+            println!(
+                "  {} (height {}) {}{} {} {}",
+                entry.slot_time, entry.height, entry.kind, amount, entry.outcome, entry.hash
+            );
+        }
+        if self.transactions.is_empty() {
+            println!("  no transactions");
+        }
+    }
+
+    pub fn print_csv(&self) -> anyhow::Result<()> {
+        let show = |v: Option<String>| v.unwrap_or_default();
+        crate::output::print_csv(
+            &[
+                "height",
+                "slot_time",
+                "hash",
+                "type",
+                "sender",
+                "amount",
+                "cost",
+                "outcome",
+            ],
+            self.transactions.iter().map(|e| {
+                vec![
+                    e.height.to_string(),
+                    e.slot_time.to_rfc3339(),
+                    e.hash.to_string(),
+                    e.kind.clone(),
+                    show(e.sender.map(|s| s.to_string())),
+                    show(e.amount.map(|a| a.to_string())),
+                    show(e.cost.map(|c| c.to_string())),
+                    e.outcome.to_string(),
+                ]
+            }),
+        )
+    }
+}
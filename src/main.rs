@@ -1,19 +1,19 @@
 use crate::clap::AppSettings;
 use anyhow::Context;
-use concordium_rust_sdk::types::smart_contracts::ContractContext;
+use concordium_rust_sdk::types::smart_contracts::{ContractContext, InvokeContractResult};
 
 use concordium_rust_sdk::{
-    common::{self, types::TransactionTime},
+    common::types::TransactionTime,
+    id::types::AccountAddress,
     smart_contracts::{
-        common as concordium_std,
-        common::Amount,
-        types::{OwnedContractName, OwnedReceiveName},
+        common::{schema::Type, Amount, Cursor},
+        types::OwnedContractName,
     },
     types::{
-        smart_contracts::{ModuleReference, OwnedParameter, WasmModule},
-        transactions::{send, BlockItem, InitContractPayload, UpdateContractPayload},
-        AccountInfo, AccountTransactionDetails, AccountTransactionEffects, BlockItemSummary,
-        BlockItemSummaryDetails, ContractAddress, WalletAccount,
+        smart_contracts::{ModuleReference, OwnedParameter},
+        transactions::ExactSizeTransactionSigner,
+        transactions::{InitContractPayload, Payload, UpdateContractPayload},
+        AccountInfo, BlockItemSummary, ContractAddress, Energy, Nonce, WalletAccount,
     },
     v2,
     v2::BlockIdentifier,
@@ -21,10 +21,46 @@ use concordium_rust_sdk::{
 use std::path::PathBuf;
 use structopt::*;
 use strum_macros::EnumString;
-use warp::path::param;
 
-#[derive(StructOpt, EnumString)]
+mod accounts;
+mod admin;
+mod batch;
+mod chain;
+mod cis2;
+mod config;
+mod diff;
+mod effects;
+mod events;
+mod explain;
+mod instance;
+mod intent;
+mod ledger;
+mod metadata;
+mod module;
+mod output;
+mod params;
+mod permit;
+mod portfolio;
+mod receipt;
+mod reject;
+mod repl;
+mod schema;
+mod signer;
+mod submit;
+mod supply;
+mod upgrades;
+mod version;
 
+use submit::TransactionPlan;
+
+use output::OutputFormat;
+
+/// Name of the CIS-2 contract in the module.
+const CONTRACT_NAME: &str = "rust_sdk_minting_tutorial";
+/// The node to connect to without --node or a profile.
+const DEFAULT_NODE: &str = "http://node.testnet.concordium.com:20000";
+
+#[derive(Clone, StructOpt, EnumString)]
 enum TransactionType {
     #[structopt(about = "Mint")]
     Mint,
@@ -34,36 +70,607 @@ enum TransactionType {
     TokenMetadata,
     #[structopt(about = "View")]
     View,
+    #[structopt(about = "Upgrade")]
+    Upgrade,
+    #[structopt(about = "Pause")]
+    Pause,
+    #[structopt(about = "Unpause")]
+    Unpause,
+    #[structopt(about = "TransferOwnership")]
+    TransferOwnership,
+    #[structopt(about = "GrantRole")]
+    GrantRole,
+    #[structopt(about = "RevokeRole")]
+    RevokeRole,
+    #[structopt(about = "Permit")]
+    Permit,
 }
 
-#[derive(StructOpt)]
+#[derive(Clone, StructOpt)]
+#[allow(clippy::large_enum_variant)]
 enum Action {
     #[structopt(about = "Deploy the module")]
     Deploy {
-        #[structopt(long = "module", help = "Path to the contract module.")]
+        #[structopt(
+            long = "module",
+            help = "Path to the contract module, or to a directory whose `.wasm.v1` modules \
+                    are all deployed. Gzip compressed modules are decompressed."
+        )]
         module_path: PathBuf,
+        #[structopt(
+            long = "continue-on-error",
+            help = "When deploying a directory, continue with the remaining modules if one \
+                    fails or is rejected. The command still fails if any module was not \
+                    deployed."
+        )]
+        continue_on_error: bool,
+        #[structopt(
+            long = "estimate",
+            help = "Only report the energy and CCD the deployment would cost at the current \
+                    exchange rate, without submitting it."
+        )]
+        estimate: bool,
     },
     #[structopt(about = "Initialize the CIS-2 NFT contract")]
     Init {
         #[structopt(
             long,
-            help = "The module reference used for initializing the contract instance."
+            help = "The module reference used for initializing the contract instance, in hex or \
+                    as the path of a file containing it.",
+            parse(try_from_str = module::parse_module_ref)
         )]
         module_ref: ModuleReference,
+        #[structopt(
+            long,
+            help = "Amount of CCD to fund the new instance with, for contracts whose init \
+                    function is payable.",
+            default_value = "0"
+        )]
+        amount: Amount,
     },
     #[structopt(
         about = "Update the contract and set the provided  using JSON parameters and a \
                  schema."
     )]
     WithSchema {
-        #[structopt(short, long, help = "Path of the JSON parameter.")]
+        #[structopt(short, long, help = "Path of the JSON or YAML parameter.")]
         parameter: Option<PathBuf>,
-        #[structopt(long, help = "Path to the schema.")]
+        #[structopt(long, help = "Path or http(s) URL of the schema.")]
         schema: PathBuf,
-        #[structopt(long, help = "The contract to update.")]
-        address: ContractAddress,
+        #[structopt(
+            long,
+            help = "The contract to update. [default: the contract of the --profile]"
+        )]
+        address: Option<ContractAddress>,
         #[structopt(long, help = "Transaction Type")]
         transaction_type_: TransactionType,
+        #[structopt(
+            long = "verify-roundtrip",
+            help = "Decode the serialized parameter back to JSON and warn about any difference \
+                    to the input."
+        )]
+        verify_roundtrip: bool,
+        #[structopt(
+            long = "check-operator",
+            help = "Before a transfer, query operatorOf and warn if the sender is neither the \
+                    owner of the tokens nor one of its operators."
+        )]
+        check_operator: bool,
+        #[structopt(
+            long = "follow-operator-chain",
+            help = "Before a transfer, query operatorOf and abort if the sender is neither the \
+                    owner of the tokens nor one of its operators."
+        )]
+        follow_operator_chain: bool,
+        #[structopt(
+            long = "check-paused",
+            help = "Before a mint, query the contract's paused entrypoint, or its alias in the \
+                    [methods] table of the config, and abort if the contract is paused."
+        )]
+        check_paused: bool,
+        #[structopt(
+            long,
+            help = "Amount of CCD to send to a payable entrypoint.",
+            default_value = "0"
+        )]
+        amount: Amount,
+        #[structopt(
+            long = "from-file",
+            help = "CSV file of transfers with the columns from, to, token_id, amount (a \
+                    positive integer) and optionally data, each submitted as its own \
+                    transaction. Only valid with the Transfer transaction type.",
+            conflicts_with = "parameter"
+        )]
+        from_file: Option<PathBuf>,
+        #[structopt(
+            long = "token-id-start",
+            help = "Mint the tokens with the IDs from this number on, each as its own \
+                    transaction. The --parameter file is the template of the mint parameter, \
+                    in which {id} is replaced by the token ID in hex, {n} by its number and \
+                    {url} by the --metadata-url. Only valid with the Mint transaction type.",
+            requires = "count",
+            conflicts_with = "from-file"
+        )]
+        token_id_start: Option<u64>,
+        #[structopt(
+            long = "parameter-from-return",
+            help = "Invoke this read-only entrypoint with the --parameter and use its decoded \
+                    return value as the parameter of the update.",
+            conflicts_with_all = &["from-file", "token-id-start"]
+        )]
+        parameter_from_return: Option<String>,
+        #[structopt(
+            long = "from-field",
+            help = "With --parameter-from-return, the part of the return value to use, e.g. \
+                    $[0].url or $.tokens[2].",
+            requires = "parameter-from-return"
+        )]
+        from_field: Option<String>,
+        #[structopt(
+            long = "token-ids",
+            help = "With the TokenMetadata transaction type, comma separated token IDs in hex to \
+                    query instead of a --parameter file. Prints the metadata URL of each.",
+            use_delimiter = true,
+            parse(try_from_str = cis2::parse_token_id),
+            conflicts_with = "parameter"
+        )]
+        token_ids: Vec<String>,
+        #[structopt(
+            long = "fetch-metadata",
+            help = "With --token-ids, also fetch the metadata JSON at each URL.",
+            requires = "token-ids"
+        )]
+        fetch_metadata: bool,
+        #[structopt(
+            long = "ipfs-gateways",
+            help = "Comma separated gateways tried in turn for ipfs:// metadata URLs.",
+            use_delimiter = true,
+            default_value = metadata::DEFAULT_GATEWAYS
+        )]
+        ipfs_gateways: Vec<String>,
+        #[structopt(
+            long = "ipfs-timeout",
+            help = "Seconds to wait for each metadata request before trying the next gateway.",
+            default_value = "10"
+        )]
+        ipfs_timeout: u64,
+        #[structopt(
+            long = "token-metadata-hash-check",
+            help = "After a mint is finalized, fetch the metadata of every token the contract \
+                    logged a metadata hash for, and fail if its SHA-256 differs."
+        )]
+        token_metadata_hash_check: bool,
+        #[structopt(
+            long = "parameter-item",
+            help = "One element of a list parameter in JSON, e.g. for a transfer. Can be given \
+                    several times; the elements are assembled into the list, or the struct \
+                    holding it, that the schema expects.",
+            number_of_values = 1,
+            parse(try_from_str = params::parse_item),
+            conflicts_with_all = &["parameter", "from-file", "token-id-start", "parameter-from-return", "token-ids"]
+        )]
+        parameter_items: Vec<serde_json::Value>,
+        #[structopt(
+            long = "parameter-base64",
+            help = "The serialized parameter in base64, or a file holding it, sent as it is \
+                    without a JSON parameter or schema check, e.g. to replay a captured \
+                    parameter. Supported for Mint and Transfer.",
+            conflicts_with_all = &["parameter", "parameter-item", "from-file", "token-id-start", "parameter-from-return", "token-ids", "data"]
+        )]
+        parameter_base64: Option<String>,
+        #[structopt(
+            long = "data",
+            help = "With the Transfer transaction type, the additional data in hex passed to \
+                    receiving contracts, for transfers of the parameter or --from-file that \
+                    give none. [default: empty]",
+            parse(try_from_str = cis2::parse_transfer_data)
+        )]
+        data: Option<String>,
+        #[structopt(
+            long = "count",
+            help = "Number of tokens to mint with --token-id-start."
+        )]
+        count: Option<u64>,
+        #[structopt(
+            long = "token-id-width",
+            help = "Size in bytes of the contract's token IDs, which are encoded in \
+                    little-endian order. [default: the size of the schema's token ID type, \
+                    else 1]"
+        )]
+        token_id_width: Option<usize>,
+        #[structopt(
+            long = "metadata-url",
+            help = "Template of the metadata URL of generated tokens, e.g. \
+                    https://example.com/{id}.json."
+        )]
+        metadata_url: Option<String>,
+        #[structopt(
+            long = "continue-on-error",
+            help = "With --from-file or --token-id-start, continue with the remaining \
+                    transactions if one cannot be submitted."
+        )]
+        continue_on_error: bool,
+        #[structopt(
+            long = "dry-run-all",
+            help = "For a batch, first simulate every transaction and submit none unless all \
+                    would succeed."
+        )]
+        dry_run_all: bool,
+        #[structopt(
+            long = "continue-on-dry-run-failure",
+            help = "With --dry-run-all, submit the batch even if some transactions would be \
+                    rejected.",
+            requires = "dry-run-all"
+        )]
+        continue_on_dry_run_failure: bool,
+        #[structopt(
+            long = "max-items-per-tx",
+            help = "With the Mint transaction type, mint at most this many tokens of the \
+                    parameter per transaction. A parameter too large for one transaction is \
+                    split automatically."
+        )]
+        max_items_per_tx: Option<usize>,
+        #[structopt(
+            long = "new-module-ref",
+            help = "With the Upgrade transaction type, the module to upgrade to, in hex or as the \
+                    path of a file containing it. It must already be deployed.",
+            parse(try_from_str = module::parse_module_ref)
+        )]
+        new_module_ref: Option<ModuleReference>,
+        #[structopt(
+            long = "migrate",
+            help = "With the Upgrade transaction type, the entrypoint of the new module to call \
+                    after the upgrade. Its parameter is read from --parameter.",
+            requires = "new-module-ref"
+        )]
+        migrate: Option<String>,
+        #[structopt(
+            long = "new-owner",
+            help = "With the TransferOwnership transaction type, the new owner, an account \
+                    address or a contract address <index,subindex>.",
+            parse(try_from_str = cis2::parse_address)
+        )]
+        new_owner: Option<concordium_rust_sdk::types::Address>,
+        #[structopt(
+            long = "role",
+            help = "With the GrantRole and RevokeRole transaction types, the role, e.g. Admin."
+        )]
+        role: Option<String>,
+        #[structopt(
+            long = "role-address",
+            help = "With the GrantRole and RevokeRole transaction types, the address that gains \
+                    or loses the role.",
+            parse(try_from_str = cis2::parse_address)
+        )]
+        role_address: Option<concordium_rust_sdk::types::Address>,
+        #[structopt(
+            long = "permit-signer",
+            help = "With the Permit transaction type, the key file of the account that signs the \
+                    permit message. The --account sponsors the transaction."
+        )]
+        permit_signer: Option<PathBuf>,
+        #[structopt(
+            long = "permit-entrypoint",
+            help = "With the Permit transaction type, the entrypoint the signer permits calling. \
+                    Its parameter is read from --parameter."
+        )]
+        permit_entrypoint: Option<String>,
+        #[structopt(
+            long = "permit-nonce",
+            help = "With the Permit transaction type, the signer's nonce in the contract. \
+                    [default: queried with nonceOf]"
+        )]
+        permit_nonce: Option<u64>,
+        #[structopt(
+            long = "yes",
+            help = "Transfer ownership or change roles without asking for confirmation."
+        )]
+        yes: bool,
+        #[structopt(
+            long = "check-schema",
+            help = "Before updating, check that the schema matches the contract instance's \
+                    name and entrypoints, and fail if it does not."
+        )]
+        check_schema: bool,
+        #[structopt(
+            long = "force",
+            help = "With --check-schema, only warn if the schema does not match the contract \
+                    instance.",
+            requires = "check-schema"
+        )]
+        force: bool,
+    },
+    #[structopt(about = "Print the chain parameters relevant for fees and limits")]
+    ChainParams,
+    #[structopt(about = "Print the versions of this tool, the SDK and the connected node")]
+    Version,
+    #[structopt(about = "Report the size of a contract instance's state")]
+    StateSize {
+        #[structopt(long, help = "The contract to inspect.")]
+        address: ContractAddress,
+    },
+    #[structopt(about = "Check that a contract instance runs the code of a local module")]
+    VerifyModule {
+        #[structopt(long, help = "The contract to check.")]
+        address: ContractAddress,
+        #[structopt(
+            long = "module",
+            help = "Path to the local contract module. Gzip compressed modules are decompressed."
+        )]
+        module_path: PathBuf,
+    },
+    #[structopt(about = "List the module upgrades of a contract within a range of blocks")]
+    UpgradeHistory {
+        #[structopt(
+            long,
+            help = "The contract to inspect. [default: the contract of the --profile]"
+        )]
+        address: Option<ContractAddress>,
+        #[structopt(
+            long = "from-block",
+            help = "The first block to scan: best, last-final, a block hash or a height. Every \
+                    block of the range is queried, so start near the contract's creation."
+        )]
+        from_block: chain::BlockSelector,
+        #[structopt(
+            long = "to-block",
+            help = "The last block to scan: best, last-final, a block hash or a height.",
+            default_value = "last-final"
+        )]
+        to_block: chain::BlockSelector,
+    },
+    #[structopt(about = "Export the transactions affecting an account within a range of blocks")]
+    AccountHistory {
+        #[structopt(long = "account", help = "The account whose transactions to export.")]
+        account: AccountAddress,
+        #[structopt(
+            long = "from-block",
+            help = "The first block to scan: best, last-final, a block hash or a height. Every \
+                    block of the range is queried."
+        )]
+        from_block: chain::BlockSelector,
+        #[structopt(
+            long = "to-block",
+            help = "The last block to scan: best, last-final, a block hash or a height.",
+            default_value = "last-final"
+        )]
+        to_block: chain::BlockSelector,
+    },
+    #[structopt(about = "Submit an already signed transaction and wait for it to finalize")]
+    SubmitRaw {
+        #[structopt(
+            long = "hex",
+            help = "The serialized signed transaction in hex.",
+            required_unless = "file"
+        )]
+        hex: Option<String>,
+        #[structopt(
+            long = "file",
+            help = "File containing the serialized signed transaction, in hex or binary.",
+            conflicts_with = "hex"
+        )]
+        file: Option<PathBuf>,
+    },
+    #[structopt(about = "Decode a raw return value with a schema, without a node connection")]
+    DecodeReturnValue {
+        #[structopt(
+            long = "hex",
+            help = "The return value in hex.",
+            required_unless = "file"
+        )]
+        hex: Option<String>,
+        #[structopt(
+            long = "file",
+            help = "File containing the return value, in hex or binary.",
+            conflicts_with = "hex"
+        )]
+        file: Option<PathBuf>,
+        #[structopt(long, help = "Path or http(s) URL of the schema.")]
+        schema: PathBuf,
+        #[structopt(
+            long,
+            help = "Name of the contract.",
+            default_value = CONTRACT_NAME
+        )]
+        contract: String,
+        #[structopt(long, help = "The entrypoint that returned the value.")]
+        method: String,
+    },
+    #[structopt(about = "Serialize a JSON parameter with a schema, without a node connection")]
+    EncodeParameter {
+        #[structopt(
+            short,
+            long,
+            help = "Path of the JSON or YAML parameter. Omit it for entrypoints without a parameter."
+        )]
+        parameter: Option<PathBuf>,
+        #[structopt(
+            long = "parameter-item",
+            help = "One element of a list parameter in JSON, instead of --parameter. Can be \
+                    given several times.",
+            number_of_values = 1,
+            parse(try_from_str = params::parse_item),
+            conflicts_with = "parameter"
+        )]
+        parameter_items: Vec<serde_json::Value>,
+        #[structopt(long, help = "Path or http(s) URL of the schema.")]
+        schema: PathBuf,
+        #[structopt(
+            long,
+            help = "Name of the contract.",
+            default_value = CONTRACT_NAME
+        )]
+        contract: String,
+        #[structopt(long, help = "The entrypoint the parameter is for.")]
+        method: String,
+        #[structopt(
+            long = "binary",
+            help = "Write the raw bytes to stdout instead of hex."
+        )]
+        binary: bool,
+        #[structopt(
+            long = "verify-roundtrip",
+            help = "Decode the serialized parameter back to JSON and warn about any difference \
+                    to the input."
+        )]
+        verify_roundtrip: bool,
+    },
+    #[structopt(about = "Report the balance of one token for many owners")]
+    Balances {
+        #[structopt(
+            long,
+            help = "The contract to query. [default: the contract of the --profile]"
+        )]
+        address: Option<ContractAddress>,
+        #[structopt(long = "token-id", help = "The token ID in hex.")]
+        token_id: concordium_rust_sdk::cis2::TokenId,
+        #[structopt(
+            long,
+            help = "Comma separated owners, accounts or contracts <index,subindex>.",
+            required_unless = "owners-file"
+        )]
+        owners: Option<String>,
+        #[structopt(
+            long = "owners-file",
+            help = "File with one owner per line, e.g. for an airdrop snapshot. Lines starting \
+                    with # are skipped."
+        )]
+        owners_file: Option<PathBuf>,
+    },
+    #[structopt(about = "Report the tokens an address holds across several CIS-2 contracts")]
+    Holdings {
+        #[structopt(
+            long,
+            help = "The owner, an account address or a contract address <index,subindex>.",
+            parse(try_from_str = cis2::parse_address)
+        )]
+        owner: concordium_rust_sdk::types::Address,
+        #[structopt(long, help = "Comma separated contracts to query, e.g. <1234,0>,5678.")]
+        contracts: String,
+        #[structopt(
+            long = "token-ids",
+            help = "Comma separated token IDs in hex to query in every contract, as CIS-2 \
+                    offers no enumeration of tokens.",
+            use_delimiter = true,
+            required_unless = "token-id-range"
+        )]
+        token_ids: Vec<concordium_rust_sdk::cis2::TokenId>,
+        #[structopt(
+            long = "token-id-range",
+            help = "Query the sequential token IDs of this inclusive range, e.g. 1..100, instead \
+                    of --token-ids. Large ranges are queried in several invocations.",
+            conflicts_with = "token-ids"
+        )]
+        token_id_range: Option<cis2::TokenIdRange>,
+        #[structopt(
+            long = "token-id-width",
+            help = "Size in bytes of the token IDs of --token-id-range, which are encoded in \
+                    little-endian order.",
+            default_value = "1"
+        )]
+        token_id_width: usize,
+    },
+    #[structopt(about = "Report the current and maximum supply of the contract's tokens")]
+    Supply {
+        #[structopt(long, help = "Path or http(s) URL of the schema.")]
+        schema: PathBuf,
+        #[structopt(
+            long,
+            help = "The contract to query. [default: the contract of the --profile]"
+        )]
+        address: Option<ContractAddress>,
+        #[structopt(
+            long,
+            help = "The entrypoint reporting the supply. [default: the supply entry of the \
+                    config's [methods] table, else the first of totalSupply, supply and view]"
+        )]
+        method: Option<String>,
+    },
+    #[structopt(about = "Show how the contract's view of its state differs between two blocks")]
+    StateDiff {
+        #[structopt(long, help = "Path or http(s) URL of the schema.")]
+        schema: PathBuf,
+        #[structopt(
+            long,
+            help = "The contract to inspect. [default: the contract of the --profile]"
+        )]
+        address: Option<ContractAddress>,
+        #[structopt(
+            long = "block-before",
+            help = "The block before the operation: best, last-final, a block hash or a height."
+        )]
+        block_before: chain::BlockSelector,
+        #[structopt(
+            long = "block-after",
+            help = "The block after the operation: best, last-final, a block hash or a height.",
+            default_value = "last-final"
+        )]
+        block_after: chain::BlockSelector,
+    },
+    #[structopt(about = "Print the contract events a finalized transaction logged")]
+    Events {
+        #[structopt(long, help = "Hash of the transaction.")]
+        tx: concordium_rust_sdk::types::hashes::TransactionHash,
+        #[structopt(
+            long,
+            help = "Path or http(s) URL of the schema, whose event schema decodes the events."
+        )]
+        schema: PathBuf,
+    },
+    #[structopt(about = "Run commands interactively on one connection, until Ctrl-D")]
+    Repl,
+    #[structopt(about = "Check parameter files against a schema, without a node connection")]
+    ValidateParameters {
+        #[structopt(
+            long = "path",
+            help = "A JSON or YAML parameter file, or a directory whose parameter files are \
+                    all checked. Can be given several times.",
+            required = true
+        )]
+        paths: Vec<PathBuf>,
+        #[structopt(long, help = "Path or http(s) URL of the schema.")]
+        schema: PathBuf,
+        #[structopt(
+            long,
+            help = "Name of the contract.",
+            default_value = CONTRACT_NAME
+        )]
+        contract: String,
+        #[structopt(long, help = "The entrypoint the parameters are for.")]
+        method: String,
+    },
+    #[structopt(about = "List the entrypoints a schema describes, without a node connection")]
+    ListMethods {
+        #[structopt(long, help = "Path or http(s) URL of the schema.")]
+        schema: PathBuf,
+        #[structopt(
+            long,
+            help = "Name of the contract.",
+            default_value = CONTRACT_NAME
+        )]
+        contract: String,
+    },
+    #[structopt(about = "Print the reference of a local module, without a node connection")]
+    ModuleRef {
+        #[structopt(
+            long = "module",
+            help = "Path to the contract module. Gzip compressed modules are decompressed."
+        )]
+        module_path: PathBuf,
+    },
+    #[structopt(
+        about = "Embed a schema into a module built without one, writing a new module file"
+    )]
+    EmbedSchema {
+        #[structopt(
+            long = "module",
+            help = "Path to the contract module. Gzip compressed modules are decompressed."
+        )]
+        module_path: PathBuf,
+        #[structopt(long, help = "Path to the base64 schema to embed.")]
+        schema: PathBuf,
+        #[structopt(long, help = "Path of the module to write.")]
+        out: PathBuf,
     },
 }
 ///
@@ -73,19 +680,312 @@ enum Action {
 struct App {
     #[structopt(
         long = "node",
-        help = "GRPC interface of the node.",
-        default_value = "http://node.testnet.concordium.com:20000"
+        help = "GRPC interface of the node. [default: the node of the --profile, else \
+                http://node.testnet.concordium.com:20000]"
     )]
+    node: Option<v2::Endpoint>,
+    /// The node to connect to, from --node or the profile.
+    #[structopt(skip = v2::Endpoint::from_static(DEFAULT_NODE))]
     endpoint: v2::Endpoint,
-    #[structopt(long = "account", help = "Path to the account key file.")]
-    keys_path: PathBuf,
+    #[structopt(
+        long = "account",
+        help = "Path to the account key file, or a directory of key files. Browser wallet, \
+                mobile wallet and genesis exports are read; a mobile wallet export adds all its \
+                accounts. Can be given several times; batch operations use the accounts in turn, \
+                other operations the first one."
+    )]
+    keys_path: Vec<PathBuf>,
+    #[structopt(
+        long = "key-index",
+        help = "Sign only with this key, given as <credential>:<key>, e.g. 0:1. Can be given \
+                several times; the selected keys must meet the account's thresholds."
+    )]
+    key_index: Vec<accounts::KeySelector>,
+    #[structopt(
+        long = "output",
+        help = "Output format of results. With json, failures are printed to stderr as \
+                {\"error\": {\"kind\", \"message\", \"context\"}}. csv prints tables of batch \
+                results, holdings, entrypoints, parameter checks, events and state differences, \
+                and other results as human. With json and csv, progress such as submitted \
+                transactions is printed to stderr, so that stdout holds only the result.",
+        default_value = "human",
+        possible_values = &["human", "json", "csv"]
+    )]
+    output: OutputFormat,
+    #[structopt(
+        long = "retry-on-reject",
+        help = "Resubmit once with a fresh nonce and expiry if the transaction fails because \
+                of its nonce or expiry. Not supported for batches."
+    )]
+    retry_on_reject: bool,
+    #[structopt(
+        long = "retry-budget",
+        help = "Fail once this many retries were made in total, counting both resubmissions \
+                and resumed waits for transactions, however many calls they are spread over."
+    )]
+    retry_budget: Option<u32>,
+    #[structopt(
+        long = "require-protocol-version",
+        help = "Abort unless the chain runs at least this protocol version."
+    )]
+    require_protocol_version: Option<u64>,
+    #[structopt(
+        long = "network",
+        help = "Abort unless the node's genesis block is the one of this network.",
+        possible_values = &["mainnet", "testnet"]
+    )]
+    network: Option<chain::Network>,
+    #[structopt(
+        long = "force-network",
+        help = "Only warn if the node is not on the --network.",
+        requires = "network"
+    )]
+    force_network: bool,
+    #[structopt(
+        long,
+        help = "Energy for executing the contract, on top of the base transaction cost. \
+                [default: from the config file, else 10000]"
+    )]
+    energy: Option<Energy>,
+    #[structopt(
+        long = "max-fee",
+        help = "The most CCD the transaction may cost. The energy for executing the contract is \
+                derived from it at the current exchange rate, instead of --energy.",
+        conflicts_with = "energy"
+    )]
+    max_fee: Option<Amount>,
+    #[structopt(
+        long = "schema-base64-variant",
+        help = "Base64 variant of schema files. `cargo concordium build --schema-base64-out` \
+                writes `standard`; URL-safe schemas are accepted with or without padding.",
+        default_value = "auto",
+        possible_values = &["auto", "standard", "url-safe", "no-pad"]
+    )]
+    schema_base64_variant: schema::Base64Variant,
+    #[structopt(
+        long = "schema-cache-dir",
+        help = "Keep parsed schemas in this directory, by the module reference of the contract, \
+                and reuse them while neither the module nor the schema file changes. Schemas \
+                downloaded from a URL are kept by URL; delete the .download file to fetch one \
+                again."
+    )]
+    schema_cache_dir: Option<PathBuf>,
+    #[structopt(
+        long = "config",
+        help = "TOML file with defaults, e.g. an [energy] table mapping method names to \
+                their energy, or a [methods] table mapping them to the contract's entrypoints, \
+                e.g. mint = \"mintNft\". The mapping applies to every entrypoint this tool \
+                calls except balanceOf, which balances and holdings query through the CIS-2 \
+                standard."
+    )]
+    config: Option<PathBuf>,
+    #[structopt(
+        long = "profile",
+        help = "Use the named [profiles.<name>] table of the config file for the node, \
+                accounts, contract and energy. Flags take precedence over the profile."
+    )]
+    profile: Option<String>,
+    /// The contract of the profile, used without --address.
+    #[structopt(skip)]
+    default_contract: Option<ContractAddress>,
+    #[structopt(
+        long = "build-unsigned",
+        help = "Build the transaction without signing or submitting it, and write its \
+                serialized header and payload in hex. Once signed offline, the resulting block \
+                item can be submitted with `submit-raw`."
+    )]
+    build_unsigned: bool,
+    #[structopt(
+        long = "sender",
+        help = "Address of the sending account. Together with --build-unsigned it replaces \
+                --account, or differs from it to build a transaction for another signer; \
+                --dry-run simulates the update as sent by it. Signed transactions are always \
+                sent by the --account."
+    )]
+    sender: Option<AccountAddress>,
+    #[structopt(
+        long = "num-signatures",
+        help = "Number of signatures the offline or external signer will add, used for the \
+                energy cost of a transaction built without --account.",
+        default_value = "1"
+    )]
+    num_signatures: u32,
+    #[structopt(
+        long = "expiry-absolute",
+        help = "Let transactions expire at this RFC 3339 time, e.g. 2024-06-01T12:00:00Z, instead \
+                of five minutes after they are built."
+    )]
+    expiry_absolute: Option<chrono::DateTime<chrono::Utc>>,
+    #[structopt(
+        long = "signer-url",
+        help = "Have the transaction signed by the external signer at this URL instead of with \
+                key files. The signer receives the unsigned transaction and its hash as JSON in \
+                a POST request and answers with the signatures. The --sender is the signing \
+                account and --num-signatures the number of signatures the signer adds.",
+        conflicts_with_all = &["keys-path", "build-unsigned"]
+    )]
+    signer_url: Option<String>,
+    #[structopt(
+        long = "signing-key",
+        help = "Sign with this raw Ed25519 key, in hex or base64, instead of a key file, for \
+                throwaway test accounts. The --sender is the account. The key ends up in the \
+                shell history.",
+        conflicts_with_all = &["keys-path", "signer-url"],
+        requires = "sender"
+    )]
+    signing_key: Option<String>,
+    #[structopt(long = "out", help = "Write the unsigned transaction to this file.")]
+    out: Option<PathBuf>,
+    #[structopt(
+        long = "json-compact",
+        help = "Print JSON results on a single line.",
+        conflicts_with = "json-pretty"
+    )]
+    json_compact: bool,
+    #[structopt(
+        long = "json-pretty",
+        help = "Print JSON results indented. This is the default."
+    )]
+    json_pretty: bool,
+    #[structopt(
+        long = "no-color",
+        help = "Print results without colors. Setting NO_COLOR has the same effect."
+    )]
+    no_color: bool,
+    #[structopt(
+        long = "timeout-overall",
+        help = "Abort if the whole command takes longer than this many seconds."
+    )]
+    timeout_overall: Option<u64>,
+    #[structopt(
+        long = "block-metadata",
+        help = "Also report the height and slot time of the block a transaction is finalized \
+                in."
+    )]
+    block_metadata: bool,
+    #[structopt(
+        long = "confirmations",
+        help = "After finalization, wait until this many further blocks are finalized on top \
+                of the transaction's block.",
+        default_value = "0"
+    )]
+    confirmations: u64,
+    #[structopt(
+        long = "strict-json",
+        help = "Reject JSON parameters with struct fields or enum variants the schema does not \
+                describe, instead of ignoring them."
+    )]
+    strict_json: bool,
+    #[structopt(
+        long = "parameter-format",
+        help = "Format of parameter files. `auto` reads files ending in .yaml or .yml as YAML \
+                and all others as JSON.",
+        default_value = "auto",
+        possible_values = &["auto", "json", "yaml"]
+    )]
+    parameter_format: params::ParameterFormat,
+    #[structopt(
+        long = "allow-unconfirmed-nonce",
+        help = "Use the node's pending nonce, which accounts for transactions that are not \
+                finalized yet, so that transactions can be sent back to back."
+    )]
+    allow_unconfirmed_nonce: bool,
+    #[structopt(
+        long = "receipt-dir",
+        help = "Write a JSON receipt with the hash, block, effects and parameter of every \
+                finalized transaction to this directory, one file per transaction."
+    )]
+    receipt_dir: Option<PathBuf>,
+    #[structopt(
+        long = "metrics-file",
+        help = "After a batch, write its totals as JSON to this file: transactions submitted, \
+                finalized, rejected and timed out, energy and CCD spent, duration and \
+                throughput."
+    )]
+    metrics_file: Option<PathBuf>,
+    #[structopt(
+        long = "dry-run",
+        help = "Simulate a contract update instead of submitting it, printing the result and \
+                the events it would log. For a batch, every transaction is simulated."
+    )]
+    dry_run: bool,
+    #[structopt(
+        long = "parameter-validate-only",
+        help = "Serialize the parameter of a contract update, then print it decoded back to \
+                JSON with the schema, showing how the input was interpreted, instead of \
+                submitting the update.",
+        conflicts_with_all = &["dry-run", "build-unsigned"]
+    )]
+    parameter_validate_only: bool,
+    #[structopt(
+        long = "explain",
+        help = "Describe what the command would do and exit, without connecting to a node."
+    )]
+    explain: bool,
+    #[structopt(
+        long = "then",
+        help = "Another command to run on the same connection once the first succeeds, e.g. \
+                --then \"state-size --address <1234,0>\". Transactions continue with the next \
+                nonce."
+    )]
+    then: Option<String>,
+    #[structopt(
+        long = "require-finalization-match",
+        help = "After a mint or transfer is finalized, check that the contract logged a Mint or \
+                Transfer event for every token of the parameter, and fail otherwise."
+    )]
+    require_finalization_match: bool,
+    #[structopt(long = "verbose", help = "Report more about what the command does.")]
+    verbose: bool,
+    #[structopt(
+        long = "print-tx-json",
+        help = "Print every transaction as JSON to stderr before submitting it."
+    )]
+    print_tx_json: bool,
+    #[structopt(
+        long = "rate-limit",
+        help = "Submit at most this many transactions per second, e.g. 2 or 0.5, to spare \
+                shared nodes during batches. [default: unlimited]"
+    )]
+    rate_limit: Option<f64>,
+    #[structopt(
+        long = "signature-count-check",
+        help = "Before submitting a transaction, check that it has as many signatures as the \
+                sender's account and credential thresholds require."
+    )]
+    signature_count_check: bool,
+    #[structopt(
+        long = "dump-raw-response",
+        help = "Print the undecoded result of every contract invocation to stderr: the energy \
+                used, the return value in hex and the full response."
+    )]
+    dump_raw_response: bool,
+    #[structopt(
+        long = "invoke-energy",
+        help = "Energy available to read-only queries of the contract, e.g. view or \
+                balanceOf, independent of the energy of updates.",
+        default_value = "1000000"
+    )]
+    invoke_energy: u64,
+    #[structopt(
+        long = "dump-effects-json",
+        help = "After a transaction is finalized, print all its effects as JSON, with the \
+                contract's events decoded by the schema."
+    )]
+    dump_effects_json: bool,
     #[structopt(subcommand, help = "The action you want to perform.")]
     action: Action,
+    /// How results are printed, from the output flags.
+    #[structopt(skip)]
+    printer: output::Printer,
+    /// How read-only invocations are made, from the invocation flags.
+    #[structopt(skip)]
+    invoke: instance::InvokeSettings,
+    /// Loads schema files with the selected base64 variant and cache.
+    #[structopt(skip)]
+    schemas: schema::Loader,
 }
-
-////
-///
-///
+
 use std::{fmt, println};
 pub struct BlockDetails(BlockItemSummary);
 impl fmt::Display for BlockDetails {
@@ -94,177 +994,1431 @@ impl fmt::Display for BlockDetails {
     }
 }
 
-use concordium_rust_sdk::types::transactions::AccountTransaction;
-use concordium_rust_sdk::types::transactions::EncodedPayload;
 // pub use endpoints::{QueryError, QueryResult, RPCError, RPCResult};
 #[derive(Debug)]
 enum TransactionResult {
-    StateChanging(AccountTransaction<EncodedPayload>),
+    StateChanging(TransactionPlan),
     None,
 }
-////
-///
-///
-///
+
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> anyhow::Result<()> {
-    use base64::{engine::general_purpose, Engine as _};
     let app = {
-        let app = App::clap().global_setting(AppSettings::ColoredHelp);
+        let mut app = App::clap().global_setting(AppSettings::ColoredHelp);
+        if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            app = app.global_setting(AppSettings::ColorNever);
+        }
         let matches = app.get_matches();
         App::from_clap(&matches)
     };
+    // With JSON output, failures are reported as JSON too.
+    let json_errors = app.output == OutputFormat::Json;
+    let printer = output::Printer::new(
+        app.output,
+        app.json_compact && !app.json_pretty,
+        app.no_color,
+    );
+    let result = start(app).await;
+    match result {
+        Err(e) if json_errors => {
+            printer.print_error_json(&e);
+            std::process::exit(1)
+        }
+        result => result,
+    }
+}
+
+/// Apply the settings of the flags and run the command, within the overall
+/// timeout if one is given.
+async fn start(mut app: App) -> anyhow::Result<()> {
+    app.apply_profile()?;
+    app.printer = output::Printer::new(
+        app.output,
+        app.json_compact && !app.json_pretty,
+        app.no_color,
+    );
+    app.invoke = instance::InvokeSettings {
+        energy: app.invoke_energy.into(),
+        dump_raw_response: app.dump_raw_response,
+    };
+    app.schemas = schema::Loader::new(app.schema_base64_variant, app.schema_cache_dir.clone())?;
+    let mut session = Session::new(&app)?;
+
+    let Some(seconds) = app.timeout_overall else {
+        return run(&app, &mut session).await;
+    };
+    let timeout = std::time::Duration::from_secs(seconds);
+    match tokio::time::timeout(timeout, run(&app, &mut session)).await {
+        Ok(result) => result,
+        Err(elapsed) => {
+            for hash in session.submitter.submitted() {
+                eprintln!("Transaction {} was submitted before the timeout.", hash);
+            }
+            Err(anyhow::Error::new(elapsed).context(format!(
+                "The command did not finish within {} seconds.",
+                seconds
+            )))
+        }
+    }
+}
+
+impl App {
+    /// Fill in what the flags leave open from the selected profile.
+    fn apply_profile(&mut self) -> anyhow::Result<()> {
+        let config = config::Config::load(self.config.as_deref(), self.profile.as_deref())?;
+        if let Some(node) = self.node.clone().or(config.node()?) {
+            self.endpoint = node;
+        }
+        if self.keys_path.is_empty() {
+            if let Some(profile) = &config.profile {
+                self.keys_path = profile.accounts.clone();
+            }
+        }
+        self.default_contract = config.contract()?;
+        Ok(())
+    }
+
+    /// The parameter checks selected with the flags, verifying the round trip
+    /// if `verify_roundtrip` is set.
+    fn checks(&self, verify_roundtrip: bool) -> params::Checks {
+        params::Checks {
+            strict_json: self.strict_json,
+            verify_roundtrip,
+        }
+    }
+
+    /// The contract given with --address, else the one of the profile.
+    fn contract(&self, address: Option<ContractAddress>) -> anyhow::Result<ContractAddress> {
+        address
+            .or(self.default_contract)
+            .context("--address is required unless the --profile names a contract.")
+    }
+}
+
+async fn run(app: &App, session: &mut Session) -> anyhow::Result<()> {
+    let then = app.then.as_deref().map(repl::parse_action).transpose()?;
+    if app.explain {
+        println!("{}", explain::describe(app, &app.action).await?);
+        if let Some(then) = &then {
+            println!("Then:\n{}", explain::describe(app, then).await?);
+        }
+        return Ok(());
+    }
+    anyhow::ensure!(
+        then.is_none() || !matches!(app.action, Action::Version | Action::Repl),
+        "--then cannot follow version or repl."
+    );
+    // actions that do not need a node connection
+    if let Action::Version = app.action {
+        let info = version::VersionInfo::collect(app.endpoint.clone()).await;
+        match app.output {
+            OutputFormat::Human | OutputFormat::Csv => info.print(),
+            OutputFormat::Json => app.printer.print_json(&info)?,
+        }
+        return Ok(());
+    }
+    if run_offline(app, &app.action).await? {
+        let Some(then) = then else {
+            return Ok(());
+        };
+        if run_offline(app, &then).await? {
+            return Ok(());
+        }
+        let mut client = connect(app).await?;
+        return execute(app, then, &mut client, session).await;
+    }
+
+    let mut client = connect(app).await?;
+    if let Action::Repl = app.action {
+        return repl::run(app, &mut client, session).await;
+    }
+    execute(app, app.action.clone(), &mut client, session).await?;
+    if let Some(then) = then {
+        if !run_offline(app, &then).await? {
+            execute(app, then, &mut client, session).await?;
+        }
+    }
+    Ok(())
+}
 
-    let mut client = v2::Client::new(app.endpoint)
+/// Connect to the node and check that it runs the required protocol version
+/// and network.
+async fn connect(app: &App) -> anyhow::Result<v2::Client> {
+    let mut client = v2::Client::new(app.endpoint.clone())
         .await
         .context("Cannot connect.")?;
 
+    if let Some(required) = app.require_protocol_version {
+        chain::require_protocol_version(&mut client, required).await?;
+    }
+    if let Some(network) = app.network {
+        chain::require_network(&mut client, network, app.force_network).await?;
+    }
+    Ok(client)
+}
+
+/// Run the actions that need neither a node connection nor keys. Returns
+/// whether `action` was one of them.
+async fn run_offline(app: &App, action: &Action) -> anyhow::Result<bool> {
+    if let Action::DecodeReturnValue {
+        hex,
+        file,
+        schema,
+        contract,
+        method,
+    } = action
+    {
+        let bytes = output::read_hex_or_binary(hex.as_deref(), file.as_deref())?;
+        let schema = app.schemas.load(schema).await?;
+        schema::receive_name(contract, method, Some(&schema))?;
+        let rv_schema = schema.get_receive_return_value_schema(contract, method)?;
+        app.printer.print_return_value(&rv_schema, &bytes)?;
+        return Ok(true);
+    }
+    if let Action::EncodeParameter {
+        parameter,
+        parameter_items,
+        schema,
+        contract,
+        method,
+        binary,
+        verify_roundtrip,
+    } = action
+    {
+        let schema = app.schemas.load(schema).await?;
+        schema::receive_name(contract, method, Some(&schema))?;
+        let param_schema = schema::receive_param_schema(&schema, contract, method)?;
+        let parameter = if parameter_items.is_empty() {
+            parameter
+                .as_deref()
+                .map(|p| params::read_json(p, app.parameter_format))
+                .transpose()?
+        } else {
+            Some(params::wrap_items(
+                method,
+                param_schema.as_ref(),
+                parameter_items.clone(),
+            )?)
+        };
+        let bytes = params::serialize_optional(
+            method,
+            param_schema.as_ref(),
+            parameter.as_ref(),
+            app.checks(*verify_roundtrip),
+        )?;
+        if *binary {
+            use std::io::Write;
+            std::io::stdout().write_all(bytes.as_ref())?;
+            return Ok(true);
+        }
+        match app.output {
+            OutputFormat::Human | OutputFormat::Csv => println!("{}", hex::encode(bytes.as_ref())),
+            OutputFormat::Json => app.printer.print_json(&serde_json::json!({
+                "parameter": hex::encode(bytes.as_ref()),
+                "size": bytes.as_ref().len(),
+            }))?,
+        }
+        return Ok(true);
+    }
+    if let Action::ValidateParameters {
+        paths,
+        schema,
+        contract,
+        method,
+    } = action
+    {
+        let schema = app.schemas.load(schema).await?;
+        schema::receive_name(contract, method, Some(&schema))?;
+        let param_schema = schema.get_receive_param_schema(contract, method)?;
+        let mut files = Vec::new();
+        for path in paths {
+            files.extend(params::json_files(path)?);
+        }
+        let results = params::validate_files(
+            &param_schema,
+            &files,
+            app.parameter_format,
+            app.checks(false),
+        );
+        match app.output {
+            OutputFormat::Human => {
+                for result in &results {
+                    match &result.error {
+                        None => println!("ok    {}", result.file.display()),
+                        Some(e) => println!("FAIL  {}: {}", result.file.display(), e),
+                    }
+                }
+            }
+            OutputFormat::Json => app.printer.print_json(&results)?,
+            OutputFormat::Csv => output::print_csv(
+                &["file", "size", "error"],
+                results.iter().map(|r| {
+                    vec![
+                        r.file.display().to_string(),
+                        r.size.map(|size| size.to_string()).unwrap_or_default(),
+                        r.error.clone().unwrap_or_default(),
+                    ]
+                }),
+            )?,
+        }
+        let failed = results.iter().filter(|r| r.error.is_some()).count();
+        anyhow::ensure!(
+            failed == 0,
+            "{} of {} parameter file(s) are invalid.",
+            failed,
+            results.len()
+        );
+        return Ok(true);
+    }
+    if let Action::ModuleRef { module_path } = action {
+        let module = module::read_module(module_path)?;
+        match app.output {
+            OutputFormat::Human | OutputFormat::Csv => println!("{}", module.get_module_ref()),
+            OutputFormat::Json => app.printer.print_json(&serde_json::json!({
+                "moduleRef": module.get_module_ref(),
+                "size": module.source.size(),
+            }))?,
+        }
+        return Ok(true);
+    }
+    if let Action::EmbedSchema {
+        module_path,
+        schema,
+        out,
+    } = action
+    {
+        let module = module::read_module(module_path)?;
+        let schema = app.schemas.load(schema).await?;
+        let embedded = module::embed_schema(&module, &schema)?;
+        std::fs::write(out, concordium_rust_sdk::common::to_bytes(&embedded))
+            .with_context(|| format!("Could not write the module {}.", out.display()))?;
+        match app.output {
+            OutputFormat::Human | OutputFormat::Csv => println!(
+                "Wrote the module with its schema to {}. Its reference is {}.",
+                out.display(),
+                embedded.get_module_ref()
+            ),
+            OutputFormat::Json => app.printer.print_json(&serde_json::json!({
+                "moduleRef": embedded.get_module_ref(),
+                "size": embedded.source.size(),
+            }))?,
+        }
+        return Ok(true);
+    }
+    if let Action::ListMethods { schema, contract } = action {
+        let schema = app.schemas.load(schema).await?;
+        let methods = schema::methods(&schema, contract)?;
+        match app.output {
+            OutputFormat::Human => schema::print_methods(&methods),
+            OutputFormat::Json => app.printer.print_json(&methods)?,
+            OutputFormat::Csv => schema::print_methods_csv(&methods)?,
+        }
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Print the metadata URL of each queried token from a `tokenMetadata`
+/// return value, or the whole value if it has no URLs in the expected places.
+/// With a `resolver` the metadata at each URL is fetched and printed too; a
+/// token whose metadata cannot be fetched gets the error instead.
+async fn print_metadata_urls(
+    rv_schema: &Type,
+    bytes: &[u8],
+    token_ids: &[String],
+    resolver: Option<&metadata::Resolver>,
+    printer: &output::Printer,
+) -> anyhow::Result<()> {
+    let response = rv_schema.to_json(&mut Cursor::new(bytes))?;
+    let Some(urls) = cis2::metadata_urls_of(&response).filter(|urls| urls.len() == token_ids.len())
+    else {
+        return printer.print_json(&response);
+    };
+    let mut fetched = Vec::new();
+    if let Some(resolver) = resolver {
+        for url in &urls {
+            fetched.push(resolver.fetch(url).await.map_err(|e| format!("{:#}", e)));
+        }
+    }
+    let fetched = |i: usize| fetched.get(i);
+    match printer.format {
+        OutputFormat::Human => {
+            for (i, (token_id, url)) in token_ids.iter().zip(urls).enumerate() {
+                printer.field(token_id, url);
+                match fetched(i) {
+                    Some(Ok(metadata)) => println!("{}", printer.to_json_string(metadata)?),
+                    Some(Err(e)) => println!("{}", printer.paint(e, output::Style::Failure)),
+                    None => (),
+                }
+            }
+            Ok(())
+        }
+        OutputFormat::Json => printer.print_json(
+            &token_ids
+                .iter()
+                .zip(urls)
+                .enumerate()
+                .map(|(i, (token_id, url))| {
+                    let mut entry = serde_json::json!({ "tokenId": token_id, "url": url });
+                    match fetched(i) {
+                        Some(Ok(metadata)) => entry["metadata"] = metadata.clone(),
+                        Some(Err(e)) => entry["error"] = e.clone().into(),
+                        None => (),
+                    }
+                    entry
+                })
+                .collect::<Vec<_>>(),
+        ),
+        OutputFormat::Csv => {
+            let mut header = vec!["token_id", "url"];
+            if resolver.is_some() {
+                header.extend(["metadata", "error"]);
+            }
+            output::print_csv(
+                &header,
+                token_ids
+                    .iter()
+                    .zip(urls)
+                    .enumerate()
+                    .map(|(i, (token_id, url))| {
+                        let mut row = vec![token_id.clone(), url.to_string()];
+                        match fetched(i) {
+                            Some(Ok(metadata)) => row.extend([metadata.to_string(), String::new()]),
+                            Some(Err(e)) => row.extend([String::new(), e.clone()]),
+                            None => (),
+                        }
+                        row
+                    }),
+            )
+        }
+    }
+}
+
+/// The ownership or role change of an administrative transaction type, from
+/// its flags.
+fn admin_change(
+    transaction_type: &TransactionType,
+    new_owner: Option<concordium_rust_sdk::types::Address>,
+    role: Option<String>,
+    role_address: Option<concordium_rust_sdk::types::Address>,
+) -> anyhow::Result<admin::AdminChange> {
+    let role_change = || -> anyhow::Result<(String, concordium_rust_sdk::types::Address)> {
+        Ok((
+            role.clone().context("--role is required.")?,
+            role_address.context("--role-address is required.")?,
+        ))
+    };
+    Ok(match transaction_type {
+        TransactionType::TransferOwnership => admin::AdminChange::TransferOwnership {
+            new_owner: new_owner.context("--new-owner is required to transfer ownership.")?,
+        },
+        TransactionType::GrantRole => {
+            let (role, address) = role_change()?;
+            admin::AdminChange::GrantRole { role, address }
+        }
+        TransactionType::RevokeRole => {
+            let (role, address) = role_change()?;
+            admin::AdminChange::RevokeRole { role, address }
+        }
+        _ => anyhow::bail!("Not an ownership or role change."),
+    })
+}
+
+/// State kept between the commands run on one connection.
+struct Session {
+    /// The account keys, loaded by the first command that needs them.
+    keys: Option<Vec<WalletAccount>>,
+    /// The next nonce of an account, tracked locally after a submission.
+    nonce: Option<(AccountAddress, Nonce)>,
+    /// Submits the transactions of all commands of the session.
+    submitter: submit::Submitter,
+}
+
+impl Session {
+    fn new(app: &App) -> anyhow::Result<Self> {
+        let settings = submit::Settings {
+            block_metadata: app.block_metadata,
+            confirmations: app.confirmations,
+            print_tx_json: app.print_tx_json,
+            signature_count_check: app.signature_count_check,
+            rate_limit: app.rate_limit,
+            retry_budget: app.retry_budget,
+            expiry_absolute: app.expiry_absolute,
+            receipt_dir: app.receipt_dir.clone(),
+        };
+        Ok(Self {
+            keys: None,
+            nonce: None,
+            submitter: submit::Submitter::new(settings, app.endpoint.clone(), app.printer)?,
+        })
+    }
+}
+
+/// Run an action that needs a node connection.
+async fn execute(
+    app: &App,
+    action: Action,
+    client: &mut v2::Client,
+    session: &mut Session,
+) -> anyhow::Result<()> {
+    // actions that only query the node and need no keys
+    if let Action::ChainParams = action {
+        let params = chain::ChainParams::fetch(client).await?;
+        match app.output {
+            OutputFormat::Human | OutputFormat::Csv => params.print(),
+            OutputFormat::Json => app.printer.print_json(&params)?,
+        }
+        return Ok(());
+    }
+    if let Action::StateSize { address } = action {
+        let size = instance::StateSize::fetch(client, address).await?;
+        match app.output {
+            OutputFormat::Human | OutputFormat::Csv => size.print(),
+            OutputFormat::Json => app.printer.print_json(&size)?,
+        }
+        return Ok(());
+    }
+    if let Action::Deploy {
+        module_path,
+        estimate: true,
+        ..
+    } = &action
+    {
+        let paths = if module_path.is_dir() {
+            module::modules_in_dir(module_path)?
+        } else {
+            vec![module_path.clone()]
+        };
+        anyhow::ensure!(
+            !paths.is_empty(),
+            "No .wasm.v1 modules found in {}.",
+            module_path.display()
+        );
+        let num_sigs = match accounts::load_keys(&app.keys_path)?.first() {
+            Some(keys) => keys.num_keys(),
+            None => app.num_signatures,
+        };
+        let params = chain::ChainParams::fetch(client).await?;
+        let mut estimates = Vec::new();
+        for path in paths {
+            let module = module::read_module(&path)?;
+            estimates.push(module::DeployEstimate::new(
+                &path, module, num_sigs, &params,
+            ));
+        }
+        match app.output {
+            OutputFormat::Human | OutputFormat::Csv => {
+                for estimate in &estimates {
+                    estimate.print(&app.printer);
+                }
+                if estimates.len() > 1 {
+                    let total = estimates.iter().map(|e| e.energy.energy).sum::<u64>();
+                    app.printer.field("total energy", total);
+                    app.printer.field(
+                        "total cost",
+                        format!("{} CCD", params.energy_cost(Energy::from(total))),
+                    );
+                }
+            }
+            OutputFormat::Json => app.printer.print_json(&estimates)?,
+        }
+        return Ok(());
+    }
+    if let Action::UpgradeHistory {
+        address,
+        from_block,
+        to_block,
+    } = &action
+    {
+        let address = app.contract(*address)?;
+        let history =
+            upgrades::History::scan(client, address, from_block, to_block, app.verbose).await?;
+        match app.output {
+            OutputFormat::Human => history.print(),
+            OutputFormat::Json => app.printer.print_json(&history)?,
+            OutputFormat::Csv => history.print_csv()?,
+        }
+        return Ok(());
+    }
+    if let Action::AccountHistory {
+        account,
+        from_block,
+        to_block,
+    } = &action
+    {
+        let history =
+            ledger::History::scan(client, *account, from_block, to_block, app.verbose).await?;
+        match app.output {
+            OutputFormat::Human => history.print(),
+            OutputFormat::Json => app.printer.print_json(&history)?,
+            OutputFormat::Csv => history.print_csv()?,
+        }
+        return Ok(());
+    }
+    if let Action::VerifyModule {
+        address,
+        module_path,
+    } = &action
+    {
+        let local = module::read_module(module_path)?;
+        let verification = module::Verification::fetch(client, *address, &local).await?;
+        match app.output {
+            OutputFormat::Human | OutputFormat::Csv => verification.print(&app.printer),
+            OutputFormat::Json => app.printer.print_json(&verification)?,
+        }
+        anyhow::ensure!(
+            verification.matches,
+            "Module mismatch: contract {} runs {}, but {} is {}.",
+            address,
+            verification.deployed,
+            module_path.display(),
+            verification.local
+        );
+        return Ok(());
+    }
+    if let Action::Balances {
+        address,
+        token_id,
+        owners,
+        owners_file,
+    } = &action
+    {
+        let address = app.contract(*address)?;
+        let owners = portfolio::parse_owners(owners.as_deref(), owners_file.as_deref())?;
+        let balances =
+            portfolio::OwnerBalances::fetch(client, address, token_id.clone(), &owners).await?;
+        match app.output {
+            OutputFormat::Human => balances.print(),
+            OutputFormat::Json => app.printer.print_json(&balances)?,
+            OutputFormat::Csv => balances.print_csv()?,
+        }
+        return Ok(());
+    }
+    if let Action::Holdings {
+        owner,
+        contracts,
+        token_ids,
+        token_id_range,
+        token_id_width,
+    } = &action
+    {
+        let contracts = portfolio::parse_contracts(contracts)?;
+        let token_ids = match token_id_range {
+            Some(range) => portfolio::range_token_ids(*range, *token_id_width)?,
+            None => token_ids.clone(),
+        };
+        let holdings = portfolio::Holdings::fetch(client, *owner, &contracts, &token_ids).await;
+        match app.output {
+            OutputFormat::Human => holdings.print(),
+            OutputFormat::Json => app.printer.print_json(&holdings)?,
+            OutputFormat::Csv => holdings.print_csv()?,
+        }
+        return Ok(());
+    }
+    if let Action::Supply {
+        schema,
+        address,
+        method,
+    } = &action
+    {
+        let address = app.contract(*address)?;
+        let schema = app
+            .schemas
+            .load_for_contract(client, address, schema)
+            .await?;
+        let config = config::Config::load(app.config.as_deref(), app.profile.as_deref())?;
+        // Only an explicit [methods] entry counts: `config.method` would fall
+        // back to "supply" itself and skip detecting the entrypoint.
+        let method = supply::method(
+            &schema,
+            CONTRACT_NAME,
+            method
+                .as_deref()
+                .or(config.methods.get("supply").map(String::as_str)),
+        )?;
+        let supply = supply::Supply::fetch(
+            client,
+            &app.invoke,
+            &schema,
+            CONTRACT_NAME,
+            address,
+            &method,
+        )
+        .await?;
+        match app.output {
+            OutputFormat::Human | OutputFormat::Csv => supply.print(),
+            OutputFormat::Json => app.printer.print_json(&supply)?,
+        }
+        return Ok(());
+    }
+    if let Action::StateDiff {
+        schema,
+        address,
+        block_before,
+        block_after,
+    } = &action
+    {
+        let address = app.contract(*address)?;
+        let schema = app
+            .schemas
+            .load_for_contract(client, address, schema)
+            .await?;
+        let config = config::Config::load(app.config.as_deref(), app.profile.as_deref())?;
+        let method = config.method("view");
+        let rv_schema = schema.get_receive_return_value_schema(CONTRACT_NAME, method)?;
+        let receive_name = schema::receive_name(CONTRACT_NAME, method, Some(&schema))?;
+        let mut states = Vec::new();
+        for block in [block_before, block_after] {
+            let block_id = block.resolve(client).await?;
+            let bytes = instance::invoke_at(
+                client,
+                &app.invoke,
+                &block_id,
+                address,
+                receive_name.clone(),
+                Default::default(),
+                Some(&schema),
+            )
+            .await
+            .with_context(|| format!("Could not query the state in {}.", block))?;
+            states.push(rv_schema.to_json(
+                &mut concordium_rust_sdk::smart_contracts::common::Cursor::new(&bytes[..]),
+            )?);
+        }
+        let changes = diff::diff(&states[0], &states[1]);
+        match app.output {
+            OutputFormat::Human => diff::print(&changes),
+            OutputFormat::Json => app.printer.print_json(&changes)?,
+            OutputFormat::Csv => diff::print_csv(&changes)?,
+        }
+        return Ok(());
+    }
+    if let Action::Events { tx, schema } = &action {
+        let schema = app.schemas.load(schema).await?;
+        let event_schema = schema::event_schema(&schema, CONTRACT_NAME);
+        let status = client.get_block_item_status(tx).await?;
+        let (block, summary) = status
+            .is_finalized()
+            .with_context(|| format!("Transaction {} is not finalized.", tx))?;
+        let events = events::decode_summary(event_schema.as_ref(), summary);
+        match app.output {
+            OutputFormat::Human => {
+                println!(
+                    "Transaction {} (block {}) logged {} event(s):",
+                    tx,
+                    block,
+                    events.len()
+                );
+                events::print_events(&events, &app.printer);
+            }
+            OutputFormat::Json => app.printer.print_json(&events)?,
+            OutputFormat::Csv => events::print_events_csv(&events)?,
+        }
+        return Ok(());
+    }
+    if let Action::SubmitRaw { hex, file } = &action {
+        let bytes = match (hex, file) {
+            (Some(hex), _) => hex.as_bytes().to_vec(),
+            (None, Some(file)) => {
+                std::fs::read(file).context("Unable to read the transaction file.")?
+            }
+            (None, None) => anyhow::bail!("Either --hex or --file is required."),
+        };
+        let item = submit::parse_block_item(&bytes)?;
+        let transaction_hash = session.submitter.send_item(client, &item).await?;
+        session.submitter.wait(client, &transaction_hash).await?;
+        return Ok(());
+    }
+
     // load account keys and sender address from a file
-    let keys: WalletAccount =
-        WalletAccount::from_json_file(app.keys_path).context("Could not read the keys file.")?;
+    if session.keys.is_none() {
+        let accounts = match (&app.signing_key, app.sender) {
+            (Some(key), Some(sender)) => {
+                eprintln!(
+                    "Warning: --signing-key exposes the key in the shell history and process \
+                     list. Only use it for test accounts."
+                );
+                vec![accounts::signing_key_account(key, sender)?]
+            }
+            _ => accounts::load_keys(&app.keys_path)?,
+        };
+        session.keys = Some(
+            accounts
+                .into_iter()
+                .map(|account| accounts::select_keys(account, &app.key_index))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        );
+    }
+    let all_keys: &[WalletAccount] = session.keys.as_deref().unwrap_or_default();
+    anyhow::ensure!(
+        !all_keys.is_empty() || app.build_unsigned || app.signer_url.is_some(),
+        "The --account key file is required for this action."
+    );
+    let keys: Option<&WalletAccount> = all_keys.first();
+    let sender = match (app.sender, keys) {
+        (Some(sender), _) => sender,
+        (None, Some(keys)) => keys.address,
+        (None, None) => anyhow::bail!("Either --account or --sender is required."),
+    };
+    // Signed transactions are sent by the signing account. Only transactions
+    // that are built for signing elsewhere or simulated can have another one.
+    if let Some(keys) = keys.filter(|keys| keys.address != sender) {
+        anyhow::ensure!(
+            app.build_unsigned || app.dry_run || app.parameter_validate_only,
+            "--sender {} differs from the signing account {}, which is only possible with \
+             --build-unsigned, --dry-run or --parameter-validate-only.",
+            sender,
+            keys.address
+        );
+    }
+    let num_sigs = match keys.filter(|keys| keys.address == sender) {
+        Some(keys) => keys.num_keys(),
+        None => app.num_signatures,
+    };
 
     // Get the initial nonce at the last finalized block.
     let acc_info: AccountInfo = client
-        .get_account_info(&keys.address.into(), &v2::BlockIdentifier::Best)
+        .get_account_info(&sender.into(), &v2::BlockIdentifier::Best)
         .await?
         .response;
 
-    let nonce = acc_info.account_nonce;
-    // set expiry to now + 5min
-    let expiry: TransactionTime =
-        TransactionTime::from_seconds((chrono::Utc::now().timestamp() + 300) as u64);
+    // The pending nonce also counts transactions that are not yet finalized.
+    // Only the command that follows a submission can rely on the tracked nonce.
+    let tracked = session.nonce.take();
+    let nonce = if let Some((_, nonce)) = tracked.filter(|(address, _)| *address == sender) {
+        nonce
+    } else if app.allow_unconfirmed_nonce {
+        client
+            .get_next_account_sequence_number(&sender)
+            .await?
+            .nonce
+    } else {
+        acc_info.account_nonce
+    };
+    // set expiry to now + 5min, or to the --expiry-absolute
+    let expiry: TransactionTime = session.submitter.expiry();
+
+    let config = config::Config::load(app.config.as_deref(), app.profile.as_deref())?;
+    let energy_for = |method: &str| config.energy_for(app.energy, method);
+    let entrypoint = |method: &'static str| config.method(method);
 
-    let tx = match app.action {
+    // set when the schema is loaded, for decoding the events of a dry run
+    let mut event_schema = None;
+    session.submitter.set_error_schema(None);
+    // set with the schema, for --parameter-validate-only
+    let mut module_schema = None;
+    let mut validated_input = None;
+    // set for mints and transfers whose events are checked after finalization
+    let mut intent = None;
+    // set for mints whose metadata hashes are checked after finalization
+    let mut metadata_check = None;
+    // set for permits, whose transaction must not expire after the permit
+    let mut permit_valid_until = None;
+    let mut tx = match action {
         Action::Init {
             module_ref: mod_ref,
+            amount,
         } => {
             let param = OwnedParameter::empty();
             //                 .expect("Known to not exceed parameter size limit.");
             let payload = InitContractPayload {
-                amount: Amount::zero(),
+                amount,
                 mod_ref,
-                init_name: OwnedContractName::new_unchecked(
-                    "init_rust_sdk_minting_tutorial".to_string(),
-                ),
+                init_name: OwnedContractName::new_unchecked(format!("init_{}", CONTRACT_NAME)),
                 param,
             };
-            TransactionResult::StateChanging(send::init_contract(
-                &keys,
-                keys.address,
-                nonce,
-                expiry,
-                payload,
-                10000u64.into(),
+            TransactionResult::StateChanging(TransactionPlan::new(
+                Payload::InitContract { payload },
+                energy_for("init"),
             ))
         }
-        Action::Deploy { module_path } => {
-            let contents = std::fs::read(module_path).context("Could not read contract module.")?;
-            let payload: WasmModule =
-                common::Deserial::deserial(&mut std::io::Cursor::new(contents))?;
-            TransactionResult::StateChanging(send::deploy_module(
-                &keys,
-                keys.address,
-                nonce,
-                expiry,
-                payload,
-            ))
+        Action::Deploy {
+            module_path,
+            continue_on_error,
+            ..
+        } => {
+            if module_path.is_dir() {
+                anyhow::ensure!(
+                    !app.build_unsigned,
+                    "--build-unsigned does not support deploying a directory."
+                );
+                anyhow::ensure!(
+                    app.signer_url.is_none(),
+                    "--signer-url does not support deploying a directory."
+                );
+                anyhow::ensure!(
+                    !app.dry_run && !app.parameter_validate_only,
+                    "--dry-run and --parameter-validate-only do not support deploying a \
+                     directory, use --estimate to see the costs."
+                );
+                let mut pool = accounts::AccountPool::new(client, all_keys).await?;
+                let params = chain::ChainParams::fetch(client).await?;
+                let modules = module::modules_in_dir(&module_path)?;
+                anyhow::ensure!(
+                    !modules.is_empty(),
+                    "No .wasm.v1 modules found in {}.",
+                    module_path.display()
+                );
+                let mut failed = 0;
+                for path in modules {
+                    app.printer
+                        .progress(format_args!("Deploying {}.", path.display()));
+                    let result = async {
+                        let plan = TransactionPlan::deploy_module(module::read_module(&path)?);
+                        let account = pool.next_account();
+                        let expiry = session.submitter.expiry();
+                        let energy = plan
+                            .construct(
+                                account.keys.address,
+                                account.keys.num_keys(),
+                                account.nonce,
+                                expiry,
+                            )
+                            .header
+                            .energy_amount;
+                        // fail before signing if the account cannot pay for it
+                        let cost = account.check_funds(&params, plan.amount(), energy)?;
+                        let finalized = match session
+                            .submitter
+                            .send_plan(
+                                client,
+                                account.keys,
+                                &plan,
+                                account.nonce,
+                                expiry,
+                                app.retry_on_reject,
+                            )
+                            .await
+                        {
+                            Ok(finalized) => finalized,
+                            Err(e) => {
+                                // the nonce may or may not have been used
+                                account.nonce = client
+                                    .get_next_account_sequence_number(&account.keys.address)
+                                    .await?
+                                    .nonce;
+                                return Err(e);
+                            }
+                        };
+                        // a resubmission with --retry-on-reject may have used a fresh nonce
+                        if let Some(used) = finalized.nonce {
+                            account.nonce = used;
+                        }
+                        account.record_submission(cost);
+                        // a module rejected on chain is not deployed
+                        anyhow::ensure!(
+                            finalized.outcome == "success",
+                            "Transaction {} was {}.",
+                            finalized.hash,
+                            finalized.outcome
+                        );
+                        Ok(())
+                    }
+                    .await;
+                    if let Err(e) = result {
+                        if !continue_on_error {
+                            return Err(e);
+                        }
+                        eprintln!("Failed to deploy {}: {:#}", path.display(), e);
+                        failed += 1;
+                    }
+                }
+                pool.print_report(&app.printer);
+                anyhow::ensure!(failed == 0, "{} module(s) failed to deploy.", failed);
+                return Ok(());
+            }
+            let payload = module::read_module(&module_path)?;
+            TransactionResult::StateChanging(TransactionPlan::deploy_module(payload))
         }
         Action::WithSchema {
             parameter,
             schema,
             address,
             transaction_type_,
+            verify_roundtrip,
+            check_operator,
+            follow_operator_chain,
+            check_paused,
+            amount,
+            from_file,
+            token_id_start,
+            parameter_from_return,
+            from_field,
+            token_ids,
+            fetch_metadata,
+            ipfs_gateways,
+            ipfs_timeout,
+            token_metadata_hash_check,
+            parameter_items,
+            parameter_base64,
+            data,
+            count,
+            token_id_width,
+            metadata_url,
+            continue_on_error,
+            dry_run_all,
+            continue_on_dry_run_failure,
+            max_items_per_tx,
+            new_module_ref,
+            migrate,
+            new_owner,
+            role,
+            role_address,
+            yes,
+            permit_signer,
+            permit_entrypoint,
+            permit_nonce,
+            check_schema,
+            force,
         } => {
-            let parameter: serde_json::Value = serde_json::from_slice(
-                &std::fs::read(parameter.unwrap()).context("Unable to read parameter file.")?,
-            )
-            .context("Unable to parse parameter JSON.")?;
+            let address = app.contract(address)?;
+            let schema = app
+                .schemas
+                .load_for_contract(client, address, &schema)
+                .await?;
+            event_schema = schema::event_schema(&schema, CONTRACT_NAME);
+            session.submitter.set_error_schema(Some(schema.clone()));
+            if app.parameter_validate_only {
+                module_schema = Some(schema.clone());
+            }
+            if check_schema {
+                let mismatches =
+                    instance::schema_mismatches(client, address, &schema, CONTRACT_NAME).await?;
+                for mismatch in &mismatches {
+                    eprintln!("Warning: {}", mismatch);
+                }
+                anyhow::ensure!(
+                    mismatches.is_empty() || force,
+                    "The schema does not match contract {}. Pass --force to proceed anyway.",
+                    address
+                );
+            }
+            if check_paused {
+                anyhow::ensure!(
+                    matches!(transaction_type_, TransactionType::Mint),
+                    "--check-paused is only supported for the Mint transaction type."
+                );
+                admin::ensure_not_paused(
+                    client,
+                    &app.invoke,
+                    &schema,
+                    CONTRACT_NAME,
+                    address,
+                    entrypoint("paused"),
+                )
+                .await?;
+            }
 
-            let schemab64 = std::fs::read(schema).context("Unable to read the schema file.")?;
-            let schema_source = general_purpose::STANDARD_NO_PAD.decode(schemab64);
+            // The parameter of the update, read from the file or piped from the
+            // return value of another entrypoint.
+            let mut input = match &parameter_from_return {
+                Some(source) => {
+                    let source_parameter = parameter
+                        .as_deref()
+                        .map(|p| params::read_json(p, app.parameter_format))
+                        .transpose()?;
+                    let returned = instance::invoke_json(
+                        client,
+                        &app.invoke,
+                        &schema,
+                        CONTRACT_NAME,
+                        address,
+                        source,
+                        source_parameter.as_ref(),
+                    )
+                    .await?;
+                    let selected = params::select(&returned, from_field.as_deref().unwrap_or("$"))?;
+                    eprintln!("Parameter from {}: {}", source, selected);
+                    Some(selected.clone())
+                }
+                None if !token_ids.is_empty() => {
+                    anyhow::ensure!(
+                        matches!(transaction_type_, TransactionType::TokenMetadata),
+                        "--token-ids is only supported for the TokenMetadata transaction type."
+                    );
+                    let param_schema = schema
+                        .get_receive_param_schema(CONTRACT_NAME, entrypoint("tokenMetadata"))?;
+                    Some(cis2::token_metadata_params_json(&param_schema, &token_ids)?)
+                }
+                None if !parameter_items.is_empty() => {
+                    let method = match transaction_type_ {
+                        TransactionType::Mint => "mint",
+                        TransactionType::Transfer => "transfer",
+                        TransactionType::TokenMetadata => "tokenMetadata",
+                        _ => anyhow::bail!(
+                            "--parameter-item is only supported for the Mint, Transfer and \
+                             TokenMetadata transaction types."
+                        ),
+                    };
+                    let param_schema =
+                        schema::receive_param_schema(&schema, CONTRACT_NAME, entrypoint(method))?;
+                    Some(params::wrap_items(
+                        entrypoint(method),
+                        param_schema.as_ref(),
+                        parameter_items,
+                    )?)
+                }
+                None if from_file.is_none() && token_id_start.is_none() => parameter
+                    .as_deref()
+                    .map(|p| params::read_json(p, app.parameter_format))
+                    .transpose()?,
+                None => None,
+            };
+            anyhow::ensure!(
+                data.is_none() || matches!(transaction_type_, TransactionType::Transfer),
+                "--data is only supported for the Transfer transaction type."
+            );
+            if let (TransactionType::Transfer, Some(input)) = (&transaction_type_, &mut input) {
+                cis2::fill_transfer_data(input, data.as_deref().unwrap_or_default())?;
+            }
 
-            let schema = concordium_std::from_bytes::<concordium_std::schema::VersionedModuleSchema>(
-                &schema_source?,
-            )?;
+            let batch = match (from_file, token_id_start) {
+                (Some(from_file), _) => {
+                    anyhow::ensure!(
+                        matches!(transaction_type_, TransactionType::Transfer),
+                        "--from-file is only supported for the Transfer transaction type."
+                    );
+                    let transfers =
+                        batch::read_transfers(&from_file, data.as_deref().unwrap_or_default())?;
+                    let param_schema =
+                        schema.get_receive_param_schema(CONTRACT_NAME, entrypoint("transfer"))?;
+                    Some((
+                        "transfer",
+                        batch::transfer_items(&param_schema, &transfers)?,
+                    ))
+                }
+                (None, Some(start)) => {
+                    anyhow::ensure!(
+                        matches!(transaction_type_, TransactionType::Mint),
+                        "--token-id-start is only supported for the Mint transaction type."
+                    );
+                    let template = params::read_json(
+                        parameter
+                            .as_ref()
+                            .context("--parameter is required as the template of the mints.")?,
+                        app.parameter_format,
+                    )?;
+                    let param_schema =
+                        schema.get_receive_param_schema(CONTRACT_NAME, entrypoint("mint"))?;
+                    let token_ids = cis2::sequential_token_ids(
+                        start,
+                        count.context("--count is required.")?,
+                        cis2::token_id_width(&param_schema, token_id_width)?,
+                    )?;
+                    Some((
+                        "mint",
+                        batch::mint_items(
+                            &param_schema,
+                            &template,
+                            &token_ids,
+                            metadata_url.as_deref(),
+                            app.checks(false),
+                        )?,
+                    ))
+                }
+                (None, None) => match (&transaction_type_, &input) {
+                    (TransactionType::Mint, Some(parameter)) => {
+                        let param_schema =
+                            schema.get_receive_param_schema(CONTRACT_NAME, entrypoint("mint"))?;
+                        cis2::check_metadata_urls(&param_schema, parameter)?;
+                        batch::split_mint(
+                            &param_schema,
+                            parameter,
+                            max_items_per_tx,
+                            app.checks(false),
+                        )?
+                        .map(|items| ("mint", items))
+                    }
+                    _ => None,
+                },
+            };
+            if let Some((method, items)) = batch {
+                anyhow::ensure!(
+                    !app.build_unsigned,
+                    "--build-unsigned does not support batches."
+                );
+                anyhow::ensure!(
+                    !app.parameter_validate_only,
+                    "--parameter-validate-only does not support batches."
+                );
+                anyhow::ensure!(
+                    app.max_fee.is_none(),
+                    "--max-fee does not support batches, use --energy."
+                );
+                anyhow::ensure!(
+                    !app.require_finalization_match,
+                    "--require-finalization-match does not support batches."
+                );
+                anyhow::ensure!(
+                    !token_metadata_hash_check,
+                    "--token-metadata-hash-check does not support batches."
+                );
+                anyhow::ensure!(
+                    app.signer_url.is_none(),
+                    "--signer-url does not support batches."
+                );
+                anyhow::ensure!(
+                    !app.retry_on_reject,
+                    "--retry-on-reject does not support batches."
+                );
+                let receive_name =
+                    schema::receive_name(CONTRACT_NAME, entrypoint(method), Some(&schema))?;
+                if app.verbose {
+                    batch::print_plan(method, &items, &app.printer);
+                }
+                let mut pool = accounts::AccountPool::new(client, all_keys).await?;
+                if dry_run_all || app.dry_run {
+                    let simulated = batch::dry_run_items(
+                        client,
+                        &app.invoke,
+                        &pool,
+                        address,
+                        &receive_name,
+                        &items,
+                        amount,
+                        energy_for(method),
+                        Some(&schema),
+                    )
+                    .await?;
+                    let rejected = simulated.iter().filter(|o| o.outcome != "success").count();
+                    if app.dry_run {
+                        match app.output {
+                            OutputFormat::Human => batch::print_dry_run(&simulated, &app.printer),
+                            OutputFormat::Json => app.printer.print_json(&simulated)?,
+                            OutputFormat::Csv => batch::print_dry_run_csv(&simulated)?,
+                        }
+                        anyhow::ensure!(
+                            rejected == 0,
+                            "{} of {} transaction(s) would be rejected.",
+                            rejected,
+                            simulated.len()
+                        );
+                        return Ok(());
+                    }
+                    // Stdout holds a single document, so the simulation is only
+                    // printed there in place of the outcomes when nothing is
+                    // submitted. Otherwise the rejections go with the progress.
+                    match app.output {
+                        OutputFormat::Human => batch::print_dry_run(&simulated, &app.printer),
+                        _ if rejected == 0 => {}
+                        _ if !continue_on_dry_run_failure => match app.output {
+                            OutputFormat::Json => app.printer.print_json(&simulated)?,
+                            _ => batch::print_dry_run_csv(&simulated)?,
+                        },
+                        _ => {
+                            for o in simulated.iter().filter(|o| o.outcome != "success") {
+                                app.printer.progress(format_args!(
+                                    "Item {} would be {}.",
+                                    o.item, o.outcome
+                                ));
+                            }
+                        }
+                    }
+                    anyhow::ensure!(
+                        rejected == 0 || continue_on_dry_run_failure,
+                        "{} of {} transaction(s) would be rejected, so none were submitted.",
+                        rejected,
+                        simulated.len()
+                    );
+                }
+                let params = chain::ChainParams::fetch(client).await?;
+                let started = std::time::Instant::now();
+                let outcomes = batch::submit_updates(
+                    client,
+                    &session.submitter,
+                    &mut pool,
+                    address,
+                    &params,
+                    &receive_name,
+                    items,
+                    amount,
+                    energy_for(method),
+                    continue_on_error,
+                )
+                .await?;
+                let metrics = batch::Metrics::new(&outcomes, started.elapsed());
+                match app.output {
+                    OutputFormat::Human => {
+                        batch::print_outcomes(&outcomes, &app.printer);
+                        pool.print_report(&app.printer);
+                        metrics.print();
+                    }
+                    OutputFormat::Json => app.printer.print_json(&outcomes)?,
+                    OutputFormat::Csv => batch::print_outcomes_csv(&outcomes)?,
+                }
+                if let Some(path) = &app.metrics_file {
+                    metrics.write(path)?;
+                }
+                let failed = outcomes.iter().filter(|o| o.outcome != "success").count();
+                anyhow::ensure!(failed == 0, "{} transaction(s) failed.", failed);
+                return Ok(());
+            }
+
+            let parameter = input;
+            validated_input = parameter.clone();
+            let raw_parameter = parameter_base64
+                .as_deref()
+                .map(params::read_base64)
+                .transpose()?;
+            anyhow::ensure!(
+                raw_parameter.is_none()
+                    || matches!(
+                        transaction_type_,
+                        TransactionType::Mint | TransactionType::Transfer
+                    ),
+                "--parameter-base64 is only supported for the Mint and Transfer transaction types."
+            );
             // schema_global = schema;
             match transaction_type_ {
                 TransactionType::Mint => {
                     let param_schema =
-                        schema.get_receive_param_schema("rust_sdk_minting_tutorial", "mint")?;
-                    let serialized_parameter = param_schema.serial_value(&parameter)?;
-                    let message = OwnedParameter::try_from(serialized_parameter).unwrap();
+                        schema::receive_param_schema(&schema, CONTRACT_NAME, entrypoint("mint"))?;
+                    if let (Some(param_schema), Some(parameter)) = (&param_schema, &parameter) {
+                        cis2::check_metadata_urls(param_schema, parameter)?;
+                    }
+                    if let (true, Some(parameter)) = (app.require_finalization_match, &parameter) {
+                        intent = Some(intent::expected_mints(parameter)?);
+                    }
+                    if token_metadata_hash_check {
+                        metadata_check = Some(metadata::Resolver::new(
+                            &ipfs_gateways,
+                            std::time::Duration::from_secs(ipfs_timeout),
+                            app.verbose,
+                        )?);
+                    }
+                    let message = match raw_parameter {
+                        Some(raw) => raw,
+                        None => params::serialize_optional(
+                            entrypoint("mint"),
+                            param_schema.as_ref(),
+                            parameter.as_ref(),
+                            app.checks(verify_roundtrip),
+                        )?,
+                    };
                     let payload = UpdateContractPayload {
-                        amount: Amount::zero(),
+                        amount,
                         address,
-                        receive_name: OwnedReceiveName::new_unchecked(
-                            "rust_sdk_minting_tutorial.mint".to_string(),
-                        ),
+                        receive_name: schema::receive_name(
+                            CONTRACT_NAME,
+                            entrypoint("mint"),
+                            Some(&schema),
+                        )?,
                         message,
                     };
 
-                    TransactionResult::StateChanging(send::update_contract(
-                        &keys,
-                        keys.address,
-                        nonce,
-                        expiry,
-                        payload,
-                        10000u64.into(),
+                    TransactionResult::StateChanging(TransactionPlan::new(
+                        Payload::Update { payload },
+                        energy_for("mint"),
                     ))
                 }
                 //// Transfer Transaction which changes the state
                 TransactionType::Transfer => {
-                    let param_schema =
-                        schema.get_receive_param_schema("rust_sdk_minting_tutorial", "transfer")?;
-                    let serialized_parameter = param_schema.serial_value(&parameter)?;
-                    let message = OwnedParameter::try_from(serialized_parameter).unwrap();
+                    if let (true, Some(parameter)) =
+                        (check_operator || follow_operator_chain, &parameter)
+                    {
+                        cis2::check_transfer_authorized(
+                            client,
+                            &app.invoke,
+                            &schema,
+                            CONTRACT_NAME,
+                            address,
+                            sender,
+                            parameter,
+                            entrypoint("operatorOf"),
+                            follow_operator_chain,
+                        )
+                        .await?;
+                    }
+                    if let (true, Some(parameter)) = (app.require_finalization_match, &parameter) {
+                        intent = Some(intent::expected_transfers(parameter)?);
+                    }
+                    let param_schema = schema::receive_param_schema(
+                        &schema,
+                        CONTRACT_NAME,
+                        entrypoint("transfer"),
+                    )?;
+                    let message = match raw_parameter {
+                        Some(raw) => raw,
+                        None => params::serialize_optional(
+                            entrypoint("transfer"),
+                            param_schema.as_ref(),
+                            parameter.as_ref(),
+                            app.checks(verify_roundtrip),
+                        )?,
+                    };
                     let payload = UpdateContractPayload {
-                        amount: Amount::zero(),
+                        amount,
                         address,
-                        receive_name: OwnedReceiveName::new_unchecked(
-                            "rust_sdk_minting_tutorial.transfer".to_string(),
-                        ),
+                        receive_name: schema::receive_name(
+                            CONTRACT_NAME,
+                            entrypoint("transfer"),
+                            Some(&schema),
+                        )?,
                         message,
                     };
                     //// call update contract with the payload
-                    TransactionResult::StateChanging(send::update_contract(
-                        &keys,
-                        keys.address,
-                        nonce,
-                        expiry,
-                        payload,
-                        10000u64.into(),
+                    TransactionResult::StateChanging(TransactionPlan::new(
+                        Payload::Update { payload },
+                        energy_for("transfer"),
                     ))
                 }
-                /// Token Metadata function with no state change
+                // Token Metadata function with no state change
                 TransactionType::TokenMetadata => {
-                    let param_schema = schema
-                        .get_receive_param_schema("rust_sdk_minting_tutorial", "tokenMetadata")?;
+                    let param_schema = schema::receive_param_schema(
+                        &schema,
+                        CONTRACT_NAME,
+                        entrypoint("tokenMetadata"),
+                    )?;
                     let rv_schema = schema.get_receive_return_value_schema(
-                        "rust_sdk_minting_tutorial",
-                        "tokenMetadata",
+                        CONTRACT_NAME,
+                        entrypoint("tokenMetadata"),
                     )?;
 
-                    let serialized_parameter = param_schema.serial_value(&parameter)?;
+                    let parameter = params::serialize_optional(
+                        entrypoint("tokenMetadata"),
+                        param_schema.as_ref(),
+                        parameter.as_ref(),
+                        app.checks(verify_roundtrip),
+                    )?;
                     let context = ContractContext {
                         invoker: None, //Account(AccountAddress),
                         contract: address,
                         amount: Amount::zero(),
-                        method: OwnedReceiveName::new_unchecked(
-                            "rust_sdk_minting_tutorial.tokenMetadata".to_string(),
-                        ),
-                        parameter: OwnedParameter::try_from(serialized_parameter).unwrap(), //Default::default(),
-                        energy: 1000000.into(),
+                        method: schema::receive_name(
+                            CONTRACT_NAME,
+                            entrypoint("tokenMetadata"),
+                            Some(&schema),
+                        )?,
+                        parameter,
+                        energy: app.invoke.energy,
                     };
                     // invoke instance
                     let info = client
                         .invoke_instance(&BlockIdentifier::Best, &context)
                         .await?;
+                    app.invoke
+                        .dump_raw_response(&context.method, &info.response);
+                    app.invoke
+                        .warn_if_out_of_energy(&context.method, &info.response);
 
                     match info.response {
                             concordium_rust_sdk::types::smart_contracts::InvokeContractResult::Success { return_value, .. } => {
                                 let bytes: concordium_rust_sdk::types::smart_contracts::ReturnValue = return_value.unwrap();
-                                // deserialize and print return value
-                                println!( "{}",rv_schema.to_json_string_pretty(&bytes.value)?);//jsonxf::pretty_print(&param_schema.to_json_string_pretty(&bytes.value)?).unwrap());
+                                if token_ids.is_empty() {
+                                    // deserialize and print return value
+                                    app.printer.print_return_value(&rv_schema, &bytes.value)?;
+                                } else {
+                                    let resolver = if fetch_metadata {
+                                        Some(metadata::Resolver::new(
+                                            &ipfs_gateways,
+                                            std::time::Duration::from_secs(ipfs_timeout),
+                                            app.verbose,
+                                        )?)
+                                    } else {
+                                        None
+                                    };
+                                    print_metadata_urls(&rv_schema, &bytes.value, &token_ids, resolver.as_ref(), &app.printer).await?;
+                                }
                             }
                             _ => {
                                 println!("Could'nt succesfully invoke the instance. Check the parameters.")
@@ -276,28 +2430,34 @@ async fn main() -> anyhow::Result<()> {
                 }
                 TransactionType::View => {
                     let rv_schema = schema
-                        .get_receive_return_value_schema("rust_sdk_minting_tutorial", "view")?;
+                        .get_receive_return_value_schema(CONTRACT_NAME, entrypoint("view"))?;
 
                     let context = ContractContext {
                         invoker: None, //Account(AccountAddress),
                         contract: address,
                         amount: Amount::zero(),
-                        method: OwnedReceiveName::new_unchecked(
-                            "rust_sdk_minting_tutorial.view".to_string(),
-                        ),
+                        method: schema::receive_name(
+                            CONTRACT_NAME,
+                            entrypoint("view"),
+                            Some(&schema),
+                        )?,
                         parameter: Default::default(),
-                        energy: 1000000.into(),
+                        energy: app.invoke.energy,
                     };
                     // invoke instance
                     let info = client
                         .invoke_instance(&BlockIdentifier::Best, &context)
                         .await?;
+                    app.invoke
+                        .dump_raw_response(&context.method, &info.response);
+                    app.invoke
+                        .warn_if_out_of_energy(&context.method, &info.response);
 
                     match info.response {
                             concordium_rust_sdk::types::smart_contracts::InvokeContractResult::Success { return_value, .. } => {
                                 let bytes: concordium_rust_sdk::types::smart_contracts::ReturnValue = return_value.unwrap();
                                 // deserialize and print return value
-                                println!( "{}",rv_schema.to_json_string_pretty(&bytes.value)?);//jsonxf::pretty_print(&param_schema.to_json_string_pretty(&bytes.value)?).unwrap());
+                                app.printer.print_return_value(&rv_schema, &bytes.value)?;
                             }
                             _ => {
                                 println!("Could'nt succesfully invoke the instance. Check the parameters.")
@@ -307,49 +2467,421 @@ async fn main() -> anyhow::Result<()> {
 
                     // info
                 }
+                TransactionType::Upgrade => {
+                    let module_ref =
+                        new_module_ref.context("--new-module-ref is required for an upgrade.")?;
+                    admin::ensure_deployed(client, module_ref).await?;
+                    let migration = match &migrate {
+                        Some(entrypoint) => {
+                            let param_schema =
+                                schema::receive_param_schema(&schema, CONTRACT_NAME, entrypoint)?;
+                            Some(params::serialize_optional(
+                                entrypoint,
+                                param_schema.as_ref(),
+                                parameter.as_ref(),
+                                app.checks(verify_roundtrip),
+                            )?)
+                        }
+                        None => {
+                            anyhow::ensure!(
+                                parameter.is_none(),
+                                "--parameter is the migration parameter of an upgrade and \
+                                 requires --migrate."
+                            );
+                            None
+                        }
+                    };
+                    let upgrade = admin::upgrade_params_json(
+                        module_ref,
+                        migrate.as_deref().zip(migration.as_ref()),
+                    );
+                    let param_schema = schema::receive_param_schema(
+                        &schema,
+                        CONTRACT_NAME,
+                        entrypoint("upgrade"),
+                    )?;
+                    let message = params::serialize_optional(
+                        entrypoint("upgrade"),
+                        param_schema.as_ref(),
+                        Some(&upgrade),
+                        app.checks(verify_roundtrip),
+                    )?;
+                    let payload = UpdateContractPayload {
+                        amount,
+                        address,
+                        receive_name: schema::receive_name(
+                            CONTRACT_NAME,
+                            entrypoint("upgrade"),
+                            Some(&schema),
+                        )?,
+                        message,
+                    };
+                    TransactionResult::StateChanging(TransactionPlan::new(
+                        Payload::Update { payload },
+                        energy_for("upgrade"),
+                    ))
+                }
+                TransactionType::Pause | TransactionType::Unpause => {
+                    anyhow::ensure!(
+                        parameter.is_none(),
+                        "Pause and Unpause build their parameter and take no --parameter."
+                    );
+                    let paused = matches!(transaction_type_, TransactionType::Pause);
+                    let (method, parameter) =
+                        admin::pause_call(&schema, CONTRACT_NAME, paused, &config)?;
+                    let param_schema =
+                        schema::receive_param_schema(&schema, CONTRACT_NAME, entrypoint(method))?;
+                    let message = params::serialize_optional(
+                        entrypoint(method),
+                        param_schema.as_ref(),
+                        parameter.as_ref(),
+                        app.checks(verify_roundtrip),
+                    )?;
+                    let payload = UpdateContractPayload {
+                        amount,
+                        address,
+                        receive_name: schema::receive_name(
+                            CONTRACT_NAME,
+                            entrypoint(method),
+                            Some(&schema),
+                        )?,
+                        message,
+                    };
+                    TransactionResult::StateChanging(TransactionPlan::new(
+                        Payload::Update { payload },
+                        energy_for(method),
+                    ))
+                }
+                TransactionType::TransferOwnership
+                | TransactionType::GrantRole
+                | TransactionType::RevokeRole => {
+                    anyhow::ensure!(
+                        parameter.is_none(),
+                        "Ownership and role changes build their parameter from flags and take no \
+                         --parameter."
+                    );
+                    let change = admin_change(&transaction_type_, new_owner, role, role_address)?;
+                    let method = entrypoint(change.method());
+                    let parameter = change.parameter(&schema, CONTRACT_NAME, method)?;
+                    let receive_name = schema::receive_name(CONTRACT_NAME, method, Some(&schema))?;
+                    let param_schema =
+                        schema::receive_param_schema(&schema, CONTRACT_NAME, method)?;
+                    let message = params::serialize_optional(
+                        method,
+                        param_schema.as_ref(),
+                        Some(&parameter),
+                        app.checks(verify_roundtrip),
+                    )?;
+                    if !yes && !app.dry_run && !app.build_unsigned {
+                        admin::confirm(&format!("Really {}?", change.describe(address)))?;
+                    }
+                    let payload = UpdateContractPayload {
+                        amount,
+                        address,
+                        receive_name,
+                        message,
+                    };
+                    TransactionResult::StateChanging(TransactionPlan::new(
+                        Payload::Update { payload },
+                        energy_for(change.method()),
+                    ))
+                }
+                TransactionType::Permit => {
+                    let signer_path =
+                        permit_signer.context("--permit-signer is required for a permit.")?;
+                    let signer = accounts::load_keys(std::slice::from_ref(&signer_path))?
+                        .pop()
+                        .context("--permit-signer must be a single key file.")?;
+                    let entrypoint = permit_entrypoint
+                        .context("--permit-entrypoint is required for a permit.")?;
+                    schema::receive_name(CONTRACT_NAME, &entrypoint, Some(&schema))?;
+                    let payload_schema =
+                        schema::receive_param_schema(&schema, CONTRACT_NAME, &entrypoint)?;
+                    let payload = params::serialize_optional(
+                        &entrypoint,
+                        payload_schema.as_ref(),
+                        parameter.as_ref(),
+                        app.checks(verify_roundtrip),
+                    )?;
+                    let expected = permit::nonce_of(
+                        client,
+                        &app.invoke,
+                        &schema,
+                        CONTRACT_NAME,
+                        address,
+                        config.method("nonceOf"),
+                        &signer,
+                    )
+                    .await?;
+                    let permit_nonce = match (permit_nonce, expected) {
+                        (Some(given), Some(expected)) => {
+                            anyhow::ensure!(
+                                given == expected,
+                                "The contract expects the permit nonce {} from {}, not {}.",
+                                expected,
+                                signer.address,
+                                given
+                            );
+                            given
+                        }
+                        (Some(given), None) => given,
+                        (None, Some(expected)) => expected,
+                        (None, None) => anyhow::bail!(
+                            "The contract has no {} entrypoint, --permit-nonce is required.",
+                            config.method("nonceOf")
+                        ),
+                    };
+                    let permit_expiry = session.submitter.expiry();
+                    permit_valid_until = Some(permit_expiry);
+                    let valid_until =
+                        chrono::NaiveDateTime::from_timestamp_opt(permit_expiry.seconds as i64, 0)
+                            .map(|t| chrono::DateTime::<chrono::Utc>::from_utc(t, chrono::Utc))
+                            .context("Invalid expiry.")?;
+                    let signed = permit::sign(
+                        &schema,
+                        CONTRACT_NAME,
+                        config.method("permit"),
+                        &signer,
+                        &permit::Message {
+                            contract: address,
+                            nonce: permit_nonce,
+                            expiry: valid_until,
+                            entrypoint: &entrypoint,
+                            payload: &payload,
+                        },
+                    )?;
+                    permit::check_message_hash(
+                        client,
+                        &app.invoke,
+                        &schema,
+                        CONTRACT_NAME,
+                        address,
+                        config.method("viewMessageHash"),
+                        &signed,
+                    )
+                    .await?;
+                    let param_schema =
+                        schema.get_receive_param_schema(CONTRACT_NAME, config.method("permit"))?;
+                    validated_input = Some(signed.parameter.clone());
+                    let message = params::serialize_parameter(
+                        &param_schema,
+                        &signed.parameter,
+                        app.checks(verify_roundtrip),
+                    )?;
+                    let payload = UpdateContractPayload {
+                        amount,
+                        address,
+                        receive_name: schema::receive_name(
+                            CONTRACT_NAME,
+                            config.method("permit"),
+                            Some(&schema),
+                        )?,
+                        message,
+                    };
+                    TransactionResult::StateChanging(TransactionPlan::new(
+                        Payload::Update { payload },
+                        energy_for("permit"),
+                    ))
+                }
             }
         }
+        Action::ChainParams
+        | Action::Version
+        | Action::StateSize { .. }
+        | Action::VerifyModule { .. }
+        | Action::UpgradeHistory { .. }
+        | Action::AccountHistory { .. }
+        | Action::SubmitRaw { .. }
+        | Action::Balances { .. }
+        | Action::Holdings { .. }
+        | Action::Supply { .. }
+        | Action::StateDiff { .. }
+        | Action::Events { .. }
+        | Action::DecodeReturnValue { .. }
+        | Action::EncodeParameter { .. }
+        | Action::ValidateParameters { .. }
+        | Action::ListMethods { .. }
+        | Action::ModuleRef { .. }
+        | Action::EmbedSchema { .. }
+        | Action::Repl => {
+            unreachable!("Handled before loading the keys.")
+        }
     };
+    if let (Some(max_fee), TransactionResult::StateChanging(plan)) = (app.max_fee, &mut tx) {
+        let params = chain::ChainParams::fetch(client).await?;
+        let total = plan
+            .construct(sender, num_sigs, nonce, expiry)
+            .header
+            .energy_amount;
+        if let Payload::DeployModule { .. } = plan.payload {
+            // the energy of a deployment is fixed by the module size
+            let fee = params.energy_cost(total);
+            anyhow::ensure!(
+                fee <= max_fee,
+                "Deploying the module costs {} CCD, more than --max-fee {} CCD.",
+                fee,
+                max_fee
+            );
+        } else {
+            let base = Energy::from(total.energy - plan.energy.energy);
+            plan.energy = params.execution_energy(max_fee, base)?;
+            eprintln!(
+                "Using {} energy for execution, at most {} CCD in fees.",
+                plan.energy, max_fee
+            );
+        }
+    }
     // let mut a;
     match tx {
-        TransactionResult::StateChanging(result) => {
-            let item = BlockItem::AccountTransaction(result);
-            // submit the transaction to the chain
-            let transaction_hash = client.send_block_item(&item).await?;
-            println!(
-                "Transaction {} submitted (nonce = {}).",
-                transaction_hash, nonce,
-            );
-            let (bh, bs) = client.wait_until_finalized(&transaction_hash).await?;
-            println!("Transaction finalized in block {}.", bh);
-
-            match bs.details {
-                BlockItemSummaryDetails::AccountTransaction(ad) => {
-                    match ad.effects {
-                        AccountTransactionEffects::ModuleDeployed { module_ref } => {
-                            println!("module ref is {}", module_ref);
-                        }
-                        AccountTransactionEffects::ContractInitialized { data } => {
-                            println!("Contract address is {}", data.address);
-                        }
-                        AccountTransactionEffects::None {
-                            transaction_type,
-                            reject_reason,
-                        } => {
-                            println!("The Rejection Outcome is {:#?}", reject_reason);
+        TransactionResult::StateChanging(plan) if app.parameter_validate_only => {
+            let Payload::Update { payload } = &plan.payload else {
+                anyhow::bail!("--parameter-validate-only only supports contract updates.");
+            };
+            let schema = module_schema
+                .as_ref()
+                .context("--parameter-validate-only requires with-schema.")?;
+            let decoded = params::DecodedParameter::decode(
+                schema,
+                &payload.receive_name,
+                &payload.message,
+                validated_input.as_ref(),
+            )?;
+            match app.output {
+                OutputFormat::Human | OutputFormat::Csv => decoded.print(&app.printer)?,
+                OutputFormat::Json => app.printer.print_json(&decoded)?,
+            }
+        }
+        TransactionResult::StateChanging(plan) if app.dry_run => {
+            let Payload::Update { payload } = &plan.payload else {
+                anyhow::bail!("--dry-run only supports contract updates.");
+            };
+            match instance::dry_run(client, &app.invoke, sender, payload, plan.energy).await? {
+                InvokeContractResult::Success {
+                    events,
+                    used_energy,
+                    ..
+                } => {
+                    let events =
+                        events::decode_trace(payload.address, event_schema.as_ref(), &events);
+                    match app.output {
+                        OutputFormat::Human | OutputFormat::Csv => {
+                            println!("The update would succeed using {} energy.", used_energy);
+                            println!("Events:");
+                            events::print_events(&events, &app.printer);
                         }
-                        _ => (),
-                    };
+                        OutputFormat::Json => app.printer.print_json(&serde_json::json!({
+                            "outcome": "success",
+                            "usedEnergy": used_energy,
+                            "events": events,
+                        }))?,
+                    }
+                }
+                InvokeContractResult::Failure {
+                    reason,
+                    return_value,
+                    used_energy,
+                } => anyhow::bail!(
+                    "The update would be rejected after {} energy: {}",
+                    used_energy,
+                    reject::describe(
+                        &reason,
+                        return_value.as_ref().map(|rv| &rv.value[..]),
+                        session.submitter.error_schema()
+                    )
+                ),
+            }
+        }
+        TransactionResult::StateChanging(plan) => {
+            // fail before submitting if the account cannot pay for the transaction
+            let params = chain::ChainParams::fetch(client).await?;
+            let energy = plan
+                .construct(sender, num_sigs, nonce, expiry)
+                .header
+                .energy_amount;
+            params.check_energy_limit(energy)?;
+            params.check_balance(chain::available_balance(&acc_info), plan.amount(), energy)?;
+            // count the expiry from now, not from the start of the command, as
+            // the dry-run checks and queries above may have taken a while, but
+            // never past the end of a permit's validity
+            let expiry = match permit_valid_until {
+                Some(valid_until) => std::cmp::min(session.submitter.expiry(), valid_until),
+                None => session.submitter.expiry(),
+            };
+            if app.build_unsigned {
+                let pre = plan.construct(sender, num_sigs, nonce, expiry);
+                let unsigned = submit::UnsignedTransaction::from(&pre);
+                if let Some(out) = &app.out {
+                    std::fs::write(out, &unsigned.body)
+                        .context("Unable to write the unsigned transaction.")?;
                 }
-                BlockItemSummaryDetails::AccountCreation(_) => (),
-                BlockItemSummaryDetails::Update(_) => {
-                    println!("Transaction finalized in block {:?}.", bs.details);
-                    ()
+                match app.output {
+                    OutputFormat::Human | OutputFormat::Csv => {
+                        if app.out.is_none() {
+                            println!("{}", unsigned.body);
+                        }
+                        eprintln!("Hash to sign: {}", unsigned.hash_to_sign);
+                    }
+                    OutputFormat::Json => app.printer.print_json(&unsigned)?,
                 }
+                return Ok(());
+            }
+            let finalized = if let Some(url) = &app.signer_url {
+                let signer = signer::ExternalSigner::new(url);
+                let tx = signer
+                    .sign(plan.construct(sender, num_sigs, nonce, expiry), num_sigs)
+                    .await?;
+                session.submitter.send_and_wait(client, tx).await?
+            } else {
+                let keys = keys.context("--account is required.")?;
+                session
+                    .submitter
+                    .send_plan(client, keys, &plan, nonce, expiry, app.retry_on_reject)
+                    .await?
+            };
+            // a resubmission with --retry-on-reject may have used a fresh nonce
+            session.nonce = finalized.nonce.map(|used| (sender, used.next()));
+            if let (Some(expected), Payload::Update { payload }) = (&intent, &plan.payload) {
+                intent::verify_finalized(
+                    client,
+                    &finalized.hash,
+                    payload.address,
+                    expected,
+                    &app.printer,
+                )
+                .await?;
+            }
+            if let (Some(resolver), Payload::Update { payload }) = (&metadata_check, &plan.payload)
+            {
+                metadata::verify_minted(
+                    client,
+                    resolver,
+                    &finalized.hash,
+                    payload.address,
+                    &app.printer,
+                )
+                .await?;
+            }
+            let effects = if app.dump_effects_json {
+                Some(effects::fetch(client, &finalized.hash, event_schema.as_ref()).await?)
+            } else {
+                None
             };
+            match (app.output, effects) {
+                (OutputFormat::Json, Some(effects)) => {
+                    let mut json = serde_json::to_value(&finalized)?;
+                    json["effects"] = effects;
+                    app.printer.print_json(&json)?;
+                }
+                (OutputFormat::Json, None) => app.printer.print_json(&finalized)?,
+                (OutputFormat::Human | OutputFormat::Csv, Some(effects)) => {
+                    app.printer.print_json(&effects)?
+                }
+                (OutputFormat::Human | OutputFormat::Csv, None) => (),
+            }
         }
         TransactionResult::None => {
-            println!("No state changes, already printed, gracefully exiting.");
+            app.printer
+                .progress("No state changes, already printed, gracefully exiting.");
         }
     }
 
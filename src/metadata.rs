@@ -0,0 +1,170 @@
+//! Fetching the metadata JSON of tokens, resolving `ipfs://` URLs through a
+//! list of gateways.
+use crate::output::Printer;
+use anyhow::Context;
+use concordium_rust_sdk::{
+    cis2,
+    smart_contracts::common::{Cursor, Deserial},
+    types::{hashes::TransactionHash, ContractAddress},
+    v2,
+};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+/// Gateways tried in turn for `ipfs://` URLs without `--ipfs-gateways`.
+pub const DEFAULT_GATEWAYS: &str = "https://ipfs.io/ipfs/,https://dweb.link/ipfs/";
+
+/// Fetches metadata over HTTP, trying the gateways in turn for IPFS.
+pub struct Resolver {
+    client: reqwest::Client,
+    gateways: Vec<String>,
+    verbose: bool,
+}
+
+impl Resolver {
+    /// A resolver giving up on each request, to a gateway or a plain URL,
+    /// after `timeout`.
+    pub fn new(gateways: &[String], timeout: Duration, verbose: bool) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            !gateways.is_empty(),
+            "At least one IPFS gateway is required."
+        );
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("Could not set up the HTTP client.")?;
+        Ok(Self {
+            client,
+            gateways: gateways
+                .iter()
+                .map(|g| format!("{}/", g.trim_end_matches('/')))
+                .collect(),
+            verbose,
+        })
+    }
+
+    /// The URLs to fetch `url` from, in order: one per gateway for an IPFS
+    /// URL, else the URL itself.
+    fn candidates(&self, url: &str) -> Vec<String> {
+        match url.strip_prefix("ipfs://") {
+            Some(path) => {
+                let path = path.trim_start_matches("ipfs/");
+                self.gateways
+                    .iter()
+                    .map(|gateway| format!("{}{}", gateway, path))
+                    .collect()
+            }
+            None => vec![url.to_string()],
+        }
+    }
+
+    /// Fetch the metadata JSON at `url`, falling back to the next gateway if
+    /// one fails or times out.
+    pub async fn fetch(&self, url: &str) -> anyhow::Result<Value> {
+        let bytes = self.fetch_bytes(url).await?;
+        serde_json::from_slice(&bytes).context("The metadata is not valid JSON.")
+    }
+
+    /// Fetch the content at `url` as it is, which its hash is computed over.
+    pub async fn fetch_bytes(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        let mut failures = Vec::new();
+        for candidate in self.candidates(url) {
+            match self.get(&candidate).await {
+                Ok(value) => {
+                    if self.verbose && url.starts_with("ipfs://") {
+                        eprintln!("Fetched {} from {}.", url, candidate);
+                    }
+                    return Ok(value);
+                }
+                Err(e) => failures.push(format!("{}: {:#}", candidate, e)),
+            }
+        }
+        anyhow::bail!(
+            "Could not fetch the metadata at {}:\n  {}",
+            url,
+            failures.join("\n  ")
+        )
+    }
+
+    async fn get(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        let response = self.client.get(url).send().await?.error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+/// Check the metadata of every token `contract` logged a `TokenMetadata`
+/// event with a hash for in the finalized transaction: fetch it and compare
+/// its SHA-256 with the recorded hash. Fail if any differs or cannot be
+/// fetched.
+pub async fn verify_minted(
+    client: &mut v2::Client,
+    resolver: &Resolver,
+    hash: &TransactionHash,
+    contract: ContractAddress,
+    printer: &Printer,
+) -> anyhow::Result<()> {
+    let status = client.get_block_item_status(hash).await?;
+    let (_, summary) = status
+        .is_finalized()
+        .context("The transaction is not finalized.")?;
+    let recorded: Vec<(cis2::TokenId, cis2::MetadataUrl)> = summary
+        .contract_update_logs()
+        .into_iter()
+        .flatten()
+        .filter(|(address, _)| *address == contract)
+        .flat_map(|(_, events)| events.iter())
+        .filter_map(
+            |event| match cis2::Event::deserial(&mut Cursor::new(event.as_ref())) {
+                Ok(cis2::Event::TokenMetadata {
+                    token_id,
+                    metadata_url,
+                }) => Some((token_id, metadata_url)),
+                _ => None,
+            },
+        )
+        .collect();
+    let mut problems = Vec::new();
+    let mut checked = 0;
+    for (token_id, metadata_url) in &recorded {
+        let Some(expected) = metadata_url.hash() else {
+            continue;
+        };
+        checked += 1;
+        let actual = match resolver.fetch_bytes(metadata_url.url()).await {
+            Ok(bytes) => hex::encode(Sha256::digest(bytes)),
+            Err(e) => {
+                problems.push(format!("token {}: {:#}", token_id, e));
+                continue;
+            }
+        };
+        if actual != expected.to_string() {
+            problems.push(format!(
+                "token {}: the metadata at {} has hash {}, but {} is recorded",
+                token_id,
+                metadata_url.url(),
+                actual,
+                expected
+            ));
+        }
+    }
+    anyhow::ensure!(
+        problems.is_empty(),
+        "The metadata of {} of {} token(s) does not match its recorded hash:\n  {}",
+        problems.len(),
+        checked,
+        problems.join("\n  ")
+    );
+    if checked == 0 {
+        eprintln!(
+            "Warning: contract {} recorded no metadata hash, nothing to check.",
+            contract
+        );
+    } else {
+        printer.progress(format_args!(
+            "The metadata of all {} token(s) matches its recorded hash.",
+            checked
+        ));
+    }
+    Ok(())
+}
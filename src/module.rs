@@ -0,0 +1,391 @@
+//! Reading smart contract modules from disk.
+use crate::{
+    chain::ChainParams,
+    output::{Printer, Style},
+    submit::TransactionPlan,
+};
+use anyhow::Context;
+use concordium_rust_sdk::{
+    common::{self, types::TransactionTime},
+    id::types::AccountAddress,
+    smart_contracts::common::{from_bytes, schema::VersionedModuleSchema, to_bytes, Amount},
+    types::{
+        smart_contracts::{ModuleReference, ModuleSource, WasmModule},
+        ContractAddress, Energy, Nonce,
+    },
+    v2,
+};
+use serde::Serialize;
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// Read a build artifact, decompressing it if it is gzip compressed.
+pub fn read_artifact(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let contents =
+        std::fs::read(path).with_context(|| format!("Could not read {}.", path.display()))?;
+    let is_gzip = path.extension().is_some_and(|e| e == "gz");
+    gunzip(contents, is_gzip).with_context(|| format!("Could not decompress {}.", path.display()))
+}
+
+/// Decompress `contents` if they start with the gzip magic bytes or
+/// `is_gzip` is set, else return them as they are.
+pub fn gunzip(contents: Vec<u8>, is_gzip: bool) -> anyhow::Result<Vec<u8>> {
+    if !is_gzip && !contents.starts_with(&[0x1f, 0x8b]) {
+        return Ok(contents);
+    }
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(&contents[..]).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Read and parse a versioned Wasm module, e.g., a `.wasm.v1` file, which may
+/// be gzip compressed.
+pub fn read_module(path: &Path) -> anyhow::Result<WasmModule> {
+    let contents = read_artifact(path)
+        .with_context(|| format!("Could not read contract module {}.", path.display()))?;
+    let module = common::Deserial::deserial(&mut std::io::Cursor::new(contents))
+        .with_context(|| format!("Could not parse contract module {}.", path.display()))?;
+    Ok(module)
+}
+
+/// Name of the custom section holding a module's versioned schema.
+const SCHEMA_SECTION: &str = "concordium-schema";
+
+/// Names of the custom sections in which older tooling embedded schemas.
+const LEGACY_SCHEMA_SECTIONS: [&str; 2] = ["concordium-schema-v1", "concordium-schema-v2"];
+
+/// Length of the Wasm magic number and version that precede the sections.
+const WASM_HEADER_LEN: usize = 8;
+
+/// Append the schema to the module as a custom section. Fails if the module
+/// already embeds a schema, and checks that the result parses and yields the
+/// schema again.
+pub fn embed_schema(
+    module: &WasmModule,
+    schema: &VersionedModuleSchema,
+) -> anyhow::Result<WasmModule> {
+    let source: &[u8] = module.source.as_ref();
+    let existing = custom_sections(source)?;
+    anyhow::ensure!(
+        !existing
+            .iter()
+            .any(|(name, _)| name == SCHEMA_SECTION
+                || LEGACY_SCHEMA_SECTIONS.contains(&name.as_str())),
+        "The module already embeds a schema."
+    );
+    let schema_bytes = to_bytes(schema);
+    let mut contents = Vec::new();
+    write_leb128(&mut contents, SCHEMA_SECTION.len() as u32);
+    contents.extend_from_slice(SCHEMA_SECTION.as_bytes());
+    contents.extend_from_slice(&schema_bytes);
+    let mut bytes = source.to_vec();
+    bytes.push(0); // the id of custom sections
+    write_leb128(&mut bytes, contents.len() as u32);
+    bytes.extend_from_slice(&contents);
+    let embedded = WasmModule {
+        version: module.version,
+        source: ModuleSource::from(bytes),
+    };
+    let reparsed: WasmModule =
+        common::Deserial::deserial(&mut std::io::Cursor::new(common::to_bytes(&embedded)))
+            .context("The module with the schema does not parse.")?;
+    let sections = custom_sections(reparsed.source.as_ref())?;
+    let (_, section) = sections
+        .iter()
+        .find(|(name, _)| name == SCHEMA_SECTION)
+        .context("The schema section is missing from the new module.")?;
+    from_bytes::<VersionedModuleSchema>(section).context("The embedded schema does not parse.")?;
+    Ok(reparsed)
+}
+
+/// The names and contents of the custom sections of Wasm module source.
+fn custom_sections(source: &[u8]) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    anyhow::ensure!(
+        source.len() >= WASM_HEADER_LEN && source.starts_with(b"\0asm"),
+        "The module source is not Wasm."
+    );
+    let mut sections = Vec::new();
+    let mut pos = WASM_HEADER_LEN;
+    while pos < source.len() {
+        let id = source[pos];
+        pos += 1;
+        let size = read_leb128(source, &mut pos)? as usize;
+        let end = pos
+            .checked_add(size)
+            .filter(|end| *end <= source.len())
+            .context("A section of the module is truncated.")?;
+        if id == 0 {
+            let mut name_pos = pos;
+            let name_len = read_leb128(&source[..end], &mut name_pos)? as usize;
+            let name = source
+                .get(name_pos..name_pos + name_len)
+                .filter(|_| name_pos + name_len <= end)
+                .context("The name of a custom section is truncated.")?;
+            let name = String::from_utf8_lossy(name).into_owned();
+            sections.push((name, source[name_pos + name_len..end].to_vec()));
+        }
+        pos = end;
+    }
+    Ok(sections)
+}
+
+fn read_leb128(bytes: &[u8], pos: &mut usize) -> anyhow::Result<u32> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*pos).context("The module is truncated.")?;
+        *pos += 1;
+        value |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    anyhow::bail!("Invalid LEB128 number in the module.")
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// All `.wasm.v1` modules directly inside `dir`, also gzip compressed ones,
+/// sorted by file name.
+pub fn modules_in_dir(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut modules = Vec::new();
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("Could not read module directory {}.", dir.display()))?
+    {
+        let path = entry?.path();
+        let is_module = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.ends_with(".wasm.v1") || n.ends_with(".wasm.v1.gz"));
+        if is_module && path.is_file() {
+            modules.push(path);
+        }
+    }
+    modules.sort();
+    Ok(modules)
+}
+
+/// The expected cost of deploying a module.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployEstimate {
+    pub module: PathBuf,
+    pub module_ref: ModuleReference,
+    pub size: u64,
+    /// The energy of the deployment, which grows with the module size, and
+    /// of the transaction carrying it.
+    pub energy: Energy,
+    /// The energy at the current exchange rate.
+    pub cost: Amount,
+}
+
+impl DeployEstimate {
+    /// The cost of deploying the module at `path` with a transaction signed
+    /// with `num_sigs` signatures.
+    pub fn new(path: &Path, module: WasmModule, num_sigs: u32, params: &ChainParams) -> Self {
+        let module_ref = module.get_module_ref();
+        let size = module.source.size();
+        // Sender, nonce and expiry do not affect the energy.
+        let energy = TransactionPlan::deploy_module(module)
+            .construct(
+                AccountAddress([0; 32]),
+                num_sigs,
+                Nonce::from(1),
+                TransactionTime::from_seconds(0),
+            )
+            .header
+            .energy_amount;
+        Self {
+            module: path.to_path_buf(),
+            module_ref,
+            size,
+            energy,
+            cost: params.energy_cost(energy),
+        }
+    }
+
+    pub fn print(&self, printer: &Printer) {
+        printer.field("module", self.module.display());
+        printer.field("module ref", self.module_ref);
+        printer.field("size", format!("{} bytes", self.size));
+        printer.field("energy", self.energy);
+        printer.field("cost", format!("{} CCD", self.cost));
+    }
+}
+
+/// How a local module compares with the module a contract instance runs.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Verification {
+    pub contract: ContractAddress,
+    /// The reference computed from the deployed module source.
+    pub deployed: ModuleReference,
+    pub local: ModuleReference,
+    pub matches: bool,
+}
+
+impl Verification {
+    /// Fetch the source of the module the contract runs and compare its
+    /// reference with that of the local module. The reference is computed
+    /// from the fetched source rather than taken from the instance.
+    pub async fn fetch(
+        client: &mut v2::Client,
+        contract: ContractAddress,
+        local: &WasmModule,
+    ) -> anyhow::Result<Self> {
+        let module_ref = client
+            .get_instance_info(contract, v2::BlockIdentifier::LastFinal)
+            .await?
+            .response
+            .source_module();
+        let source = client
+            .get_module_source(&module_ref, v2::BlockIdentifier::LastFinal)
+            .await?
+            .response;
+        let deployed = source.get_module_ref();
+        let local = local.get_module_ref();
+        Ok(Self {
+            contract,
+            deployed,
+            local,
+            matches: deployed == local,
+        })
+    }
+
+    pub fn print(&self, printer: &Printer) {
+        printer.field("contract", self.contract);
+        printer.field("deployed", self.deployed);
+        printer.field("local", self.local);
+        if self.matches {
+            println!(
+                "{}",
+                printer.paint("The contract runs the local module.", Style::Success)
+            );
+        } else {
+            println!(
+                "{}",
+                printer.paint(
+                    "The contract does not run the local module.",
+                    Style::Failure
+                )
+            );
+        }
+    }
+}
+
+/// Parse a module reference given in hex, or read it from a file such as the
+/// `.hash` file written by the build tooling.
+pub fn parse_module_ref(arg: &str) -> anyhow::Result<ModuleReference> {
+    let path = Path::new(arg);
+    let (text, source) = if path.is_file() {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read module reference file {}.", arg))?;
+        (text, format!("in {}", path.display()))
+    } else {
+        (arg.to_string(), "given".to_string())
+    };
+    let text = text.trim();
+    text.parse().map_err(|_| {
+        anyhow::anyhow!(
+            "The module reference {} is not 64 hex digits: {}",
+            source,
+            text
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use concordium_rust_sdk::{
+        smart_contracts::common::schema::ModuleV3, types::smart_contracts::WasmVersion,
+    };
+
+    const HEADER: &[u8] = b"\0asm\x01\0\0\0";
+
+    fn module(source: &[u8]) -> WasmModule {
+        WasmModule {
+            version: WasmVersion::V1,
+            source: ModuleSource::from(source.to_vec()),
+        }
+    }
+
+    fn schema() -> VersionedModuleSchema {
+        VersionedModuleSchema::V3(ModuleV3 {
+            contracts: Default::default(),
+        })
+    }
+
+    #[test]
+    fn leb128_round_trip() {
+        for value in [0, 1, 127, 128, 300, 16_384, u32::MAX] {
+            let mut bytes = Vec::new();
+            write_leb128(&mut bytes, value);
+            let mut pos = 0;
+            assert_eq!(read_leb128(&bytes, &mut pos).unwrap(), value);
+            assert_eq!(pos, bytes.len());
+        }
+        let mut bytes = Vec::new();
+        write_leb128(&mut bytes, 300);
+        assert_eq!(bytes, [0xac, 0x02]);
+    }
+
+    #[test]
+    fn read_leb128_rejects_invalid_numbers() {
+        assert!(read_leb128(&[0x80], &mut 0).is_err());
+        assert!(read_leb128(&[0xff; 6], &mut 0).is_err());
+    }
+
+    #[test]
+    fn custom_sections_skip_other_sections() {
+        let mut source = HEADER.to_vec();
+        source.extend_from_slice(&[1, 2, 0xaa, 0xbb]);
+        source.extend_from_slice(&[0, 4, 2, b'h', b'i', 7]);
+        assert_eq!(
+            custom_sections(&source).unwrap(),
+            [("hi".to_string(), vec![7])]
+        );
+    }
+
+    #[test]
+    fn custom_sections_reject_invalid_source() {
+        assert!(custom_sections(b"\0wasm\x01\0\0\0").is_err());
+        let mut source = HEADER.to_vec();
+        source.extend_from_slice(&[1, 3, 0xaa]);
+        assert!(custom_sections(&source).is_err());
+        let mut source = HEADER.to_vec();
+        source.extend_from_slice(&[0, 1, 5]);
+        assert!(custom_sections(&source).is_err());
+    }
+
+    #[test]
+    fn embed_schema_appends_a_section() {
+        let embedded = embed_schema(&module(HEADER), &schema()).unwrap();
+        assert_eq!(
+            custom_sections(embedded.source.as_ref()).unwrap(),
+            [(SCHEMA_SECTION.to_string(), to_bytes(&schema()))]
+        );
+    }
+
+    #[test]
+    fn embed_schema_refuses_a_second_schema() {
+        let embedded = embed_schema(&module(HEADER), &schema()).unwrap();
+        assert!(embed_schema(&embedded, &schema()).is_err());
+        let mut legacy = HEADER.to_vec();
+        let name = LEGACY_SCHEMA_SECTIONS[0].as_bytes();
+        legacy.extend_from_slice(&[0, name.len() as u8 + 1, name.len() as u8]);
+        legacy.extend_from_slice(name);
+        assert!(embed_schema(&module(&legacy), &schema()).is_err());
+    }
+}
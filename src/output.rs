@@ -0,0 +1,263 @@
+//! Formatting of command results.
+use anyhow::Context;
+use concordium_rust_sdk::{
+    endpoints::{QueryError, RPCError},
+    smart_contracts::common::{schema::Type, Cursor},
+    v2,
+};
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::path::Path;
+use strum_macros::EnumString;
+
+/// Width of the labels of summary lines, so that their values line up.
+const LABEL_WIDTH: usize = 14;
+
+/// Styles of human-readable output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Success,
+    Failure,
+    Warning,
+    Label,
+    Dim,
+}
+
+/// How results are written to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum OutputFormat {
+    Human,
+    Json,
+    /// Tables of batch results, holdings, entrypoints, parameter checks,
+    /// events and state differences. Other results are printed as with
+    /// `Human`.
+    Csv,
+}
+
+/// How results are printed, from the output flags.
+#[derive(Debug, Clone, Copy)]
+pub struct Printer {
+    pub format: OutputFormat,
+    /// Whether JSON is printed on a single line instead of indented.
+    compact: bool,
+    /// Whether human-readable results are colored.
+    color: bool,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::Human,
+            compact: false,
+            color: false,
+        }
+    }
+}
+
+impl Printer {
+    /// Print results in `format`. Human-readable results are colored unless
+    /// `no_color` is set, `NO_COLOR` is set or stdout is not a terminal.
+    pub fn new(format: OutputFormat, compact: bool, no_color: bool) -> Self {
+        let color = !no_color
+            && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+            && std::io::stdout().is_terminal();
+        Self {
+            format,
+            compact,
+            color,
+        }
+    }
+
+    /// `text` in the style if colors are enabled, else unchanged.
+    pub fn paint(&self, text: &str, style: Style) -> String {
+        if !self.color {
+            return text.to_string();
+        }
+        let code = match style {
+            Style::Success => "32",
+            Style::Failure => "31",
+            Style::Warning => "33",
+            Style::Label => "1",
+            Style::Dim => "2",
+        };
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+
+    /// An outcome such as `success` or `rejected: ...`, colored by what it
+    /// says.
+    pub fn paint_outcome(&self, outcome: &str) -> String {
+        let style = if outcome == "success" {
+            Style::Success
+        } else if outcome.starts_with("unknown") {
+            Style::Warning
+        } else {
+            Style::Failure
+        };
+        self.paint(outcome, style)
+    }
+
+    /// Print a summary line with the value aligned after its label.
+    pub fn field(&self, label: &str, value: impl std::fmt::Display) {
+        println!(
+            "{} {}",
+            self.paint(
+                &format!("{:<width$}", label, width = LABEL_WIDTH),
+                Style::Label
+            ),
+            value
+        );
+    }
+
+    /// Print a line reporting progress, such as a submitted transaction. It
+    /// goes to stdout with human-readable output and to stderr otherwise, so
+    /// that stdout holds only the JSON or CSV result.
+    pub fn progress(&self, line: impl std::fmt::Display) {
+        match self.format {
+            OutputFormat::Human => println!("{}", line),
+            OutputFormat::Json | OutputFormat::Csv => eprintln!("{}", line),
+        }
+    }
+
+    /// Print a progress line with the value aligned after its label, on the
+    /// same stream as `progress`.
+    pub fn progress_field(&self, label: &str, value: impl std::fmt::Display) {
+        self.progress(format_args!(
+            "{} {}",
+            self.paint(
+                &format!("{:<width$}", label, width = LABEL_WIDTH),
+                Style::Label
+            ),
+            value
+        ));
+    }
+
+    /// Print `value` as JSON on stdout.
+    pub fn print_json<T: Serialize>(&self, value: &T) -> anyhow::Result<()> {
+        println!("{}", self.to_json_string(value)?);
+        Ok(())
+    }
+
+    /// Format `value` as pretty or compact JSON, depending on the selection.
+    pub fn to_json_string<T: Serialize>(self, value: &T) -> anyhow::Result<String> {
+        let json = if self.compact {
+            serde_json::to_string(value)?
+        } else {
+            serde_json::to_string_pretty(value)?
+        };
+        Ok(json)
+    }
+
+    /// Print the error as `{"error": {"kind", "message", "context"}}` on
+    /// stderr.
+    pub fn print_error_json(&self, error: &anyhow::Error) {
+        let json = serde_json::json!({
+            "error": ErrorJson {
+                kind: ErrorKind::of(error),
+                message: error.to_string(),
+                context: error.chain().skip(1).map(ToString::to_string).collect(),
+            }
+        });
+        match self.to_json_string(&json) {
+            Ok(json) => eprintln!("{}", json),
+            Err(_) => eprintln!("Error: {:#}", error),
+        }
+    }
+
+    /// Decode a return value with its schema and print it as JSON.
+    pub fn print_return_value(&self, rv_schema: &Type, bytes: &[u8]) -> anyhow::Result<()> {
+        let value = rv_schema.to_json(&mut Cursor::new(bytes))?;
+        self.print_json(&value)
+    }
+}
+
+/// Print a table as CSV on stdout, quoting fields where needed.
+pub fn print_csv(
+    header: &[&str],
+    rows: impl IntoIterator<Item = Vec<String>>,
+) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    writer.write_record(header)?;
+    for row in rows {
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// What kind of failure an error is, for callers that react differently to
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    /// The node could not be reached or failed a query or submission.
+    Node,
+    /// A file could not be read or written.
+    Io,
+    /// An input such as a parameter, key or CSV file could not be parsed.
+    Parse,
+    /// An HTTP service such as an external signer failed.
+    Http,
+    Timeout,
+    Other,
+}
+
+impl ErrorKind {
+    /// The kind of the outermost cause that has one, e.g. `node` for a failed
+    /// connection rather than the I/O error beneath it.
+    pub fn of(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(|cause| {
+                if cause.is::<QueryError>() || cause.is::<RPCError>() || cause.is::<v2::Error>() {
+                    Some(ErrorKind::Node)
+                } else if cause.is::<std::io::Error>() {
+                    Some(ErrorKind::Io)
+                } else if cause.is::<serde_json::Error>()
+                    || cause.is::<serde_yaml::Error>()
+                    || cause.is::<toml::de::Error>()
+                    || cause.is::<csv::Error>()
+                    || cause.is::<hex::FromHexError>()
+                {
+                    Some(ErrorKind::Parse)
+                } else if cause.is::<reqwest::Error>() {
+                    Some(ErrorKind::Http)
+                } else if cause.is::<tokio::time::error::Elapsed>() {
+                    Some(ErrorKind::Timeout)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(ErrorKind::Other)
+    }
+}
+
+/// A failure as JSON.
+#[derive(Serialize)]
+struct ErrorJson {
+    kind: ErrorKind,
+    /// The outermost error, what the command failed to do.
+    message: String,
+    /// The causes, from the outermost to the root cause.
+    context: Vec<String>,
+}
+
+/// Bytes given either as hex on the command line or in a file, which may hold
+/// hex text or the raw bytes.
+pub fn read_hex_or_binary(hex: Option<&str>, file: Option<&Path>) -> anyhow::Result<Vec<u8>> {
+    match (hex, file) {
+        (Some(hex), _) => hex::decode(hex.trim()).context("The value is not valid hex."),
+        (None, Some(file)) => {
+            let bytes = std::fs::read(file)
+                .with_context(|| format!("Unable to read {}.", file.display()))?;
+            let text = std::str::from_utf8(&bytes)
+                .map(str::trim)
+                .unwrap_or_default();
+            match hex::decode(text) {
+                Ok(decoded) if !text.is_empty() => Ok(decoded),
+                _ => Ok(bytes),
+            }
+        }
+        (None, None) => anyhow::bail!("Either --hex or --file is required."),
+    }
+}
@@ -0,0 +1,573 @@
+//! Building contract parameters from JSON using a schema.
+use anyhow::Context;
+use base64::{engine::general_purpose, Engine as _};
+use concordium_rust_sdk::{
+    smart_contracts::common::{
+        constants::MAX_PARAMETER_LEN,
+        schema::{Fields, Type, VersionedModuleSchema},
+        Cursor,
+    },
+    types::smart_contracts::{OwnedParameter, OwnedReceiveName},
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use strum_macros::EnumString;
+
+/// The format of parameter files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum ParameterFormat {
+    /// YAML for files ending in `.yaml` or `.yml`, JSON otherwise.
+    Auto,
+    Json,
+    Yaml,
+}
+
+impl ParameterFormat {
+    fn is_yaml(self, path: &Path) -> bool {
+        match self {
+            ParameterFormat::Json => false,
+            ParameterFormat::Yaml => true,
+            ParameterFormat::Auto => path.extension().is_some_and(|e| e == "yaml" || e == "yml"),
+        }
+    }
+}
+
+/// Checks made while serializing a parameter, from the flags.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Checks {
+    /// Reject parameters with keys the schema does not know.
+    pub strict_json: bool,
+    /// Decode the serialized bytes back to JSON and warn about every
+    /// structural difference to the input.
+    pub verify_roundtrip: bool,
+}
+
+/// Read a parameter from a file, in JSON or, for hand-written parameters, in
+/// YAML, which is converted to the same JSON value.
+pub fn read_json(path: &Path, format: ParameterFormat) -> anyhow::Result<Value> {
+    let bytes = std::fs::read(path).context("Unable to read parameter file.")?;
+    if format.is_yaml(path) {
+        return serde_yaml::from_slice(&bytes).context("Unable to parse parameter YAML.");
+    }
+    serde_json::from_slice(&bytes).context("Unable to parse parameter JSON.")
+}
+
+/// The given file, or all `.json`, `.yaml` and `.yml` files directly inside
+/// the given directory sorted by file name.
+pub fn json_files(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)
+        .with_context(|| format!("Could not read parameter directory {}.", path.display()))?
+    {
+        let file = entry?.path();
+        let is_parameter = file
+            .extension()
+            .is_some_and(|e| e == "json" || e == "yaml" || e == "yml");
+        if file.is_file() && is_parameter {
+            files.push(file);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Result of validating one parameter file.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Validation {
+    pub file: PathBuf,
+    /// Size of the serialized parameter, if it is valid.
+    pub size: Option<usize>,
+    pub error: Option<String>,
+}
+
+/// Serialize every file with the schema and report which ones are valid.
+pub fn validate_files(
+    param_schema: &Type,
+    files: &[PathBuf],
+    format: ParameterFormat,
+    checks: Checks,
+) -> Vec<Validation> {
+    files
+        .iter()
+        .map(|file| {
+            let result = read_json(file, format)
+                .and_then(|parameter| serialize_parameter(param_schema, &parameter, checks));
+            match result {
+                Ok(bytes) => Validation {
+                    file: file.clone(),
+                    size: Some(bytes.as_ref().len()),
+                    error: None,
+                },
+                Err(e) => Validation {
+                    file: file.clone(),
+                    size: None,
+                    error: Some(format!("{:#}", e)),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Replace every occurrence of the placeholders in the strings and object
+/// keys of `template`.
+pub fn substitute(template: &Value, vars: &[(&str, &str)]) -> Value {
+    match template {
+        Value::String(s) => Value::String(substitute_str(s, vars)),
+        Value::Array(items) => Value::Array(items.iter().map(|v| substitute(v, vars)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (substitute_str(k, vars), substitute(v, vars)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+pub fn substitute_str(s: &str, vars: &[(&str, &str)]) -> String {
+    vars.iter().fold(s.to_string(), |s, (placeholder, value)| {
+        s.replace(placeholder, value)
+    })
+}
+
+/// Serialize the JSON `parameter` using the `param_schema` of an entrypoint.
+///
+/// With `checks.verify_roundtrip` the serialized bytes are decoded back to
+/// JSON with the same schema and every structural difference to the input is
+/// printed as a warning.
+pub fn serialize_parameter(
+    param_schema: &Type,
+    parameter: &Value,
+    checks: Checks,
+) -> anyhow::Result<OwnedParameter> {
+    if checks.strict_json {
+        check_known_keys(param_schema, parameter)?;
+    }
+    let serialized_parameter = param_schema.serial_value(parameter)?;
+    if checks.verify_roundtrip {
+        let decoded = param_schema.to_json(&mut Cursor::new(&serialized_parameter[..]))?;
+        let discrepancies = json_discrepancies(parameter, &decoded);
+        if discrepancies.is_empty() {
+            eprintln!("Round-trip verification passed.");
+        }
+        for discrepancy in discrepancies {
+            eprintln!("Warning: round-trip mismatch {}", discrepancy);
+        }
+    }
+    Ok(OwnedParameter::try_from(serialized_parameter)?)
+}
+
+/// An already serialized parameter in base64, given directly or as the path
+/// of a file holding it.
+pub fn read_base64(value: &str) -> anyhow::Result<OwnedParameter> {
+    let path = Path::new(value);
+    let text = if path.is_file() {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}.", path.display()))?
+    } else {
+        value.to_string()
+    };
+    let bytes = general_purpose::STANDARD
+        .decode(text.trim())
+        .context("The parameter is not valid base64.")?;
+    anyhow::ensure!(
+        bytes.len() <= MAX_PARAMETER_LEN,
+        "The parameter has {} bytes, more than the limit of {}.",
+        bytes.len(),
+        MAX_PARAMETER_LEN
+    );
+    Ok(OwnedParameter::try_from(bytes)?)
+}
+
+/// Parse one `--parameter-item`, an element of a list parameter in JSON.
+pub fn parse_item(s: &str) -> anyhow::Result<Value> {
+    serde_json::from_str(s).with_context(|| format!("The parameter item {} is not valid JSON.", s))
+}
+
+/// Assemble the items into the parameter of `method`: the list itself if the
+/// parameter is a list, or a struct whose only field is the list.
+pub fn wrap_items(
+    method: &str,
+    param_schema: Option<&Type>,
+    items: Vec<Value>,
+) -> anyhow::Result<Value> {
+    let list = Value::Array(items);
+    match param_schema {
+        Some(Type::List(..) | Type::Set(..)) => Ok(list),
+        Some(Type::Struct(Fields::Named(fields)))
+            if fields.len() == 1 && matches!(fields[0].1, Type::List(..) | Type::Set(..)) =>
+        {
+            Ok(serde_json::json!({ fields[0].0.clone(): list }))
+        }
+        Some(Type::Struct(Fields::Unnamed(fields)))
+            if fields.len() == 1 && matches!(fields[0], Type::List(..) | Type::Set(..)) =>
+        {
+            Ok(Value::Array(vec![list]))
+        }
+        _ => anyhow::bail!(
+            "The parameter of {} is not a list, so it cannot be given with --parameter-item.",
+            method
+        ),
+    }
+}
+
+/// Serialize the parameter of `method`, which takes no parameter if it has no
+/// `param_schema`. Supplying a parameter for such a method is an error, as is
+/// omitting one for a method that has a parameter.
+pub fn serialize_optional(
+    method: &str,
+    param_schema: Option<&Type>,
+    parameter: Option<&Value>,
+    checks: Checks,
+) -> anyhow::Result<OwnedParameter> {
+    match (param_schema, parameter) {
+        (Some(param_schema), Some(parameter)) => {
+            serialize_parameter(param_schema, parameter, checks)
+        }
+        (Some(_), None) => anyhow::bail!("{} requires a parameter (--parameter).", method),
+        (None, Some(_)) => anyhow::bail!(
+            "{} takes no parameter according to the schema, but one was given.",
+            method
+        ),
+        (None, None) => Ok(OwnedParameter::empty()),
+    }
+}
+
+/// Fail if the JSON contains a struct field or enum variant that the schema
+/// does not describe, listing every such key with its path.
+pub fn check_known_keys(param_schema: &Type, parameter: &Value) -> anyhow::Result<()> {
+    let mut unknown = Vec::new();
+    unknown_keys("$", param_schema, parameter, &mut unknown);
+    anyhow::ensure!(
+        unknown.is_empty(),
+        "The parameter has keys the schema does not know: {}",
+        unknown.join(", ")
+    );
+    Ok(())
+}
+
+fn unknown_keys(path: &str, ty: &Type, value: &Value, out: &mut Vec<String>) {
+    match (ty, value) {
+        (Type::Struct(fields), value) => unknown_field_keys(path, fields, value, out),
+        (Type::Enum(variants), Value::Object(obj)) => {
+            for (key, fields_value) in obj {
+                match variants.iter().find(|(name, _)| name == key) {
+                    Some((_, fields)) => {
+                        unknown_field_keys(&format!("{}.{}", path, key), fields, fields_value, out)
+                    }
+                    None => out.push(format!("{}.{}", path, key)),
+                }
+            }
+        }
+        (Type::TaggedEnum(variants), Value::Object(obj)) => {
+            for (key, fields_value) in obj {
+                match variants.values().find(|(name, _)| name == key) {
+                    Some((_, fields)) => {
+                        unknown_field_keys(&format!("{}.{}", path, key), fields, fields_value, out)
+                    }
+                    None => out.push(format!("{}.{}", path, key)),
+                }
+            }
+        }
+        (Type::List(_, item) | Type::Set(_, item) | Type::Array(_, item), Value::Array(items)) => {
+            for (i, v) in items.iter().enumerate() {
+                unknown_keys(&format!("{}[{}]", path, i), item, v, out);
+            }
+        }
+        (Type::Map(_, key, val), Value::Array(entries)) => {
+            for (i, entry) in entries.iter().enumerate() {
+                if let Some([k, v]) = entry.as_array().map(Vec::as_slice) {
+                    unknown_keys(&format!("{}[{}][0]", path, i), key, k, out);
+                    unknown_keys(&format!("{}[{}][1]", path, i), val, v, out);
+                }
+            }
+        }
+        (Type::Pair(a, b), Value::Array(items)) if items.len() == 2 => {
+            unknown_keys(&format!("{}[0]", path), a, &items[0], out);
+            unknown_keys(&format!("{}[1]", path), b, &items[1], out);
+        }
+        _ => (),
+    }
+}
+
+fn unknown_field_keys(path: &str, fields: &Fields, value: &Value, out: &mut Vec<String>) {
+    match (fields, value) {
+        (Fields::Named(named), Value::Object(obj)) => {
+            for (key, v) in obj {
+                let field_path = format!("{}.{}", path, key);
+                match named.iter().find(|(name, _)| name == key) {
+                    Some((_, ty)) => unknown_keys(&field_path, ty, v, out),
+                    None => out.push(field_path),
+                }
+            }
+        }
+        (Fields::Unnamed(types), Value::Array(items)) => {
+            for (i, (ty, v)) in types.iter().zip(items).enumerate() {
+                unknown_keys(&format!("{}[{}]", path, i), ty, v, out);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// The part of `value` a selector such as `$.tokens[0].url` picks: field
+/// names separated by dots, each optionally followed by array indices. The
+/// leading `$` may be omitted.
+pub fn select<'a>(value: &'a Value, selector: &str) -> anyhow::Result<&'a Value> {
+    let path = selector.strip_prefix('$').unwrap_or(selector);
+    let mut current = value;
+    let mut rest = path;
+    while !rest.is_empty() {
+        let (step, remaining) = if let Some(index) = rest.strip_prefix('[') {
+            let (index, remaining) = index
+                .split_once(']')
+                .with_context(|| format!("Unclosed [ in the selector {}.", selector))?;
+            let index: usize = index.trim().parse().with_context(|| {
+                format!("Invalid index {} in the selector {}.", index, selector)
+            })?;
+            (current.get(index), remaining)
+        } else {
+            let field = rest.strip_prefix('.').unwrap_or(rest);
+            let end = field.find(['.', '[']).unwrap_or(field.len());
+            anyhow::ensure!(end > 0, "Empty field name in the selector {}.", selector);
+            (current.get(&field[..end]), &field[end..])
+        };
+        let consumed = &path[..path.len() - remaining.len()];
+        current =
+            step.with_context(|| format!("The return value has nothing at ${}.", consumed))?;
+        rest = remaining;
+    }
+    Ok(current)
+}
+
+/// A serialized parameter decoded back to JSON with the schema, showing how
+/// the contract reads the input.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedParameter {
+    pub entrypoint: String,
+    pub size: usize,
+    /// `None` if the entrypoint takes no parameter.
+    pub decoded: Option<Value>,
+    /// Differences between the input and the decoded JSON.
+    pub discrepancies: Vec<String>,
+}
+
+impl DecodedParameter {
+    /// Decode the `message` sent to `receive_name` and compare it with the
+    /// `input` it was serialized from, if known.
+    pub fn decode(
+        schema: &VersionedModuleSchema,
+        receive_name: &OwnedReceiveName,
+        message: &OwnedParameter,
+        input: Option<&Value>,
+    ) -> anyhow::Result<Self> {
+        let name = receive_name.as_receive_name();
+        let entrypoint = name.entrypoint_name().to_string();
+        let param_schema =
+            crate::schema::receive_param_schema(schema, name.contract_name(), &entrypoint)?;
+        let bytes: &[u8] = message.as_ref();
+        let decoded = param_schema
+            .map(|ty| ty.to_json(&mut Cursor::new(bytes)))
+            .transpose()
+            .context("Could not decode the serialized parameter.")?;
+        let discrepancies = match (input, &decoded) {
+            (Some(input), Some(decoded)) => json_discrepancies(input, decoded),
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            entrypoint,
+            size: bytes.len(),
+            decoded,
+            discrepancies,
+        })
+    }
+
+    pub fn print(&self, printer: &crate::output::Printer) -> anyhow::Result<()> {
+        let Some(decoded) = &self.decoded else {
+            println!("{} takes no parameter.", self.entrypoint);
+            return Ok(());
+        };
+        println!(
+            "The parameter of {} is valid ({} bytes) and reads as:",
+            self.entrypoint, self.size
+        );
+        printer.print_json(decoded)?;
+        for discrepancy in &self.discrepancies {
+            eprintln!("Differs from the input: {}", discrepancy);
+        }
+        Ok(())
+    }
+}
+
+/// Structurally compare the JSON the user supplied with the JSON obtained by
+/// decoding the serialized parameter, returning one message per difference.
+pub fn json_discrepancies(original: &Value, decoded: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    compare_values("$", original, decoded, &mut out);
+    out
+}
+
+fn compare_values(path: &str, original: &Value, decoded: &Value, out: &mut Vec<String>) {
+    match (original, decoded) {
+        (Value::Object(o), Value::Object(d)) => {
+            for (key, value) in o {
+                let child = format!("{}.{}", path, key);
+                match d.get(key) {
+                    Some(dv) => compare_values(&child, value, dv, out),
+                    None => out.push(format!("at {}: field was dropped", child)),
+                }
+            }
+            for key in d.keys().filter(|k| !o.contains_key(*k)) {
+                out.push(format!("at {}.{}: field was added", path, key));
+            }
+        }
+        (Value::Array(o), Value::Array(d)) => {
+            if o.len() != d.len() {
+                out.push(format!(
+                    "at {}: array length changed from {} to {}",
+                    path,
+                    o.len(),
+                    d.len()
+                ));
+            }
+            for (i, (ov, dv)) in o.iter().zip(d.iter()).enumerate() {
+                compare_values(&format!("{}[{}]", path, i), ov, dv, out);
+            }
+        }
+        (o, d) if o == d => (),
+        (o, d) if json_kind(o) != json_kind(d) => out.push(format!(
+            "at {}: {} {} was coerced to {} {}",
+            path,
+            json_kind(o),
+            o,
+            json_kind(d),
+            d
+        )),
+        (o, d) => out.push(format!("at {}: value {} was decoded as {}", path, o, d)),
+    }
+}
+
+fn json_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use concordium_rust_sdk::smart_contracts::common::schema::SizeLength;
+    use serde_json::json;
+
+    #[test]
+    fn json_discrepancies_of_equal_values() {
+        let value = json!({ "a": [1, { "b": "c" }] });
+        assert!(json_discrepancies(&value, &value).is_empty());
+    }
+
+    #[test]
+    fn json_discrepancies_lists_every_difference() {
+        let original = json!({ "dropped": 1, "n": "1", "v": 2, "list": [1, 2] });
+        let decoded = json!({ "added": 1, "n": 1, "v": 3, "list": [1] });
+        assert_eq!(
+            json_discrepancies(&original, &decoded),
+            [
+                "at $.dropped: field was dropped",
+                "at $.list: array length changed from 2 to 1",
+                "at $.n: string \"1\" was coerced to number 1",
+                "at $.v: value 2 was decoded as 3",
+                "at $.added: field was added",
+            ]
+        );
+    }
+
+    fn mint_schema() -> Type {
+        let token = Type::Struct(Fields::Named(vec![
+            ("id".into(), Type::U8),
+            ("url".into(), Type::String(SizeLength::U8)),
+        ]));
+        Type::Struct(Fields::Named(vec![
+            (
+                "owner".into(),
+                Type::Enum(vec![("Account".into(), Fields::Unnamed(vec![Type::U8]))]),
+            ),
+            ("tokens".into(), Type::List(SizeLength::U8, Box::new(token))),
+        ]))
+    }
+
+    #[test]
+    fn check_known_keys_accepts_known_keys() {
+        let parameter = json!({ "owner": { "Account": [1] }, "tokens": [{ "id": 1, "url": "a" }] });
+        assert!(check_known_keys(&mint_schema(), &parameter).is_ok());
+    }
+
+    #[test]
+    fn check_known_keys_lists_every_unknown_key() {
+        let parameter = json!({
+            "owner": { "Contract": [1] },
+            "tokens": [{ "id": 1, "url": "a" }, { "id": 2, "uri": "b" }],
+            "extra": true,
+        });
+        let error = check_known_keys(&mint_schema(), &parameter)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.ends_with("$.extra, $.owner.Contract, $.tokens[1].uri"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn select_fields_and_indices() {
+        let value = json!({ "tokens": [{ "url": "a" }, { "url": "b" }], "pairs": [[1, 2]] });
+        assert_eq!(select(&value, "$.tokens[1].url").unwrap(), "b");
+        assert_eq!(select(&value, "tokens[0].url").unwrap(), "a");
+        assert_eq!(select(&value, "$.pairs[0][1]").unwrap(), 2);
+        assert_eq!(select(&value, "$").unwrap(), &value);
+    }
+
+    #[test]
+    fn select_reports_the_missing_path() {
+        let value = json!({ "tokens": [] });
+        let error = select(&value, "$.tokens[2].url").unwrap_err().to_string();
+        assert!(error.contains("$.tokens[2]"), "{}", error);
+        assert!(select(&value, "$.tokens[0").is_err());
+        assert!(select(&value, "$.tokens[x]").is_err());
+        assert!(select(&value, "$..tokens").is_err());
+    }
+
+    #[test]
+    fn wrap_items_by_parameter_shape() {
+        let list = Type::List(SizeLength::U8, Box::new(Type::U8));
+        let items = || vec![json!(1), json!(2)];
+        assert_eq!(
+            wrap_items("m", Some(&list), items()).unwrap(),
+            json!([1, 2])
+        );
+        let named = Type::Struct(Fields::Named(vec![("tokens".into(), list.clone())]));
+        assert_eq!(
+            wrap_items("m", Some(&named), items()).unwrap(),
+            json!({ "tokens": [1, 2] })
+        );
+        let unnamed = Type::Struct(Fields::Unnamed(vec![list]));
+        assert_eq!(
+            wrap_items("m", Some(&unnamed), items()).unwrap(),
+            json!([[1, 2]])
+        );
+        assert!(wrap_items("m", Some(&Type::U8), items()).is_err());
+        assert!(wrap_items("m", None, items()).is_err());
+    }
+}
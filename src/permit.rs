@@ -0,0 +1,197 @@
+//! Sponsored transactions through the CIS-3 `permit` entrypoint: a signer
+//! signs a message naming an entrypoint and its parameter, and a sponsor
+//! submits it and pays for it.
+use crate::{
+    instance::{self, InvokeSettings},
+    params::{self, Checks},
+    schema,
+};
+use anyhow::Context;
+use concordium_rust_sdk::{
+    smart_contracts::common::{
+        schema::{Fields, Type, VersionedModuleSchema},
+        Cursor,
+    },
+    types::{smart_contracts::OwnedParameter, ContractAddress, WalletAccount},
+    v2,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// What the signer permits the sponsor to do.
+pub struct Message<'a> {
+    pub contract: ContractAddress,
+    pub nonce: u64,
+    /// Until when the message is valid.
+    pub expiry: chrono::DateTime<chrono::Utc>,
+    pub entrypoint: &'a str,
+    pub payload: &'a OwnedParameter,
+}
+
+impl Message<'_> {
+    /// The message in the field layout of the CIS-3 reference implementation.
+    fn to_json(&self) -> Value {
+        json!({
+            "contract_address": { "index": self.contract.index, "subindex": self.contract.subindex },
+            "nonce": self.nonce,
+            "timestamp": self.expiry.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "entry_point": self.entrypoint,
+            "payload": self.payload.as_ref(),
+        })
+    }
+}
+
+/// A signed `permit` parameter and the hash its signatures are over.
+pub struct SignedPermit {
+    pub parameter: Value,
+    pub message_hash: [u8; 32],
+}
+
+/// Serialize the message with the `message` field of the parameter schema of
+/// the contract's `permit` entrypoint `method` and sign its hash with every
+/// key of the signer. The hash is taken
+/// over the signer's address, eight zero bytes and the message, which is
+/// what wallets sign and the contract checks.
+pub fn sign(
+    schema: &VersionedModuleSchema,
+    contract_name: &str,
+    method: &str,
+    signer: &WalletAccount,
+    message: &Message,
+) -> anyhow::Result<SignedPermit> {
+    let param_schema = schema::receive_param_schema(schema, contract_name, method)?
+        .context("The permit entrypoint takes no parameter.")?;
+    let Type::Struct(Fields::Named(fields)) = &param_schema else {
+        anyhow::bail!("The permit parameter is not a struct with a message.");
+    };
+    let message_schema = fields
+        .iter()
+        .find(|(name, _)| name == "message")
+        .map(|(_, ty)| ty)
+        .context("The permit parameter has no message field.")?;
+    let message_json = message.to_json();
+    let message_bytes = message_schema
+        .serial_value(&message_json)
+        .context("The permit message does not match the contract's schema.")?;
+    let mut hasher = Sha256::new();
+    hasher.update(signer.address.0);
+    hasher.update([0u8; 8]);
+    hasher.update(&message_bytes);
+    let message_hash: [u8; 32] = hasher.finalize().into();
+    let signatures: Vec<Value> = signer
+        .keys
+        .keys
+        .iter()
+        .map(|(credential, data)| {
+            let keys: Vec<Value> = data
+                .keys
+                .iter()
+                .map(|(key, pair)| {
+                    json!([key.0, { "Ed25519": [hex::encode(pair.sign(&message_hash).sig)] }])
+                })
+                .collect();
+            json!([credential.index, keys])
+        })
+        .collect();
+    Ok(SignedPermit {
+        parameter: json!({
+            "signature": signatures,
+            "signer": signer.address.to_string(),
+            "message": message_json,
+        }),
+        message_hash,
+    })
+}
+
+/// The next nonce the contract accepts from `signer`, queried with its
+/// `nonceOf` entrypoint `method`, or `None` if the contract has no such
+/// entrypoint.
+pub async fn nonce_of(
+    client: &mut v2::Client,
+    settings: &InvokeSettings,
+    schema: &VersionedModuleSchema,
+    contract_name: &str,
+    contract: ContractAddress,
+    method: &str,
+    signer: &WalletAccount,
+) -> anyhow::Result<Option<u64>> {
+    if !schema::entrypoints(schema, contract_name)
+        .iter()
+        .any(|e| e == method)
+    {
+        return Ok(None);
+    }
+    let param_schema = schema.get_receive_param_schema(contract_name, method)?;
+    let rv_schema = schema.get_receive_return_value_schema(contract_name, method)?;
+    let queries = json!([signer.address.to_string()]);
+    let query = match &param_schema {
+        Type::Struct(Fields::Named(fields)) if fields.len() == 1 => {
+            json!({ fields[0].0.clone(): queries })
+        }
+        _ => queries,
+    };
+    let parameter = params::serialize_parameter(&param_schema, &query, Checks::default())?;
+    let receive_name = schema::receive_name(contract_name, method, Some(schema))?;
+    let bytes = instance::invoke(
+        client,
+        settings,
+        contract,
+        receive_name,
+        parameter,
+        Some(schema),
+    )
+    .await?;
+    let response = rv_schema.to_json(&mut Cursor::new(&bytes[..]))?;
+    first_number(&response)
+        .map(Some)
+        .with_context(|| format!("Unexpected {} response.", method))
+}
+
+/// Check with the `viewMessageHash` entrypoint `method`, if the contract has
+/// it, that the contract computes the same hash for the permit as was signed.
+pub async fn check_message_hash(
+    client: &mut v2::Client,
+    settings: &InvokeSettings,
+    schema: &VersionedModuleSchema,
+    contract_name: &str,
+    contract: ContractAddress,
+    method: &str,
+    permit: &SignedPermit,
+) -> anyhow::Result<()> {
+    if !schema::entrypoints(schema, contract_name)
+        .iter()
+        .any(|e| e == method)
+    {
+        return Ok(());
+    }
+    let param_schema = schema.get_receive_param_schema(contract_name, method)?;
+    let parameter =
+        params::serialize_parameter(&param_schema, &permit.parameter, Checks::default())?;
+    let receive_name = schema::receive_name(contract_name, method, Some(schema))?;
+    let bytes = instance::invoke(
+        client,
+        settings,
+        contract,
+        receive_name,
+        parameter,
+        Some(schema),
+    )
+    .await?;
+    anyhow::ensure!(
+        bytes == permit.message_hash,
+        "The contract computes the message hash {}, but {} was signed. The message does not \
+         have the layout the contract expects.",
+        hex::encode(&bytes),
+        hex::encode(permit.message_hash)
+    );
+    Ok(())
+}
+
+fn first_number(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::Array(items) => items.iter().find_map(first_number),
+        Value::Object(obj) => obj.values().find_map(first_number),
+        _ => None,
+    }
+}
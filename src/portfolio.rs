@@ -0,0 +1,323 @@
+//! Token holdings of an owner across several CIS-2 contracts.
+use crate::cis2::TokenIdRange;
+use anyhow::Context;
+use concordium_rust_sdk::{
+    cis2::{BalanceOfQuery, Cis2Contract, TokenAmount, TokenId},
+    types::{Address, ContractAddress},
+    v2::{self, BlockIdentifier},
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, path::Path};
+
+/// Split a comma separated list whose items may be contract addresses
+/// `<index,subindex>`, which contain a comma themselves.
+fn split_list(s: &str) -> anyhow::Result<Vec<&str>> {
+    let mut items = Vec::new();
+    let mut rest = s.trim();
+    while !rest.is_empty() {
+        let (item, tail) = if rest.starts_with('<') {
+            let end = rest
+                .find('>')
+                .with_context(|| format!("Unterminated contract address in {}.", s))?;
+            (&rest[..=end], &rest[end + 1..])
+        } else {
+            rest.split_once(',').unwrap_or((rest, ""))
+        };
+        items.push(item.trim());
+        rest = tail.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+    }
+    Ok(items)
+}
+
+/// Parse a comma separated list of contract addresses, each written either
+/// `<index,subindex>` or as a bare index with subindex 0.
+pub fn parse_contracts(s: &str) -> anyhow::Result<Vec<ContractAddress>> {
+    let mut contracts = Vec::new();
+    for item in split_list(s)? {
+        let contract = if item.starts_with('<') {
+            item.parse()
+                .map_err(|_| anyhow::anyhow!("Invalid contract address {}.", item))?
+        } else {
+            let index = item
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid contract address {}.", item))?;
+            ContractAddress::new(index, 0)
+        };
+        contracts.push(contract);
+    }
+    anyhow::ensure!(!contracts.is_empty(), "No contract addresses given.");
+    Ok(contracts)
+}
+
+/// Token IDs per `balanceOf` invocation, which keeps the parameter well below
+/// the size limit of 65535 bytes even for long token IDs.
+const BALANCE_OF_CHUNK: usize = 500;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBalance {
+    pub token_id: TokenId,
+    pub balance: TokenAmount,
+}
+
+/// The owner's non-zero balances in one contract, or why they could not be
+/// queried.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractHoldings {
+    pub contract: ContractAddress,
+    pub tokens: Vec<TokenBalance>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Holdings {
+    pub owner: Address,
+    pub contracts: Vec<ContractHoldings>,
+}
+
+impl Holdings {
+    /// Query `balanceOf` for every token ID in every contract. A contract that
+    /// cannot be queried is reported with its error instead of aborting.
+    pub async fn fetch(
+        client: &v2::Client,
+        owner: Address,
+        contracts: &[ContractAddress],
+        token_ids: &[TokenId],
+    ) -> Self {
+        let mut report = Vec::with_capacity(contracts.len());
+        for &contract in contracts {
+            let (tokens, error) = match balances(client, owner, contract, token_ids).await {
+                Ok(tokens) => (tokens, None),
+                Err(e) => (Vec::new(), Some(format!("{:#}", e))),
+            };
+            report.push(ContractHoldings {
+                contract,
+                tokens,
+                error,
+            });
+        }
+        Holdings {
+            owner,
+            contracts: report,
+        }
+    }
+
+    pub fn print(&self) {
+        println!("Holdings of {}:", self.owner);
+        for holdings in &self.contracts {
+            println!("  {}:", holdings.contract);
+            if let Some(error) = &holdings.error {
+                println!("    error: {}", error);
+            } else if holdings.tokens.is_empty() {
+                println!("    no tokens");
+            }
+            for token in &holdings.tokens {
+                println!("    {}: {}", token.token_id, token.balance);
+            }
+        }
+    }
+
+    /// One row per token held, and one with the error per contract that
+    /// could not be queried.
+    pub fn print_csv(&self) -> anyhow::Result<()> {
+        let rows = self.contracts.iter().flat_map(|holdings| {
+            let error = holdings.error.iter().map(|error| {
+                vec![
+                    holdings.contract.to_string(),
+                    String::new(),
+                    String::new(),
+                    error.clone(),
+                ]
+            });
+            let tokens = holdings.tokens.iter().map(|token| {
+                vec![
+                    holdings.contract.to_string(),
+                    token.token_id.to_string(),
+                    token.balance.to_string(),
+                    String::new(),
+                ]
+            });
+            error.chain(tokens).collect::<Vec<_>>()
+        });
+        crate::output::print_csv(&["contract", "token_id", "balance", "error"], rows)
+    }
+}
+
+async fn balances(
+    client: &v2::Client,
+    owner: Address,
+    contract: ContractAddress,
+    token_ids: &[TokenId],
+) -> anyhow::Result<Vec<TokenBalance>> {
+    let mut client = client.clone();
+    let info = client
+        .get_instance_info(contract, &BlockIdentifier::LastFinal)
+        .await?
+        .response;
+    let mut cis2 = Cis2Contract::new(client, contract, info.name().clone());
+    let mut tokens = Vec::new();
+    for chunk in token_ids.chunks(BALANCE_OF_CHUNK) {
+        let queries = chunk
+            .iter()
+            .map(|token_id| BalanceOfQuery {
+                token_id: token_id.clone(),
+                address: owner,
+            })
+            .collect();
+        let response = cis2
+            .balance_of(&BlockIdentifier::LastFinal, queries)
+            .await
+            .context("balanceOf failed, the contract may not implement CIS-2")?;
+        tokens.extend(
+            chunk
+                .iter()
+                .zip(Vec::<TokenAmount>::from(response))
+                .filter(|(_, balance)| !balance.is_zero())
+                .map(|(token_id, balance)| TokenBalance {
+                    token_id: token_id.clone(),
+                    balance,
+                }),
+        );
+    }
+    Ok(tokens)
+}
+
+/// The owners of a comma separated list of accounts and contracts
+/// `<index,subindex>`, followed by those of a file with one per line. Empty
+/// lines and lines starting with `#` in the file are skipped.
+pub fn parse_owners(list: Option<&str>, file: Option<&Path>) -> anyhow::Result<Vec<Address>> {
+    let mut owners = match list {
+        Some(list) => split_list(list)?
+            .into_iter()
+            .map(crate::cis2::parse_address)
+            .collect::<anyhow::Result<Vec<_>>>()?,
+        None => Vec::new(),
+    };
+    if let Some(file) = file {
+        let text = std::fs::read_to_string(file)
+            .with_context(|| format!("Could not read {}.", file.display()))?;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            owners.push(
+                crate::cis2::parse_address(line)
+                    .with_context(|| format!("Line {} of {}.", i + 1, file.display()))?,
+            );
+        }
+    }
+    anyhow::ensure!(!owners.is_empty(), "No owners given.");
+    Ok(owners)
+}
+
+/// The owner as a key of the balance map: the account address or the
+/// contract address `<index,subindex>`.
+fn owner_key(owner: &Address) -> String {
+    match owner {
+        Address::Account(a) => a.to_string(),
+        Address::Contract(c) => c.to_string(),
+    }
+}
+
+/// The balances of one token for many owners.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnerBalances {
+    pub contract: ContractAddress,
+    pub token_id: TokenId,
+    pub balances: BTreeMap<String, TokenAmount>,
+}
+
+impl OwnerBalances {
+    /// Query `balanceOf` for every owner, in chunks like [`Holdings`].
+    pub async fn fetch(
+        client: &mut v2::Client,
+        contract: ContractAddress,
+        token_id: TokenId,
+        owners: &[Address],
+    ) -> anyhow::Result<Self> {
+        let info = client
+            .get_instance_info(contract, &BlockIdentifier::LastFinal)
+            .await?
+            .response;
+        let mut cis2 = Cis2Contract::new(client.clone(), contract, info.name().clone());
+        let mut balances = BTreeMap::new();
+        for chunk in owners.chunks(BALANCE_OF_CHUNK) {
+            let queries = chunk
+                .iter()
+                .map(|owner| BalanceOfQuery {
+                    token_id: token_id.clone(),
+                    address: *owner,
+                })
+                .collect();
+            let response = cis2
+                .balance_of(&BlockIdentifier::LastFinal, queries)
+                .await
+                .context("balanceOf failed, the contract may not implement CIS-2")?;
+            balances.extend(
+                chunk
+                    .iter()
+                    .map(owner_key)
+                    .zip(Vec::<TokenAmount>::from(response)),
+            );
+        }
+        Ok(Self {
+            contract,
+            token_id,
+            balances,
+        })
+    }
+
+    pub fn print(&self) {
+        println!("Balances of token {} in {}:", self.token_id, self.contract);
+        for (owner, balance) in &self.balances {
+            println!("  {}: {}", owner, balance);
+        }
+    }
+
+    pub fn print_csv(&self) -> anyhow::Result<()> {
+        crate::output::print_csv(
+            &["owner", "balance"],
+            self.balances
+                .iter()
+                .map(|(owner, balance)| vec![owner.clone(), balance.to_string()]),
+        )
+    }
+}
+
+/// The token IDs of the range, encoded as `width` bytes like the IDs minted
+/// with `--token-id-start`.
+pub fn range_token_ids(range: TokenIdRange, width: usize) -> anyhow::Result<Vec<TokenId>> {
+    crate::cis2::sequential_token_ids(range.start, range.count(), width)?
+        .into_iter()
+        .map(|(_, hex)| Ok(hex.parse()?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_list_keeps_contract_addresses_whole() {
+        assert_eq!(
+            split_list("<1,0>,2, <3,1> ,4").unwrap(),
+            ["<1,0>", "2", "<3,1>", "4"]
+        );
+    }
+
+    #[test]
+    fn split_list_skips_empty_items() {
+        assert_eq!(split_list("1,,2,").unwrap(), ["1", "2"]);
+        assert!(split_list("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn split_list_rejects_an_unterminated_address() {
+        assert!(split_list("1,<2,0").is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! Receipt files for finalized transactions, written by `--receipt-dir`.
+use crate::submit::Finalized;
+use anyhow::Context;
+use concordium_rust_sdk::{
+    id::types::AccountAddress,
+    types::{
+        hashes::TransactionHash,
+        transactions::{AccountTransaction, EncodedPayload, Payload},
+        BlockItemSummary,
+    },
+};
+use serde::Serialize;
+use std::{path::PathBuf, sync::Mutex};
+
+/// Version of the receipt format, increased on incompatible changes.
+const RECEIPT_VERSION: u32 = 1;
+
+/// A self-contained record of a finalized transaction.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Receipt<'a> {
+    version: u32,
+    #[serde(flatten)]
+    finalized: &'a Finalized,
+    sender: Option<AccountAddress>,
+    /// The parameter sent to the contract, in hex.
+    parameter: Option<String>,
+    payload: Option<Payload>,
+    /// The effects of the transaction as reported by the node.
+    summary: &'a BlockItemSummary,
+}
+
+/// Receipts written to a directory, with what was sent for each transaction.
+pub struct Receipts {
+    dir: PathBuf,
+    /// Sender and payload of every transaction submitted, until its receipt
+    /// is written.
+    sent: Mutex<Vec<(TransactionHash, AccountAddress, Payload)>>,
+}
+
+impl Receipts {
+    /// Write a receipt for every finalized transaction to `dir`.
+    pub fn new(dir: PathBuf) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&dir).with_context(|| {
+            format!("Could not create the receipt directory {}.", dir.display())
+        })?;
+        Ok(Self {
+            dir,
+            sent: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Remember what was sent, so that the receipt can include it.
+    pub fn record_sent(&self, hash: TransactionHash, tx: &AccountTransaction<EncodedPayload>) {
+        if let (Ok(payload), Ok(mut sent)) = (tx.payload.decode(), self.sent.lock()) {
+            sent.push((hash, tx.header.sender, payload));
+        }
+    }
+
+    /// Write the receipt `<hash>.json` and return its path.
+    pub fn write(
+        &self,
+        finalized: &Finalized,
+        summary: &BlockItemSummary,
+    ) -> anyhow::Result<PathBuf> {
+        let sent = self.sent.lock().ok().and_then(|mut sent| {
+            let index = sent.iter().position(|(hash, ..)| *hash == finalized.hash)?;
+            Some(sent.remove(index))
+        });
+        let (sender, payload) = match sent {
+            Some((_, sender, payload)) => (Some(sender), Some(payload)),
+            None => (None, None),
+        };
+        let parameter = match &payload {
+            Some(Payload::Update { payload }) => Some(hex::encode(payload.message.as_ref())),
+            Some(Payload::InitContract { payload }) => Some(hex::encode(payload.param.as_ref())),
+            _ => None,
+        };
+        let receipt = Receipt {
+            version: RECEIPT_VERSION,
+            finalized,
+            sender,
+            parameter,
+            payload,
+            summary,
+        };
+        let path = self.dir.join(format!("{}.json", finalized.hash));
+        // always pretty, so that receipts look the same whatever the output options
+        std::fs::write(&path, serde_json::to_string_pretty(&receipt)?)
+            .with_context(|| format!("Could not write the receipt {}.", path.display()))?;
+        Ok(path)
+    }
+}
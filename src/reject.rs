@@ -0,0 +1,116 @@
+//! Describing why a transaction or invocation was rejected, decoding the
+//! errors contracts reject with using their error schemas.
+use concordium_rust_sdk::{
+    smart_contracts::common::{
+        schema::{Type, VersionedModuleSchema},
+        Cursor,
+    },
+    types::RejectReason,
+};
+use serde_json::Value;
+
+/// The reason in a readable form. A contract's rejection is decoded with the
+/// error schema in `schema`, if given: the `return_value` of an invocation in
+/// full, else the error code as the variant of the error enum, following
+/// `#[derive(Reject)]`, which numbers the variants -1, -2, ...
+pub fn describe(
+    reason: &RejectReason,
+    return_value: Option<&[u8]>,
+    schema: Option<&VersionedModuleSchema>,
+) -> String {
+    let RejectReason::RejectedReceive {
+        reject_reason,
+        contract_address,
+        receive_name,
+        ..
+    } = reason
+    else {
+        return format!("{:?}", reason);
+    };
+    let name = receive_name.as_receive_name();
+    let entrypoint = name.entrypoint_name().to_string();
+    let error_schema = schema.and_then(|s| {
+        s.get_receive_error_schema(name.contract_name(), &entrypoint)
+            .ok()
+    });
+    let decoded = error_schema.as_ref().and_then(|ty| {
+        return_value
+            .and_then(|bytes| ty.to_json(&mut Cursor::new(bytes)).ok())
+            .map(|value| variant_name(&value).unwrap_or_else(|| value.to_string()))
+            .or_else(|| enum_variant(ty, *reject_reason))
+    });
+    match decoded {
+        Some(error) => format!(
+            "contract {} rejected {} with {} (code {})",
+            contract_address, receive_name, error, reject_reason
+        ),
+        None => format!(
+            "contract {} rejected {} with code {}",
+            contract_address, receive_name, reject_reason
+        ),
+    }
+}
+
+/// The name of a variant without fields, e.g. `Unauthorized` for
+/// `{"Unauthorized": []}`.
+fn variant_name(value: &Value) -> Option<String> {
+    let (name, fields) = value.as_object()?.iter().next()?;
+    let empty = match fields {
+        Value::Array(a) => a.is_empty(),
+        Value::Object(o) => o.is_empty(),
+        _ => false,
+    };
+    empty.then(|| name.clone())
+}
+
+/// The variant of the error enum the code of `#[derive(Reject)]` stands for.
+fn enum_variant(ty: &Type, code: i32) -> Option<String> {
+    let Type::Enum(variants) = ty else {
+        return None;
+    };
+    let index = usize::try_from(-(i64::from(code)) - 1).ok()?;
+    variants.get(index).map(|(name, _)| name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use concordium_rust_sdk::smart_contracts::common::schema::Fields;
+    use serde_json::json;
+
+    fn error_enum() -> Type {
+        Type::Enum(vec![
+            ("ParseParams".into(), Fields::None),
+            ("Unauthorized".into(), Fields::None),
+            ("InvalidTokenId".into(), Fields::None),
+        ])
+    }
+
+    #[test]
+    fn enum_variant_from_negative_codes() {
+        let ty = error_enum();
+        assert_eq!(enum_variant(&ty, -1).as_deref(), Some("ParseParams"));
+        assert_eq!(enum_variant(&ty, -2).as_deref(), Some("Unauthorized"));
+        assert_eq!(enum_variant(&ty, -3).as_deref(), Some("InvalidTokenId"));
+    }
+
+    #[test]
+    fn enum_variant_out_of_range() {
+        let ty = error_enum();
+        assert_eq!(enum_variant(&ty, -4), None);
+        assert_eq!(enum_variant(&ty, 0), None);
+        assert_eq!(enum_variant(&ty, 1), None);
+        assert_eq!(enum_variant(&ty, i32::MIN), None);
+        assert_eq!(enum_variant(&Type::U8, -1), None);
+    }
+
+    #[test]
+    fn variant_name_only_without_fields() {
+        assert_eq!(
+            variant_name(&json!({ "Unauthorized": [] })).as_deref(),
+            Some("Unauthorized")
+        );
+        assert_eq!(variant_name(&json!({ "Custom": [1] })), None);
+        assert_eq!(variant_name(&json!("Unauthorized")), None);
+    }
+}
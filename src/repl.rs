@@ -0,0 +1,115 @@
+//! Interactive mode running several commands on one connection.
+use crate::{execute, run_offline, version, Action, App, OutputFormat, Session};
+use concordium_rust_sdk::v2;
+use std::io::Write;
+use structopt::StructOpt;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// A line of input, parsed like the subcommand of the command line.
+#[derive(StructOpt)]
+#[structopt(no_version, name = "")]
+struct Line {
+    #[structopt(subcommand)]
+    action: Action,
+}
+
+/// Parse the command of `--then`, which cannot be another session or
+/// `version`.
+pub fn parse_action(line: &str) -> anyhow::Result<Action> {
+    let words = split_words(line)?;
+    let Line { action } = Line::from_iter_safe(std::iter::once(String::new()).chain(words))
+        .map_err(|e| anyhow::anyhow!("Invalid --then command: {}", e.message))?;
+    anyhow::ensure!(
+        !matches!(action, Action::Repl | Action::Version),
+        "--then cannot run repl or version."
+    );
+    Ok(action)
+}
+
+/// Read commands from stdin until end of input. A failing command is reported
+/// and does not end the session.
+pub async fn run(app: &App, client: &mut v2::Client, session: &mut Session) -> anyhow::Result<()> {
+    println!("Connected. Enter commands such as `state-size --address <1234,0>`, `help` for a list, Ctrl-D to exit.");
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+        let Some(line) = lines.next_line().await? else {
+            println!();
+            return Ok(());
+        };
+        let words = match split_words(&line) {
+            Ok(words) if words.is_empty() => continue,
+            Ok(words) => words,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                continue;
+            }
+        };
+        let action = match Line::from_iter_safe(std::iter::once(String::new()).chain(words)) {
+            Ok(Line {
+                action: Action::Repl,
+            }) => {
+                eprintln!("Already in interactive mode.");
+                continue;
+            }
+            Ok(Line {
+                action: Action::Version,
+            }) => {
+                let info = version::VersionInfo::collect(app.endpoint.clone()).await;
+                match app.output {
+                    OutputFormat::Human | OutputFormat::Csv => info.print(),
+                    OutputFormat::Json => app.printer.print_json(&info)?,
+                }
+                continue;
+            }
+            Ok(line) => line.action,
+            Err(e) => {
+                // help and usage errors
+                eprintln!("{}", e.message);
+                continue;
+            }
+        };
+        let result = match run_offline(app, &action).await {
+            Ok(true) => Ok(()),
+            Ok(false) => execute(app, action, client, session).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {:#}", e);
+        }
+    }
+}
+
+/// Split a line into words at whitespace, keeping quoted parts together.
+fn split_words(line: &str) -> anyhow::Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    anyhow::ensure!(quote.is_none(), "Unterminated quote.");
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
@@ -0,0 +1,542 @@
+//! Inspecting module schemas.
+use anyhow::Context;
+use base64::{
+    alphabet,
+    engine::{general_purpose, DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine as _,
+};
+use concordium_rust_sdk::{
+    smart_contracts::common::{
+        from_bytes,
+        schema::{Type, VersionedModuleSchema},
+        to_bytes, OwnedReceiveName,
+    },
+    types::ContractAddress,
+    v2,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use strum_macros::EnumString;
+
+/// The base64 variant of a schema file.
+///
+/// `cargo concordium build --schema-base64-out` writes standard base64 with
+/// padding. Schemas embedded in URLs or generated by web tooling are often
+/// URL-safe, and some tools strip the padding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Base64Variant {
+    /// Try the variants in turn and use the first that yields a valid schema.
+    #[default]
+    Auto,
+    /// The standard alphabet with padding.
+    Standard,
+    /// The URL-safe alphabet, with or without padding.
+    UrlSafe,
+    /// The standard alphabet without padding.
+    NoPad,
+}
+
+const URL_SAFE_ANY_PAD: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// The URL `path` names, if it is an `http://` or `https://` URL rather than
+/// a file.
+fn url_of(path: &Path) -> Option<&str> {
+    path.to_str()
+        .filter(|p| p.starts_with("http://") || p.starts_with("https://"))
+}
+
+/// Parse a schema that is base64 text or, if the bytes are not text, the
+/// binary schema itself.
+fn parse(source: &[u8], variant: Base64Variant) -> anyhow::Result<VersionedModuleSchema> {
+    let Ok(text) = std::str::from_utf8(source) else {
+        return from_bytes(source)
+            .context("The schema is neither base64 text nor a valid binary schema.");
+    };
+    let text = text.trim();
+    let decode = |variant| -> anyhow::Result<VersionedModuleSchema> {
+        let schema_source = match variant {
+            Base64Variant::Standard => general_purpose::STANDARD.decode(text),
+            Base64Variant::UrlSafe => URL_SAFE_ANY_PAD.decode(text),
+            Base64Variant::NoPad | Base64Variant::Auto => {
+                general_purpose::STANDARD_NO_PAD.decode(text)
+            }
+        }
+        .with_context(|| format!("The schema file is not valid {:?} base64.", variant))?;
+        let schema = from_bytes(&schema_source).context("Unable to parse the schema.")?;
+        Ok(schema)
+    };
+    if variant != Base64Variant::Auto {
+        return decode(variant);
+    }
+    [
+        Base64Variant::NoPad,
+        Base64Variant::Standard,
+        Base64Variant::UrlSafe,
+    ]
+    .into_iter()
+    .find_map(|variant| decode(variant).ok())
+    .context("The schema file is neither standard nor URL-safe base64 of a valid schema.")
+}
+
+/// Reads schemas, keeping downloads and parsed schemas in the cache
+/// directory if one is given.
+#[derive(Debug, Clone, Default)]
+pub struct Loader {
+    variant: Base64Variant,
+    /// Directory of parsed schemas, by module reference, and of downloaded
+    /// schemas, by URL, if caching is enabled.
+    cache_dir: Option<PathBuf>,
+}
+
+impl Loader {
+    /// Read schemas in the base64 `variant`, caching them in `cache_dir`.
+    pub fn new(variant: Base64Variant, cache_dir: Option<PathBuf>) -> anyhow::Result<Self> {
+        if let Some(dir) = &cache_dir {
+            std::fs::create_dir_all(dir).with_context(|| {
+                format!(
+                    "Could not create the schema cache directory {}.",
+                    dir.display()
+                )
+            })?;
+        }
+        Ok(Self { variant, cache_dir })
+    }
+
+    /// Read a module schema from a file or an `http(s)://` URL, which may be
+    /// gzip compressed.
+    pub async fn load(&self, path: &Path) -> anyhow::Result<VersionedModuleSchema> {
+        parse(&self.read_source(path).await?, self.variant)
+    }
+
+    /// Load the schema for `contract`. With a cache directory the parsed
+    /// schema is kept in a file named after the contract's module reference,
+    /// so an upgraded contract gets a new entry. The entry starts with the
+    /// key of the schema source it was parsed from and is replaced when the
+    /// source changes.
+    pub async fn load_for_contract(
+        &self,
+        client: &mut v2::Client,
+        contract: ContractAddress,
+        path: &Path,
+    ) -> anyhow::Result<VersionedModuleSchema> {
+        let (Some(dir), Some(key)) = (&self.cache_dir, self.source_key(path)) else {
+            return self.load(path).await;
+        };
+        let module_ref = client
+            .get_instance_info(contract, &v2::BlockIdentifier::LastFinal)
+            .await?
+            .response
+            .source_module();
+        let entry = dir.join(format!("{}.schema", module_ref));
+        let cached = std::fs::read(&entry).ok().and_then(|bytes| {
+            let (entry_key, schema) = bytes.split_at_checked(key.len())?;
+            (entry_key == key).then(|| from_bytes(schema).ok())?
+        });
+        if let Some(schema) = cached {
+            return Ok(schema);
+        }
+        let schema = self.load(path).await?;
+        let mut bytes = key.to_vec();
+        bytes.extend_from_slice(&to_bytes(&schema));
+        std::fs::write(&entry, bytes)
+            .with_context(|| format!("Could not write the schema cache {}.", entry.display()))?;
+        Ok(schema)
+    }
+
+    /// The SHA-256 of what identifies the schema source: the URL, or the
+    /// canonical path, size and modification time of a file, together with
+    /// the base64 variant. A cached schema is only used while this key is
+    /// unchanged, and computing it does not read the file. `None` if the file
+    /// cannot be inspected.
+    fn source_key(&self, path: &Path) -> Option<[u8; 32]> {
+        let source = match url_of(path) {
+            Some(url) => url.to_string(),
+            None => {
+                let metadata = std::fs::metadata(path).ok()?;
+                let modified = metadata
+                    .modified()
+                    .ok()?
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .ok()?;
+                format!(
+                    "{}\n{}\n{}",
+                    std::fs::canonicalize(path).ok()?.display(),
+                    metadata.len(),
+                    modified.as_nanos()
+                )
+            }
+        };
+        Some(Sha256::digest(format!("{}\n{:?}", source, self.variant)).into())
+    }
+
+    /// The contents of the schema file or URL, decompressed. With a cache
+    /// directory, downloads are kept there by URL and not fetched again.
+    async fn read_source(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+        let Some(url) = url_of(path) else {
+            return crate::module::read_artifact(path).context("Unable to read the schema file.");
+        };
+        let entry = self
+            .cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.download", hex::encode(Sha256::digest(url)))));
+        if let Some(bytes) = entry.as_ref().and_then(|entry| std::fs::read(entry).ok()) {
+            return Ok(bytes);
+        }
+        let bytes = async {
+            let response = reqwest::get(url).await?.error_for_status()?;
+            anyhow::Ok(response.bytes().await?.to_vec())
+        }
+        .await
+        .with_context(|| format!("Could not download the schema from {}.", url))?;
+        let bytes = crate::module::gunzip(bytes, url.ends_with(".gz"))
+            .with_context(|| format!("Could not decompress the schema from {}.", url))?;
+        if let Some(entry) = &entry {
+            std::fs::write(entry, &bytes).with_context(|| {
+                format!("Could not write the schema cache {}.", entry.display())
+            })?;
+        }
+        Ok(bytes)
+    }
+}
+
+/// The parameter schema of a receive entrypoint, or `None` if the schema
+/// describes the entrypoint without a parameter.
+pub fn receive_param_schema(
+    schema: &VersionedModuleSchema,
+    contract: &str,
+    method: &str,
+) -> anyhow::Result<Option<Type>> {
+    let param_schema = match schema {
+        VersionedModuleSchema::V0(m) => m
+            .contracts
+            .get(contract)
+            .and_then(|c| c.receive.get(method))
+            .map(|t| Some(t.clone())),
+        VersionedModuleSchema::V1(m) => m
+            .contracts
+            .get(contract)
+            .and_then(|c| c.receive.get(method))
+            .map(|f| f.parameter().cloned()),
+        VersionedModuleSchema::V2(m) => m
+            .contracts
+            .get(contract)
+            .and_then(|c| c.receive.get(method))
+            .map(|f| f.parameter().cloned()),
+        VersionedModuleSchema::V3(m) => m
+            .contracts
+            .get(contract)
+            .and_then(|c| c.receive.get(method))
+            .map(|f| f.parameter().cloned()),
+    };
+    param_schema.with_context(|| {
+        format!(
+            "The schema does not describe the entrypoint {}.{}.",
+            contract, method
+        )
+    })
+}
+
+/// The schema of the events `contract` logs, which only version 3 schemas
+/// include.
+pub fn event_schema(schema: &VersionedModuleSchema, contract: &str) -> Option<Type> {
+    match schema {
+        VersionedModuleSchema::V3(m) => m.contracts.get(contract)?.event.clone(),
+        _ => None,
+    }
+}
+
+/// Names of all receive entrypoints the schema describes for `contract`.
+pub fn entrypoints(schema: &VersionedModuleSchema, contract: &str) -> Vec<String> {
+    match schema {
+        VersionedModuleSchema::V0(m) => m
+            .contracts
+            .get(contract)
+            .map(|c| c.receive.keys().cloned().collect()),
+        VersionedModuleSchema::V1(m) => m
+            .contracts
+            .get(contract)
+            .map(|c| c.receive.keys().cloned().collect()),
+        VersionedModuleSchema::V2(m) => m
+            .contracts
+            .get(contract)
+            .map(|c| c.receive.keys().cloned().collect()),
+        VersionedModuleSchema::V3(m) => m
+            .contracts
+            .get(contract)
+            .map(|c| c.receive.keys().cloned().collect()),
+    }
+    .unwrap_or_default()
+}
+
+/// A receive entrypoint and which of its schemas are present.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodSchemas {
+    pub name: String,
+    pub parameter: bool,
+    pub return_value: bool,
+    pub error: bool,
+}
+
+/// All receive entrypoints the schema describes for `contract`, with the
+/// schemas each has. Only version 2 and 3 schemas include error schemas, and
+/// version 0 schemas include no return value schemas.
+pub fn methods(
+    schema: &VersionedModuleSchema,
+    contract: &str,
+) -> anyhow::Result<Vec<MethodSchemas>> {
+    let method = |name: &String, parameter: bool, return_value: bool, error: bool| MethodSchemas {
+        name: name.clone(),
+        parameter,
+        return_value,
+        error,
+    };
+    let methods = match schema {
+        VersionedModuleSchema::V0(m) => m.contracts.get(contract).map(|c| {
+            c.receive
+                .keys()
+                .map(|n| method(n, true, false, false))
+                .collect()
+        }),
+        VersionedModuleSchema::V1(m) => m.contracts.get(contract).map(|c| {
+            c.receive
+                .iter()
+                .map(|(n, f)| {
+                    method(
+                        n,
+                        f.parameter().is_some(),
+                        f.return_value().is_some(),
+                        false,
+                    )
+                })
+                .collect()
+        }),
+        VersionedModuleSchema::V2(m) => m.contracts.get(contract).map(|c| {
+            c.receive
+                .iter()
+                .map(|(n, f)| {
+                    method(
+                        n,
+                        f.parameter().is_some(),
+                        f.return_value().is_some(),
+                        f.error().is_some(),
+                    )
+                })
+                .collect()
+        }),
+        VersionedModuleSchema::V3(m) => m.contracts.get(contract).map(|c| {
+            c.receive
+                .iter()
+                .map(|(n, f)| {
+                    method(
+                        n,
+                        f.parameter().is_some(),
+                        f.return_value().is_some(),
+                        f.error().is_some(),
+                    )
+                })
+                .collect()
+        }),
+    };
+    methods.with_context(|| {
+        format!(
+            "The schema does not describe a contract {}. It describes: {}.",
+            contract,
+            contracts(schema).join(", ")
+        )
+    })
+}
+
+/// Names of all contracts the schema describes.
+pub fn contracts(schema: &VersionedModuleSchema) -> Vec<String> {
+    match schema {
+        VersionedModuleSchema::V0(m) => m.contracts.keys().cloned().collect(),
+        VersionedModuleSchema::V1(m) => m.contracts.keys().cloned().collect(),
+        VersionedModuleSchema::V2(m) => m.contracts.keys().cloned().collect(),
+        VersionedModuleSchema::V3(m) => m.contracts.keys().cloned().collect(),
+    }
+}
+
+/// Print the entrypoints and their schemas as a table.
+pub fn print_methods(methods: &[MethodSchemas]) {
+    let mark = |present: bool| if present { "yes" } else { "-" };
+    println!(
+        "{:<32} {:<9} {:<12} error",
+        "entrypoint", "parameter", "return value"
+    );
+    for m in methods {
+        println!(
+            "{:<32} {:<9} {:<12} {}",
+            m.name,
+            mark(m.parameter),
+            mark(m.return_value),
+            mark(m.error)
+        );
+    }
+}
+
+/// Print the entrypoints and their schemas as CSV.
+pub fn print_methods_csv(methods: &[MethodSchemas]) -> anyhow::Result<()> {
+    crate::output::print_csv(
+        &["entrypoint", "parameter", "return_value", "error"],
+        methods.iter().map(|m| {
+            vec![
+                m.name.clone(),
+                m.parameter.to_string(),
+                m.return_value.to_string(),
+                m.error.to_string(),
+            ]
+        }),
+    )
+}
+
+/// Construct the receive name `<contract>.<method>`, checking its format.
+///
+/// If a schema is given the method must also be one of the contract's
+/// entrypoints. Otherwise the error lists the valid entrypoints and suggests
+/// the closest one.
+pub fn receive_name(
+    contract: &str,
+    method: &str,
+    schema: Option<&VersionedModuleSchema>,
+) -> anyhow::Result<OwnedReceiveName> {
+    let checked = OwnedReceiveName::new(format!("{}.{}", contract, method));
+    let known = schema.map(|s| entrypoints(s, contract));
+    let problem = match (&checked, &known) {
+        (Err(e), _) => e.to_string(),
+        (Ok(_), Some(known)) if !known.iter().any(|m| m == method) => {
+            format!("The contract {} has no entrypoint {}.", contract, method)
+        }
+        (Ok(name), _) => return Ok(name.clone()),
+    };
+    match known {
+        Some(known) if !known.is_empty() => {
+            let hint = closest(method, &known)
+                .map(|m| format!(" Did you mean {}?", m))
+                .unwrap_or_default();
+            anyhow::bail!(
+                "Invalid method name {}: {}{} Valid entrypoints are: {}.",
+                method,
+                problem,
+                hint,
+                known.join(", ")
+            )
+        }
+        _ => anyhow::bail!("Invalid method name {}: {}", method, problem),
+    }
+}
+
+/// The candidate closest to `name` by edit distance, if it is reasonably close.
+fn closest<'a>(name: &str, candidates: &'a [String]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|c| (edit_distance(&name.to_lowercase(), &c.to_lowercase()), c))
+        .filter(|(d, c)| *d <= std::cmp::max(2, c.len() / 3))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c.as_str())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use concordium_rust_sdk::smart_contracts::common::schema::{ContractV3, FunctionV2, ModuleV3};
+
+    fn schema_bytes() -> Vec<u8> {
+        let mint = FunctionV2 {
+            parameter: Some(Type::U8),
+            return_value: None,
+            error: None,
+        };
+        let contract = ContractV3 {
+            init: None,
+            receive: [("mint".to_string(), mint)].into(),
+            event: None,
+        };
+        to_bytes(&VersionedModuleSchema::V3(ModuleV3 {
+            contracts: [("test".to_string(), contract)].into(),
+        }))
+    }
+
+    fn mint_param(schema: &VersionedModuleSchema) -> Type {
+        schema.get_receive_param_schema("test", "mint").unwrap()
+    }
+
+    #[test]
+    fn parse_binary() {
+        let schema = parse(&schema_bytes(), Base64Variant::Auto).unwrap();
+        assert_eq!(mint_param(&schema), Type::U8);
+    }
+
+    #[test]
+    fn parse_base64_variants() {
+        let bytes = schema_bytes();
+        let texts = [
+            (
+                Base64Variant::Standard,
+                general_purpose::STANDARD.encode(&bytes),
+            ),
+            (
+                Base64Variant::NoPad,
+                general_purpose::STANDARD_NO_PAD.encode(&bytes),
+            ),
+            (
+                Base64Variant::UrlSafe,
+                general_purpose::URL_SAFE.encode(&bytes),
+            ),
+        ];
+        for (variant, text) in texts {
+            let text = format!("{}\n", text);
+            let schema = parse(text.as_bytes(), variant).unwrap();
+            assert_eq!(mint_param(&schema), Type::U8);
+            let schema = parse(text.as_bytes(), Base64Variant::Auto).unwrap();
+            assert_eq!(mint_param(&schema), Type::U8);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_invalid_schemas() {
+        assert!(parse(b"not base64!", Base64Variant::Auto).is_err());
+        let text = general_purpose::STANDARD.encode(b"not a schema");
+        assert!(parse(text.as_bytes(), Base64Variant::Auto).is_err());
+        assert!(parse(&[0xff, 0xff, 0x03], Base64Variant::Auto).is_err());
+    }
+
+    #[test]
+    fn source_key_changes_with_the_source() {
+        let path = std::env::temp_dir().join(format!("schema-key-{}.bin", std::process::id()));
+        std::fs::write(&path, schema_bytes()).unwrap();
+        let loader = Loader::default();
+        let key = loader.source_key(&path).unwrap();
+        assert_eq!(loader.source_key(&path), Some(key));
+        let standard = Loader {
+            variant: Base64Variant::Standard,
+            ..Loader::default()
+        };
+        assert_ne!(standard.source_key(&path), Some(key));
+        std::fs::write(&path, [schema_bytes(), vec![0]].concat()).unwrap();
+        assert_ne!(loader.source_key(&path), Some(key));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loader.source_key(&path), None);
+        let url = Path::new("https://example.com/schema.bin");
+        assert!(loader.source_key(url).is_some());
+    }
+}
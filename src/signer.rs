@@ -0,0 +1,93 @@
+//! Signing transactions with an external signer, so that the account keys
+//! never have to be on the machine that builds and submits the transactions.
+//!
+//! The signer is an HTTP endpoint given with `--signer-url`. For every
+//! transaction it receives a POST request with a JSON body
+//!
+//! ```json
+//! {
+//!   "sender": "<account address>",
+//!   "hashToSign": "<hex of the 32 byte hash to sign>",
+//!   "body": "<hex of the serialized header and payload>",
+//!   "nonce": 17,
+//!   "expiry": 1700000000,
+//!   "energy": 5000
+//! }
+//! ```
+//!
+//! and answers with the Ed25519 signatures on `hashToSign`, by credential
+//! index and key index:
+//!
+//! ```json
+//! { "signatures": { "0": { "0": "<hex of the 64 byte signature>" } } }
+//! ```
+//!
+//! Any other status than 200 is an error, and its body is reported. The
+//! signer may check `body` to decide whether to sign.
+use crate::submit::UnsignedTransaction;
+use anyhow::Context;
+use concordium_rust_sdk::{
+    common::types::TransactionSignature,
+    types::transactions::{construct::PreAccountTransaction, AccountTransaction, EncodedPayload},
+};
+use serde::Deserialize;
+
+/// The answer of the signer.
+#[derive(Debug, Deserialize)]
+struct SignResponse {
+    signatures: TransactionSignature,
+}
+
+/// An HTTP endpoint that signs transactions.
+pub struct ExternalSigner {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl ExternalSigner {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Ask the signer for the signatures of the transaction and assemble the
+    /// signed transaction. The number of signatures must be the one the
+    /// transaction was built for, since it determines the energy cost.
+    pub async fn sign(
+        &self,
+        pre: PreAccountTransaction,
+        num_sigs: u32,
+    ) -> anyhow::Result<AccountTransaction<EncodedPayload>> {
+        let request = UnsignedTransaction::from(&pre);
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await
+            .with_context(|| format!("Could not reach the signer at {}.", self.url))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("The signer refused to sign ({}): {}", status, body.trim());
+        }
+        let SignResponse { signatures } = response
+            .json()
+            .await
+            .context("The signer's answer has no valid signatures.")?;
+        anyhow::ensure!(
+            signatures.num_signatures() == num_sigs,
+            "The signer returned {} signature(s), but the transaction was built for {}. Set \
+             --num-signatures to the number of keys the signer uses.",
+            signatures.num_signatures(),
+            num_sigs
+        );
+        Ok(AccountTransaction {
+            signature: signatures,
+            header: pre.header,
+            payload: pre.encoded,
+        })
+    }
+}
@@ -0,0 +1,981 @@
+//! Submitting transactions and reporting their outcome.
+use crate::{output::Printer, receipt::Receipts};
+use anyhow::Context;
+use concordium_rust_sdk::{
+    common::{
+        self,
+        types::{Amount, TransactionTime},
+    },
+    endpoints::{QueryError, RPCError},
+    id::types::{AccountAddress, AccountCredentialWithoutProofs},
+    smart_contracts::common::schema::VersionedModuleSchema,
+    types::{
+        hashes::{BlockHash, TransactionHash},
+        transactions::{
+            construct::{self, GivenEnergy, PreAccountTransaction},
+            cost, AccountTransaction, BlockItem, EncodedPayload, ExactSizeTransactionSigner,
+            Payload,
+        },
+        AbsoluteBlockHeight, AccountTransactionEffects, BlockItemSummary, BlockItemSummaryDetails,
+        Energy, Nonce, TransactionStatus, WalletAccount,
+    },
+    v2::{self, BlockIdentifier},
+};
+use futures::StreamExt;
+use serde::Serialize;
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+};
+
+/// Options of submitting and awaiting transactions, from the flags.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    /// Whether the height and slot time of the block a transaction is
+    /// finalized in are queried.
+    pub block_metadata: bool,
+    /// Number of finalized blocks to wait for on top of a transaction's block.
+    pub confirmations: u64,
+    /// Whether every transaction is printed as JSON to stderr before
+    /// submission.
+    pub print_tx_json: bool,
+    /// Whether the signatures of every transaction are checked against the
+    /// sender's thresholds before submission.
+    pub signature_count_check: bool,
+    /// The most transactions submitted per second, if limited.
+    pub rate_limit: Option<f64>,
+    /// Retries allowed across the whole command, if limited.
+    pub retry_budget: Option<u32>,
+    /// When all transactions expire, instead of `EXPIRY_SECONDS` after they
+    /// are built.
+    pub expiry_absolute: Option<chrono::DateTime<chrono::Utc>>,
+    /// Directory receipts are written to, if any.
+    pub receipt_dir: Option<PathBuf>,
+}
+
+/// Paces submissions with a token bucket that refills at `rate` tokens per
+/// second and holds at most one second's worth, so short bursts are allowed
+/// but the average stays below the rate.
+struct RateLimiter {
+    rate: f64,
+    tokens: f64,
+    last: std::time::Instant,
+}
+
+impl RateLimiter {
+    /// Take a token and return how long to wait until it is available.
+    fn reserve(&mut self) -> std::time::Duration {
+        let now = std::time::Instant::now();
+        let refill = now.duration_since(self.last).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refill).min(self.rate.max(1.0)) - 1.0;
+        self.last = now;
+        std::time::Duration::from_secs_f64((-self.tokens / self.rate).max(0.0))
+    }
+}
+
+/// How often waiting for a transaction is resumed after the connection
+/// failed, before giving up.
+const WAIT_RETRIES: u32 = 10;
+
+/// Pause before resuming to wait for a transaction.
+const WAIT_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Seconds until a transaction expires.
+pub const EXPIRY_SECONDS: i64 = 300;
+
+/// Transactions expiring sooner than this are not submitted, as they could
+/// expire before the node processes them.
+pub const EXPIRY_MARGIN_SECONDS: i64 = 30;
+
+/// Submits transactions and waits for their outcome, keeping track of what
+/// the command submitted and how many retries it made.
+pub struct Submitter {
+    settings: Settings,
+    printer: Printer,
+    /// The node endpoint, for reconnecting while waiting for a transaction.
+    endpoint: v2::Endpoint,
+    /// The limit on submissions per second, if any.
+    rate_limit: Mutex<Option<RateLimiter>>,
+    /// Retries made so far.
+    retries_used: AtomicU32,
+    /// Hashes of all transactions submitted so far.
+    submitted: Mutex<Vec<TransactionHash>>,
+    receipts: Option<Receipts>,
+    /// The schema of the contract the command calls, for decoding its
+    /// rejections.
+    error_schema: Option<VersionedModuleSchema>,
+}
+
+impl Submitter {
+    /// Submit to the node at `endpoint` with the `settings`, printing with
+    /// `printer`. The rate limit must be positive and the absolute expiry in
+    /// the future.
+    pub fn new(
+        settings: Settings,
+        endpoint: v2::Endpoint,
+        printer: Printer,
+    ) -> anyhow::Result<Self> {
+        let rate_limit = match settings.rate_limit {
+            Some(rate) => {
+                anyhow::ensure!(
+                    rate.is_finite() && rate > 0.0,
+                    "--rate-limit must be a positive number of transactions per second."
+                );
+                Some(RateLimiter {
+                    rate,
+                    tokens: 1.0,
+                    last: std::time::Instant::now(),
+                })
+            }
+            None => None,
+        };
+        if let Some(deadline) = settings.expiry_absolute {
+            anyhow::ensure!(
+                deadline > chrono::Utc::now(),
+                "The --expiry-absolute {} is not in the future.",
+                deadline.to_rfc3339()
+            );
+        }
+        let receipts = settings
+            .receipt_dir
+            .clone()
+            .map(Receipts::new)
+            .transpose()?;
+        Ok(Self {
+            settings,
+            printer,
+            endpoint,
+            rate_limit: Mutex::new(rate_limit),
+            retries_used: AtomicU32::new(0),
+            submitted: Mutex::new(Vec::new()),
+            receipts,
+            error_schema: None,
+        })
+    }
+
+    /// Decode the rejections of the called contract with `schema`.
+    pub fn set_error_schema(&mut self, schema: Option<VersionedModuleSchema>) {
+        self.error_schema = schema;
+    }
+
+    /// The schema rejections are decoded with, if any.
+    pub fn error_schema(&self) -> Option<&VersionedModuleSchema> {
+        self.error_schema.as_ref()
+    }
+
+    /// The transactions submitted so far, in order.
+    pub fn submitted(&self) -> Vec<TransactionHash> {
+        self.submitted.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Wait until the rate limit allows another submission.
+    async fn pace(&self) {
+        let delay = match self.rate_limit.lock() {
+            Ok(mut limit) => limit.as_mut().map(RateLimiter::reserve),
+            Err(_) => None,
+        };
+        if let Some(delay) = delay.filter(|d| !d.is_zero()) {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Count a retry against the budget, failing if it is used up.
+    fn use_retry(&self) -> anyhow::Result<()> {
+        let used = self.retries_used.fetch_add(1, Ordering::Relaxed);
+        if let Some(budget) = self.settings.retry_budget {
+            anyhow::ensure!(
+                used < budget,
+                "Giving up: the --retry-budget of {} retries for the whole command is used up.",
+                budget
+            );
+        }
+        Ok(())
+    }
+
+    /// The expiry for a transaction built now.
+    pub fn expiry(&self) -> TransactionTime {
+        match self.settings.expiry_absolute {
+            Some(deadline) => TransactionTime::from_seconds(deadline.timestamp() as u64),
+            None => TransactionTime::from_seconds(
+                (chrono::Utc::now().timestamp() + EXPIRY_SECONDS) as u64,
+            ),
+        }
+    }
+
+    /// Fail if the transaction expires within `EXPIRY_MARGIN_SECONDS`, which
+    /// happens when a slow signer or a pause before submission used up its
+    /// time, or the --expiry-absolute is close.
+    pub fn check_expiry(&self, expiry: TransactionTime) -> anyhow::Result<()> {
+        let remaining = expires_in(expiry);
+        anyhow::ensure!(
+            remaining >= EXPIRY_MARGIN_SECONDS,
+            "The transaction {}, too soon to submit it safely. Sign it again{}.",
+            describe_expiry(remaining),
+            if self.settings.expiry_absolute.is_some() {
+                " with a later --expiry-absolute"
+            } else {
+                ""
+            }
+        );
+        Ok(())
+    }
+
+    /// Sign and submit the planned transaction and wait for its outcome.
+    ///
+    /// If `retry_on_reject` is set and the transaction fails because of its nonce
+    /// or expiry, it is resubmitted once with a freshly queried nonce and a new
+    /// expiry. Transactions rejected by the contract are never retried. The
+    /// outcome carries the nonce that was used in the end.
+    pub async fn send_plan(
+        &self,
+        client: &mut v2::Client,
+        keys: &WalletAccount,
+        plan: &TransactionPlan,
+        nonce: Nonce,
+        expiry: TransactionTime,
+        retry_on_reject: bool,
+    ) -> anyhow::Result<Finalized> {
+        match self
+            .send_and_wait(client, plan.sign(keys, nonce, expiry))
+            .await
+        {
+            Err(e) if retry_on_reject && is_nonce_or_expiry_failure(&e) => {
+                self.use_retry().context(format!("{:#}", e))?;
+                eprintln!(
+                    "Transaction failed because of its nonce or expiry ({:#}), retrying once.",
+                    e
+                );
+                let nonce = client
+                    .get_next_account_sequence_number(&keys.address)
+                    .await?
+                    .nonce;
+                self.send_and_wait(client, plan.sign(keys, nonce, self.expiry()))
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    /// Submit the transaction to the chain, wait until it is finalized and print
+    /// its outcome.
+    pub async fn send_and_wait(
+        &self,
+        client: &mut v2::Client,
+        tx: AccountTransaction<EncodedPayload>,
+    ) -> anyhow::Result<Finalized> {
+        let nonce = tx.header.nonce;
+        let transaction_hash = self.send(client, tx).await?;
+        let finalized = self.wait(client, &transaction_hash).await?;
+        Ok(Finalized {
+            nonce: Some(nonce),
+            ..finalized
+        })
+    }
+
+    /// Submit the transaction to the chain without waiting for it. It was
+    /// signed here, so it fails if it expires too soon to submit safely.
+    pub async fn send(
+        &self,
+        client: &mut v2::Client,
+        tx: AccountTransaction<EncodedPayload>,
+    ) -> anyhow::Result<TransactionHash> {
+        self.submit(client, &BlockItem::AccountTransaction(tx), true)
+            .await
+    }
+
+    /// Submit a block item signed elsewhere to the chain without waiting for
+    /// it. It cannot be signed again, so an expiry that is close only warns.
+    pub async fn send_item(
+        &self,
+        client: &mut v2::Client,
+        item: &BlockItem<EncodedPayload>,
+    ) -> anyhow::Result<TransactionHash> {
+        self.submit(client, item, false).await
+    }
+
+    async fn submit(
+        &self,
+        client: &mut v2::Client,
+        item: &BlockItem<EncodedPayload>,
+        signed_here: bool,
+    ) -> anyhow::Result<TransactionHash> {
+        if let (true, BlockItem::AccountTransaction(tx)) = (self.settings.print_tx_json, item) {
+            let json = TransactionJson {
+                sender: tx.header.sender,
+                nonce: tx.header.nonce,
+                expiry: tx.header.expiry,
+                energy: tx.header.energy_amount,
+                payload: tx
+                    .payload
+                    .decode()
+                    .context("Could not decode the transaction payload.")?,
+            };
+            eprintln!("{}", self.printer.to_json_string(&json)?);
+        }
+        if let (true, BlockItem::AccountTransaction(tx)) =
+            (self.settings.signature_count_check, item)
+        {
+            self.check_signature_count(client, tx).await?;
+        }
+        self.pace().await;
+        if let BlockItem::AccountTransaction(tx) = item {
+            if signed_here {
+                self.check_expiry(tx.header.expiry)?;
+            } else {
+                let remaining = expires_in(tx.header.expiry);
+                if remaining < EXPIRY_MARGIN_SECONDS {
+                    eprintln!(
+                        "Warning: the transaction {}, the node may reject it.",
+                        describe_expiry(remaining)
+                    );
+                }
+            }
+        }
+        let transaction_hash = match client.send_block_item(item).await {
+            Ok(hash) => hash,
+            Err(e) if is_duplicate(&e) => self.already_submitted(client, item, e).await?,
+            Err(e) => return Err(e.into()),
+        };
+        if let (Some(receipts), BlockItem::AccountTransaction(tx)) = (&self.receipts, item) {
+            receipts.record_sent(transaction_hash, tx);
+        }
+        if let Ok(mut submitted) = self.submitted.lock() {
+            submitted.push(transaction_hash);
+        }
+        match item {
+            BlockItem::AccountTransaction(tx) => {
+                self.printer.progress_field("submitted", transaction_hash);
+                self.printer.progress_field("nonce", tx.header.nonce);
+            }
+            _ => self.printer.progress_field("submitted", transaction_hash),
+        }
+        Ok(transaction_hash)
+    }
+
+    /// Fail unless the transaction is signed by as many credentials as the
+    /// sender's account threshold requires, and by enough keys of each credential
+    /// for its own threshold. Such a transaction would be rejected by the node.
+    async fn check_signature_count(
+        &self,
+        client: &mut v2::Client,
+        tx: &AccountTransaction<EncodedPayload>,
+    ) -> anyhow::Result<()> {
+        let sender = tx.header.sender;
+        let info = client
+            .get_account_info(&sender.into(), BlockIdentifier::LastFinal)
+            .await?
+            .response;
+        let required = u8::from(info.account_threshold);
+        let signatures = &tx.signature.signatures;
+        for (index, keys) in signatures {
+            let credential = info.account_credentials.get(index).with_context(|| {
+                format!(
+                    "The transaction is signed with credential {}, which account {} does not have.",
+                    index.index, sender
+                )
+            })?;
+            let threshold = match &credential.value {
+                AccountCredentialWithoutProofs::Initial { icdv } => icdv.cred_account.threshold,
+                AccountCredentialWithoutProofs::Normal { cdv, .. } => cdv.cred_key_info.threshold,
+            };
+            anyhow::ensure!(
+                keys.len() >= usize::from(threshold.0),
+                "Credential {} of account {} requires {} signature(s), but the transaction has {}. \
+                 Nothing was submitted.",
+                index.index,
+                sender,
+                threshold.0,
+                keys.len()
+            );
+        }
+        anyhow::ensure!(
+            signatures.len() >= usize::from(required),
+            "Account {} requires signatures from {} credential(s), but the transaction has \
+             signatures from {}. Nothing was submitted.",
+            sender,
+            required,
+            signatures.len()
+        );
+        self.printer.progress_field(
+            "signatures",
+            format!(
+                "{} credential(s) signed, {} required",
+                signatures.len(),
+                required
+            ),
+        );
+        Ok(())
+    }
+
+    /// Handle a block item the node refused as a duplicate. If the node knows
+    /// this very item, it was submitted before and its hash is returned so that
+    /// it is awaited like a new one. Otherwise another transaction took its nonce.
+    async fn already_submitted(
+        &self,
+        client: &mut v2::Client,
+        item: &BlockItem<EncodedPayload>,
+        error: RPCError,
+    ) -> anyhow::Result<TransactionHash> {
+        let hash = item.hash();
+        match client.get_block_item_status(&hash).await {
+            Ok(status) => {
+                let state = match status {
+                    TransactionStatus::Received => "received",
+                    TransactionStatus::Committed(_) => "committed",
+                    TransactionStatus::Finalized(_) => "finalized",
+                };
+                self.printer.progress(format_args!(
+                    "Transaction {} was already submitted and is {}.",
+                    hash, state
+                ));
+                Ok(hash)
+            }
+            Err(_) => {
+                let nonce = match item {
+                    BlockItem::AccountTransaction(tx) => format!(" {}", tx.header.nonce),
+                    _ => String::new(),
+                };
+                Err(anyhow::Error::new(error).context(format!(
+                    "A transaction with this nonce{} was already submitted. Resubmit with a fresh \
+                     nonce if it is not the same transaction.",
+                    nonce
+                )))
+            }
+        }
+    }
+
+    /// Wait until the transaction is finalized and print its outcome.
+    pub async fn wait(
+        &self,
+        client: &mut v2::Client,
+        transaction_hash: &TransactionHash,
+    ) -> anyhow::Result<Finalized> {
+        let (bh, bs) = self.wait_until_finalized(client, transaction_hash).await?;
+        self.printer.progress_field("finalized in", bh);
+        let confirmations = self.settings.confirmations;
+        let (block_height, slot_time) =
+            if self.settings.block_metadata || confirmations > 0 || self.receipts.is_some() {
+                let info = client
+                    .get_block_info(&BlockIdentifier::Given(bh))
+                    .await?
+                    .response;
+                self.printer
+                    .progress_field("block height", info.block_height);
+                self.printer
+                    .progress_field("slot time", info.block_slot_time);
+                self.wait_for_confirmations(client, info.block_height, confirmations)
+                    .await?;
+                (Some(info.block_height), Some(info.block_slot_time))
+            } else {
+                (None, None)
+            };
+        self.print_outcome(&bs);
+        let finalized = Finalized {
+            hash: *transaction_hash,
+            block: BlockMetadata {
+                block_hash: bh,
+                block_height,
+                slot_time,
+            },
+            outcome: self.outcome_summary(&bs),
+            energy_cost: bs.energy_cost,
+            cost: match &bs.details {
+                BlockItemSummaryDetails::AccountTransaction(ad) => ad.cost,
+                _ => Amount::zero(),
+            },
+            nonce: None,
+        };
+        // The transaction is finalized either way, so a receipt that cannot be
+        // written must not turn the command into a failure.
+        if let Some(receipts) = &self.receipts {
+            match receipts.write(&finalized, &bs) {
+                Ok(path) => self
+                    .printer
+                    .progress(format_args!("Receipt written to {}.", path.display())),
+                Err(e) => eprintln!(
+                    "Warning: could not write the receipt of transaction {}: {:#}",
+                    transaction_hash, e
+                ),
+            }
+        }
+        Ok(finalized)
+    }
+
+    /// Wait until the transaction is finalized. If the connection or the stream
+    /// of finalized blocks fails, reconnect and resume by querying the status of
+    /// the transaction, so that a submitted transaction's outcome is not lost.
+    /// If the node does not know the transaction, poll its status and give up
+    /// once it stays unknown; any other error fails at once.
+    async fn wait_until_finalized(
+        &self,
+        client: &mut v2::Client,
+        transaction_hash: &TransactionHash,
+    ) -> anyhow::Result<(BlockHash, BlockItemSummary)> {
+        let mut retries = 0;
+        let mut unknown = 0;
+        loop {
+            let error = match client.wait_until_finalized(transaction_hash).await {
+                Ok(finalized) => return Ok(finalized),
+                // Either the stream of finalized blocks ended or the node does
+                // not know the transaction. Its status tells them apart.
+                Err(QueryError::NotFound) => {
+                    match client.get_block_item_status(transaction_hash).await {
+                        Ok(status) => {
+                            if let Some((bh, bs)) = status.is_finalized() {
+                                return Ok((*bh, bs.clone()));
+                            }
+                            unknown = 0;
+                            tokio::time::sleep(WAIT_RETRY_DELAY).await;
+                            continue;
+                        }
+                        Err(QueryError::NotFound) => {
+                            unknown += 1;
+                            if unknown >= WAIT_RETRIES {
+                                return Err(DroppedTransaction(*transaction_hash).into());
+                            }
+                            tokio::time::sleep(WAIT_RETRY_DELAY).await;
+                            continue;
+                        }
+                        Err(e) => e,
+                    }
+                }
+                Err(e) => e,
+            };
+            if !is_connection_error(&error) {
+                return Err(error).with_context(|| {
+                    format!("Waiting for transaction {} failed.", transaction_hash)
+                });
+            }
+            anyhow::ensure!(
+                retries < WAIT_RETRIES,
+                "Gave up waiting for transaction {} after {} retries: {}",
+                transaction_hash,
+                retries,
+                error
+            );
+            self.use_retry().with_context(|| {
+                format!(
+                    "Waiting for transaction {} failed: {}",
+                    transaction_hash, error
+                )
+            })?;
+            retries += 1;
+            eprintln!(
+                "Waiting for transaction {} failed ({}), retrying in {} seconds.",
+                transaction_hash,
+                error,
+                WAIT_RETRY_DELAY.as_secs()
+            );
+            tokio::time::sleep(WAIT_RETRY_DELAY).await;
+            match v2::Client::new(self.endpoint.clone()).await {
+                Ok(reconnected) => *client = reconnected,
+                Err(e) => eprintln!("Reconnecting failed: {}", e),
+            }
+        }
+    }
+
+    /// Wait until `confirmations` blocks are finalized on top of the block at
+    /// `height`.
+    async fn wait_for_confirmations(
+        &self,
+        client: &mut v2::Client,
+        height: AbsoluteBlockHeight,
+        confirmations: u64,
+    ) -> anyhow::Result<()> {
+        if confirmations == 0 {
+            return Ok(());
+        }
+        let target = height.height + confirmations;
+        // Subscribe before checking the current height so no block is missed.
+        let mut blocks = Box::pin(client.get_finalized_blocks().await?);
+        let last_final = client
+            .get_consensus_info()
+            .await?
+            .last_finalized_block_height;
+        if last_final.height >= target {
+            return Ok(());
+        }
+        self.printer.progress(format_args!(
+            "Waiting for {} confirmation(s).",
+            confirmations
+        ));
+        while let Some(block) = blocks.next().await {
+            if block?.height.height >= target {
+                self.printer.progress(format_args!(
+                    "Transaction has {} confirmation(s).",
+                    confirmations
+                ));
+                return Ok(());
+            }
+        }
+        anyhow::bail!("The node stopped reporting finalized blocks before the confirmations.")
+    }
+
+    /// `success`, or the reason the transaction was rejected.
+    pub fn outcome_summary(&self, bs: &BlockItemSummary) -> String {
+        match bs.is_rejected_account_transaction() {
+            Some(reason) => format!(
+                "rejected: {}",
+                crate::reject::describe(reason, None, self.error_schema.as_ref())
+            ),
+            None => "success".to_string(),
+        }
+    }
+
+    /// Print the relevant effects of a finalized block item.
+    pub fn print_outcome(&self, bs: &BlockItemSummary) {
+        match &bs.details {
+            BlockItemSummaryDetails::AccountTransaction(ad) => {
+                self.printer.progress_field(
+                    "outcome",
+                    self.printer.paint_outcome(&self.outcome_summary(bs)),
+                );
+                self.printer.progress_field("energy", bs.energy_cost);
+                self.printer
+                    .progress_field("cost", format!("{} CCD", ad.cost));
+                match &ad.effects {
+                    AccountTransactionEffects::ModuleDeployed { module_ref } => {
+                        self.printer.progress_field("module ref", module_ref);
+                    }
+                    AccountTransactionEffects::ContractInitialized { data } => {
+                        self.printer.progress_field("contract", data.address);
+                    }
+                    _ => (),
+                };
+            }
+            BlockItemSummaryDetails::AccountCreation(details) => {
+                self.printer.progress(format_args!(
+                    "Account {} created with a {:?} credential (registration ID {}).",
+                    details.address, details.credential_type, details.reg_id
+                ));
+            }
+            BlockItemSummaryDetails::Update(details) => {
+                // an effective time of 0 means the update takes effect immediately
+                let effective = match details.effective_time.seconds {
+                    0 => "immediately".to_string(),
+                    seconds => chrono::NaiveDateTime::from_timestamp_opt(seconds as i64, 0)
+                        .map_or_else(|| seconds.to_string(), |t| format!("at {} UTC", t)),
+                };
+                self.printer.progress(format_args!(
+                    "Chain update {:?} effective {}: {:?}",
+                    details.update_type(),
+                    effective,
+                    details.payload
+                ));
+            }
+        };
+    }
+}
+
+/// A transaction payload with the energy its execution needs. Signed
+/// transactions can be built from it for any nonce and expiry, which makes it
+/// possible to resubmit the same operation.
+#[derive(Debug, Clone)]
+pub struct TransactionPlan {
+    pub payload: Payload,
+    /// Energy for executing the payload, on top of the base cost that covers
+    /// the transaction size and signature checking.
+    pub energy: Energy,
+}
+
+impl TransactionPlan {
+    pub fn new(payload: Payload, energy: Energy) -> Self {
+        Self { payload, energy }
+    }
+
+    /// Deploying a module costs a fixed amount of energy given by its size.
+    pub fn deploy_module(module: concordium_rust_sdk::types::smart_contracts::WasmModule) -> Self {
+        let energy = cost::deploy_module(module.source.size());
+        Self::new(Payload::DeployModule { module }, energy)
+    }
+
+    /// The amount of CCD the transaction sends to a contract.
+    pub fn amount(&self) -> Amount {
+        match &self.payload {
+            Payload::InitContract { payload } => payload.amount,
+            Payload::Update { payload } => payload.amount,
+            _ => Amount::zero(),
+        }
+    }
+
+    /// Build the unsigned transaction for `sender`, to be signed with
+    /// `num_sigs` signatures.
+    pub fn construct(
+        &self,
+        sender: AccountAddress,
+        num_sigs: u32,
+        nonce: Nonce,
+        expiry: TransactionTime,
+    ) -> PreAccountTransaction {
+        construct::make_transaction(
+            sender,
+            nonce,
+            expiry,
+            GivenEnergy::Add {
+                num_sigs,
+                energy: self.energy,
+            },
+            self.payload.clone(),
+        )
+    }
+
+    /// Build and sign the transaction.
+    pub fn sign(
+        &self,
+        keys: &WalletAccount,
+        nonce: Nonce,
+        expiry: TransactionTime,
+    ) -> AccountTransaction<EncodedPayload> {
+        self.construct(keys.address, keys.num_keys(), nonce, expiry)
+            .sign(keys)
+    }
+}
+
+/// An unsigned transaction as written by `--build-unsigned`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsignedTransaction {
+    /// The serialized header and payload in hex.
+    pub body: String,
+    /// The hash the account keys have to sign.
+    pub hash_to_sign: String,
+    pub sender: AccountAddress,
+    pub nonce: Nonce,
+    pub expiry: TransactionTime,
+    pub energy: Energy,
+}
+
+impl From<&PreAccountTransaction> for UnsignedTransaction {
+    fn from(pre: &PreAccountTransaction) -> Self {
+        UnsignedTransaction {
+            body: hex::encode(common::to_bytes(pre)),
+            hash_to_sign: pre.hash_to_sign.to_string(),
+            sender: pre.header.sender,
+            nonce: pre.header.nonce,
+            expiry: pre.header.expiry,
+            energy: pre.header.energy_amount,
+        }
+    }
+}
+
+/// The block a transaction was finalized in. Its height and slot time are only
+/// queried if requested.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockMetadata {
+    pub block_hash: BlockHash,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_height: Option<AbsoluteBlockHeight>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slot_time: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A finalized transaction and the block it landed in.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finalized {
+    pub hash: TransactionHash,
+    #[serde(flatten)]
+    pub block: BlockMetadata,
+    /// `success`, or the reason the transaction was rejected.
+    pub outcome: String,
+    pub energy_cost: Energy,
+    /// The CCD the sender paid for the transaction.
+    pub cost: Amount,
+    /// The nonce the transaction was sent with, for the transactions this
+    /// tool signed. A resubmission may have used another one than planned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<Nonce>,
+}
+
+/// A signed account transaction in the form printed by `--print-tx-json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionJson {
+    sender: AccountAddress,
+    nonce: Nonce,
+    expiry: TransactionTime,
+    energy: Energy,
+    payload: Payload,
+}
+
+/// A transaction the node stopped knowing before it was finalized.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "The node does not know transaction {0}. It was dropped without being finalized, e.g. \
+     because it expired."
+)]
+pub struct DroppedTransaction(pub TransactionHash);
+
+/// Whether the error is the node refusing the transaction because of its nonce
+/// or expiry, or the transaction being dropped before it was finalized, as
+/// opposed to a failure caused by its content.
+pub fn is_nonce_or_expiry_failure(e: &anyhow::Error) -> bool {
+    if e.downcast_ref::<DroppedTransaction>().is_some() {
+        return true;
+    }
+    let rpc_error = match e.downcast_ref::<QueryError>() {
+        Some(QueryError::RPCError(rpc)) => rpc,
+        Some(QueryError::NotFound) => return false,
+        None => match e.downcast_ref::<RPCError>() {
+            Some(rpc) => rpc,
+            None => return false,
+        },
+    };
+    match rpc_error {
+        RPCError::CallError(status) => {
+            let message = status.message().to_lowercase();
+            message.contains("nonce") || message.contains("expir")
+        }
+        _ => false,
+    }
+}
+
+/// Seconds until `expiry`, negative once it has passed.
+fn expires_in(expiry: TransactionTime) -> i64 {
+    expiry.seconds as i64 - chrono::Utc::now().timestamp()
+}
+
+/// How the expiry `remaining` seconds from now reads in a message.
+fn describe_expiry(remaining: i64) -> String {
+    if remaining > 0 {
+        format!("expires in {} seconds", remaining)
+    } else {
+        "has already expired".into()
+    }
+}
+
+/// Whether `e` is a failure of the connection to the node or of a stream, after
+/// which reconnecting may help, rather than an answer of the node.
+fn is_connection_error(e: &QueryError) -> bool {
+    match e {
+        QueryError::RPCError(RPCError::CallError(status)) => matches!(
+            status.code(),
+            v2::Code::Unavailable
+                | v2::Code::Unknown
+                | v2::Code::Cancelled
+                | v2::Code::DeadlineExceeded
+                | v2::Code::Aborted
+                | v2::Code::ResourceExhausted
+        ),
+        _ => false,
+    }
+}
+
+/// Whether the node refused a block item because it already knows it or
+/// another transaction with the same nonce.
+fn is_duplicate(e: &RPCError) -> bool {
+    match e {
+        RPCError::CallError(status) => {
+            status.code() == v2::Code::AlreadyExists
+                || status.message().to_lowercase().contains("duplicate")
+        }
+        _ => false,
+    }
+}
+
+/// Parse a serialized, signed block item given as hex text or raw bytes.
+pub fn parse_block_item(bytes: &[u8]) -> anyhow::Result<BlockItem<EncodedPayload>> {
+    let text = std::str::from_utf8(bytes)
+        .map(str::trim)
+        .unwrap_or_default();
+    let bytes = match hex::decode(text) {
+        Ok(decoded) if !text.is_empty() => decoded,
+        _ => bytes.to_vec(),
+    };
+    let item = common::from_bytes(&mut std::io::Cursor::new(bytes))
+        .context("Could not parse the signed transaction.")?;
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_transfer() -> BlockItem<EncodedPayload> {
+        let account: AccountAddress = "3kBx2h5Y2veb4hZgAJWPrr8RyQESKm5TjzF3ti1QQ4VSYLwK1G"
+            .parse()
+            .unwrap();
+        let keys = crate::accounts::signing_key_account(&"01".repeat(32), account).unwrap();
+        construct::transfer(
+            1,
+            account,
+            Nonce { nonce: 1 },
+            TransactionTime::from_seconds(1_700_000_000),
+            account,
+            Amount::from_micro_ccd(5),
+        )
+        .sign(&keys)
+        .into()
+    }
+
+    #[test]
+    fn parse_block_item_binary() {
+        let item = signed_transfer();
+        let parsed = parse_block_item(&common::to_bytes(&item)).unwrap();
+        assert_eq!(parsed.hash(), item.hash());
+    }
+
+    #[test]
+    fn parse_block_item_hex() {
+        let item = signed_transfer();
+        let text = format!("  {}\n", hex::encode(common::to_bytes(&item)));
+        let parsed = parse_block_item(text.as_bytes()).unwrap();
+        assert_eq!(parsed.hash(), item.hash());
+    }
+
+    #[test]
+    fn parse_block_item_rejects_invalid_input() {
+        assert!(parse_block_item(b"").is_err());
+        assert!(parse_block_item(b"not a transaction").is_err());
+        let mut bytes = common::to_bytes(&signed_transfer());
+        bytes.pop();
+        assert!(parse_block_item(&bytes).is_err());
+    }
+
+    fn limiter(rate: f64, tokens: f64, idle: std::time::Duration) -> RateLimiter {
+        RateLimiter {
+            rate,
+            tokens,
+            last: std::time::Instant::now() - idle,
+        }
+    }
+
+    /// The delays of `n` reservations made right after each other.
+    fn delays(limiter: &mut RateLimiter, n: usize) -> Vec<f64> {
+        (0..n)
+            .map(|_| (limiter.reserve().as_secs_f64() * 10.0).round() / 10.0)
+            .collect()
+    }
+
+    #[test]
+    fn rate_limiter_spaces_reservations() {
+        let mut limiter = limiter(2.0, 1.0, std::time::Duration::ZERO);
+        assert_eq!(delays(&mut limiter, 3), [0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn rate_limiter_bursts_at_most_one_second() {
+        let mut limiter = limiter(2.0, 0.0, std::time::Duration::from_secs(10));
+        assert_eq!(delays(&mut limiter, 3), [0.0, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn rate_limiter_below_one_per_second() {
+        let mut limiter = limiter(0.5, 0.0, std::time::Duration::from_secs(10));
+        assert_eq!(delays(&mut limiter, 2), [0.0, 2.0]);
+    }
+
+    #[test]
+    fn dropped_transactions_are_nonce_or_expiry_failures() {
+        let dropped = anyhow::Error::from(DroppedTransaction(signed_transfer().hash()))
+            .context("Waiting failed.");
+        assert!(is_nonce_or_expiry_failure(&dropped));
+        let other = anyhow::anyhow!("The node does not know transaction 00.");
+        assert!(!is_nonce_or_expiry_failure(&other));
+        assert!(!is_nonce_or_expiry_failure(&QueryError::NotFound.into()));
+    }
+}
@@ -0,0 +1,134 @@
+//! The supply of a token collection, as reported by a view entrypoint.
+use crate::{
+    instance::{self, InvokeSettings},
+    schema,
+};
+use anyhow::Context;
+use concordium_rust_sdk::{
+    smart_contracts::common::{schema::VersionedModuleSchema, Cursor},
+    types::ContractAddress,
+    v2,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Entrypoints that commonly report the supply, tried in this order.
+const SUPPLY_METHODS: [&str; 3] = ["totalSupply", "supply", "view"];
+
+/// The current and maximum supply of a contract's tokens.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Supply {
+    pub contract: ContractAddress,
+    pub method: String,
+    pub total: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<Value>,
+}
+
+/// The entrypoint to query: `requested`, else the first of the usual supply
+/// entrypoints the schema describes.
+pub fn method(
+    schema: &VersionedModuleSchema,
+    contract_name: &str,
+    requested: Option<&str>,
+) -> anyhow::Result<String> {
+    if let Some(requested) = requested {
+        return Ok(requested.to_string());
+    }
+    let entrypoints = schema::entrypoints(schema, contract_name);
+    SUPPLY_METHODS
+        .iter()
+        .find(|m| entrypoints.iter().any(|e| e == *m))
+        .map(|m| m.to_string())
+        .with_context(|| {
+            format!(
+                "Contract {} has none of the entrypoints {}. Name the one reporting the supply \
+                 with --method.",
+                contract_name,
+                SUPPLY_METHODS.join(", ")
+            )
+        })
+}
+
+impl Supply {
+    /// Invoke `method` and find the supply in its decoded return value.
+    pub async fn fetch(
+        client: &mut v2::Client,
+        settings: &InvokeSettings,
+        schema: &VersionedModuleSchema,
+        contract_name: &str,
+        contract: ContractAddress,
+        method: &str,
+    ) -> anyhow::Result<Self> {
+        let rv_schema = schema.get_receive_return_value_schema(contract_name, method)?;
+        let receive_name = schema::receive_name(contract_name, method, Some(schema))?;
+        let bytes = instance::invoke(
+            client,
+            settings,
+            contract,
+            receive_name,
+            Default::default(),
+            Some(schema),
+        )
+        .await?;
+        let value = rv_schema
+            .to_json(&mut Cursor::new(&bytes[..]))
+            .context("Could not decode the return value.")?;
+        let (total, max) = supply_fields(&value);
+        anyhow::ensure!(
+            total.is_some() || max.is_some(),
+            "The return value of {} reports no supply: {}",
+            method,
+            value
+        );
+        Ok(Supply {
+            contract,
+            method: method.to_string(),
+            total,
+            max,
+        })
+    }
+
+    pub fn print(&self) {
+        println!("Supply of {} (from {}):", self.contract, self.method);
+        let show = |v: &Option<Value>| match v {
+            Some(Value::String(s)) => s.clone(),
+            Some(v) => v.to_string(),
+            None => "not reported".to_string(),
+        };
+        println!("  current: {}", show(&self.total));
+        println!("  maximum: {}", show(&self.max));
+    }
+}
+
+/// The current and maximum supply in a return value: the value itself if it
+/// is a number, else fields whose names mention the supply, e.g.
+/// `total_supply` and `max_supply`, searched in nested structs as well.
+fn supply_fields(value: &Value) -> (Option<Value>, Option<Value>) {
+    if value.is_number() {
+        return (Some(value.clone()), None);
+    }
+    let Value::Object(fields) = value else {
+        return (None, None);
+    };
+    let (mut total, mut max) = (None, None);
+    for (name, field) in fields {
+        let name = name.to_lowercase();
+        let is_amount = field.is_number() || field.is_string();
+        if is_amount && name.contains("max") {
+            max = max.or_else(|| Some(field.clone()));
+        } else if is_amount
+            && ["supply", "minted", "total", "count"]
+                .iter()
+                .any(|k| name.contains(k))
+        {
+            total = total.or_else(|| Some(field.clone()));
+        } else if field.is_object() {
+            let (nested_total, nested_max) = supply_fields(field);
+            total = total.or(nested_total);
+            max = max.or(nested_max);
+        }
+    }
+    (total, max)
+}
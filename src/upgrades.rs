@@ -0,0 +1,144 @@
+//! The history of the modules an upgradeable contract has run, found by
+//! scanning finalized blocks for its `Upgraded` events.
+use crate::chain::{self, BlockSelector};
+use chrono::{DateTime, Utc};
+use concordium_rust_sdk::{
+    types::{
+        hashes::{BlockHash, TransactionHash},
+        smart_contracts::{ContractTraceElement, ModuleReference},
+        AccountTransactionEffects, BlockItemSummaryDetails, ContractAddress,
+    },
+    v2,
+};
+use serde::Serialize;
+
+/// One upgrade of the contract's module.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Upgrade {
+    pub height: u64,
+    pub block: BlockHash,
+    pub slot_time: DateTime<Utc>,
+    pub transaction: TransactionHash,
+    pub from: ModuleReference,
+    pub to: ModuleReference,
+}
+
+/// The upgrades of a contract within a range of blocks.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct History {
+    pub contract: ContractAddress,
+    pub from_height: u64,
+    pub to_height: u64,
+    /// The module at the first block of the range, if the contract existed.
+    pub initial_module: Option<ModuleReference>,
+    pub upgrades: Vec<Upgrade>,
+}
+
+impl History {
+    /// Scan every block from `from` to `to` for upgrades of `contract`. This
+    /// queries each block, so narrow the range on long chains.
+    pub async fn scan(
+        client: &mut v2::Client,
+        contract: ContractAddress,
+        from: &BlockSelector,
+        to: &BlockSelector,
+        verbose: bool,
+    ) -> anyhow::Result<Self> {
+        let heights = chain::height_range(client, from, to).await?;
+        let (from_height, to_height) = (*heights.start(), *heights.end());
+        let first = BlockSelector::Height(from_height).resolve(client).await?;
+        let initial_module = match client.get_instance_info(contract, &first).await {
+            Ok(info) => Some(info.response.source_module()),
+            Err(e) if e.is_not_found() => None,
+            Err(e) => return Err(e.into()),
+        };
+        let mut upgrades = Vec::new();
+        for height in heights {
+            if verbose && (height - from_height) % 1000 == 0 {
+                eprintln!("Scanning block {} of {}.", height, to_height);
+            }
+            let (block, summaries) = chain::block_items(client, height).await?;
+            let mut found = Vec::new();
+            for summary in &summaries {
+                let BlockItemSummaryDetails::AccountTransaction(details) = &summary.details else {
+                    continue;
+                };
+                let AccountTransactionEffects::ContractUpdateIssued { effects } = &details.effects
+                else {
+                    continue;
+                };
+                for element in effects {
+                    if let ContractTraceElement::Upgraded { address, from, to } = element {
+                        if *address == contract {
+                            found.push((summary.hash, *from, *to));
+                        }
+                    }
+                }
+            }
+            if found.is_empty() {
+                continue;
+            }
+            let slot_time = client
+                .get_block_info(&block)
+                .await?
+                .response
+                .block_slot_time;
+            upgrades.extend(found.into_iter().map(|(transaction, from, to)| Upgrade {
+                height,
+                block,
+                slot_time,
+                transaction,
+                from,
+                to,
+            }));
+        }
+        Ok(Self {
+            contract,
+            from_height,
+            to_height,
+            initial_module,
+            upgrades,
+        })
+    }
+
+    pub fn print(&self) {
+        println!(
+            "Modules of contract {} from height {} to {}:",
+            self.contract, self.from_height, self.to_height
+        );
+        match &self.initial_module {
+            Some(module) => println!("  at height {}: {}", self.from_height, module),
+            None => println!(
+                "  at height {}: the contract did not exist",
+                self.from_height
+            ),
+        }
+        for upgrade in &self.upgrades {
+            println!(
+                "  {} (height {}): upgraded from {} to {} in transaction {}",
+                upgrade.slot_time, upgrade.height, upgrade.from, upgrade.to, upgrade.transaction
+            );
+        }
+        if self.upgrades.is_empty() {
+            println!("  no upgrades");
+        }
+    }
+
+    pub fn print_csv(&self) -> anyhow::Result<()> {
+        crate::output::print_csv(
+            &["height", "slot_time", "block", "transaction", "from", "to"],
+            self.upgrades.iter().map(|u| {
+                vec![
+                    u.height.to_string(),
+                    u.slot_time.to_rfc3339(),
+                    u.block.to_string(),
+                    u.transaction.to_string(),
+                    u.from.to_string(),
+                    u.to.to_string(),
+                ]
+            }),
+        )
+    }
+}
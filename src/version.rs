@@ -0,0 +1,58 @@
+//! Versions of the tool, the SDK and the connected node.
+use concordium_rust_sdk::v2;
+use serde::Serialize;
+use std::time::Duration;
+
+/// How long to wait for the node before reporting it as unreachable.
+const NODE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionInfo {
+    pub tool: &'static str,
+    pub sdk: &'static str,
+    /// Software version of the node, if it could be reached.
+    pub node: Option<String>,
+    /// Protocol version of the chain, if the node could be reached.
+    pub protocol: Option<u64>,
+}
+
+impl VersionInfo {
+    /// Collect the versions, querying the node at `endpoint` if it is
+    /// reachable.
+    pub async fn collect(endpoint: v2::Endpoint) -> Self {
+        let (node, protocol) =
+            match tokio::time::timeout(NODE_TIMEOUT, node_versions(endpoint)).await {
+                Ok(Ok((node, protocol))) => (Some(node), Some(protocol)),
+                _ => (None, None),
+            };
+        VersionInfo {
+            tool: env!("CARGO_PKG_VERSION"),
+            sdk: env!("CONCORDIUM_SDK_VERSION"),
+            node,
+            protocol,
+        }
+    }
+
+    pub fn print(&self) {
+        println!("tool:             {}", self.tool);
+        println!("concordium SDK:   {}", self.sdk);
+        match (&self.node, self.protocol) {
+            (Some(node), Some(protocol)) => {
+                println!("node:             {}", node);
+                println!("protocol version: {}", protocol);
+            }
+            _ => println!("node:             unreachable"),
+        }
+    }
+}
+
+async fn node_versions(endpoint: v2::Endpoint) -> anyhow::Result<(String, u64)> {
+    let mut client = v2::Client::new(endpoint).await?;
+    let node_info = client.get_node_info().await?;
+    let consensus_info = client.get_consensus_info().await?;
+    Ok((
+        node_info.version.to_string(),
+        u64::from(consensus_info.protocol_version),
+    ))
+}